@@ -0,0 +1,8 @@
+/* Last erase page of the linked flash, holds the BootConfig of
+   src/config.rs. Taken from the memory map so it follows the part the
+   firmware is linked for, the 64 KB GD32VF103C8 with memory-c8.x. */
+_boot_config_page = ORIGIN(REGION_TEXT) + LENGTH(REGION_TEXT) - 1024;
+
+/* Erasing the page on a config save must not wipe the end of the program */
+ASSERT(LOADADDR(.data) + SIZEOF(.data) <= _boot_config_page, "
+ERROR(weather_station): the program reaches into the last flash page, which holds the boot config");
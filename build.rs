@@ -0,0 +1,13 @@
+fn main() {
+    // Places the boot config page at the end of the linked flash and checks
+    // that the program stays clear of it
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+    println!("cargo:rustc-link-search={}", manifest_dir);
+    println!("cargo:rustc-link-arg-bins=-Tboot_config.x");
+
+    // defmt places its log strings with its own linker script, only needed
+    // when the feature is on
+    if std::env::var_os("CARGO_FEATURE_DEFMT").is_some() {
+        println!("cargo:rustc-link-arg-bins=-Tdefmt.x");
+    }
+}
@@ -0,0 +1,151 @@
+use core::cell::RefCell;
+use embedded_hal::blocking::delay::DelayUs;
+use longan_nano::hal::gpio::gpioa::PA4;
+use longan_nano::hal::gpio::Analog;
+use longan_nano::hal::pac;
+use riscv::interrupt::{free, Mutex};
+
+use crate::types::SensorReading;
+
+#[cfg(feature = "sensor_mux")]
+compile_error!("ntc_fallback uses PA4, which is an address pin of the sensor multiplexer");
+
+/// B constant of the thermistor, from its datasheet
+pub const NTC_BETA: u32 = 3950;
+
+/// Resistance of the thermistor at 25°C
+pub const NTC_R_NOMINAL_OHM: u32 = 10_000;
+
+/// Resistor between 3.3 V and PA4, the thermistor is between PA4 and ground
+pub const NTC_R_SERIES_OHM: u32 = 10_000;
+
+// Full scale of the 12-bit ADC
+const ADC_MAX: u16 = 4095;
+
+// Temperature the nominal resistance is given at, in kelvin
+const NOMINAL_KELVIN: f32 = 298.15;
+const KELVIN_OFFSET: f32 = 273.15;
+
+// ADC channel of PA4
+const NTC_CHANNEL: u32 = 4;
+
+// RCU_APB2EN ADC0 clock enable
+const APB2EN_ADC0EN: u32 = 1 << 9;
+
+// RCU_CFG0 ADCPSC[1:0] = 11 with ADCPSC[2] = 0: APB2 / 8, 10 MHz. The ADC
+// clock must stay below 14 MHz.
+const CFG0_ADCPSC_MASK: u32 = (0b11 << 14) | (1 << 28);
+const CFG0_ADCPSC_DIV8: u32 = 0b11 << 14;
+
+// ADC_CTL1 bits
+const CTL1_ADCON: u32 = 1 << 0;
+const CTL1_CLB: u32 = 1 << 2;
+const CTL1_RSTCLB: u32 = 1 << 3;
+// ETSRC = 111 with ETERC: regular conversions started by SWRCST
+const CTL1_ETSRC_SWRCST: u32 = 0b111 << 17;
+const CTL1_ETERC: u32 = 1 << 20;
+const CTL1_SWRCST: u32 = 1 << 22;
+
+// ADC_SAMPT1 SPT4 = 111, 239.5 cycles for the high impedance divider
+const SAMPT1_SPT4_239_5: u32 = 0b111 << 12;
+
+// ADC_STAT end of conversion flag
+const STAT_EOC: u32 = 1 << 1;
+
+// Time from ADCON to the ADC being ready for calibration
+const POWER_UP_US: u32 = 10;
+
+// Thermistor read while the DHT keeps failing, None without the thermistor
+pub static NTC_SENSOR: Mutex<RefCell<Option<NtcSensor>>> = Mutex::new(RefCell::new(None));
+
+/// Temperature (°C) of the thermistor from a 12-bit ADC reading of the
+/// divider, using the B-parameter form of the Steinhart-Hart equation.
+/// NaN when the reading is at either end of the scale, an open or
+/// shorted thermistor.
+pub fn ntc_to_celsius(raw_adc: u16, beta: u32, r_nominal_ohm: u32, r_series_ohm: u32) -> f32 {
+    if raw_adc == 0 || raw_adc >= ADC_MAX {
+        return f32::NAN;
+    }
+
+    let r_ntc = r_series_ohm as f32 * raw_adc as f32 / (ADC_MAX - raw_adc) as f32;
+    let inv_kelvin =
+        1.0 / NOMINAL_KELVIN + libm::logf(r_ntc / r_nominal_ohm as f32) / beta as f32;
+    1.0 / inv_kelvin - KELVIN_OFFSET
+}
+
+/// Thermistor on PA4 read through channel 4 of ADC0, one conversion at a
+/// time started by software
+pub struct NtcSensor {
+    _adc: pac::ADC0,
+    _pin: PA4<Analog>,
+}
+
+impl NtcSensor {
+    /// Powers up and calibrates the ADC
+    pub fn new(adc: pac::ADC0, pin: PA4<Analog>, delay: &mut impl DelayUs<u32>) -> Self {
+        // RCU is owned by the clock setup, only the ADC0 clock gate and prescaler are touched here
+        let rcu = unsafe { &*pac::RCU::ptr() };
+        rcu.cfg0.modify(|r, w| unsafe {
+            w.bits((r.bits() & !CFG0_ADCPSC_MASK) | CFG0_ADCPSC_DIV8)
+        });
+        rcu.apb2en.modify(|r, w| unsafe { w.bits(r.bits() | APB2EN_ADC0EN) });
+
+        adc.sampt1.write(|w| unsafe { w.bits(SAMPT1_SPT4_239_5) });
+        // One conversion in the regular sequence, the thermistor channel
+        adc.rsq0.write(|w| unsafe { w.bits(0) });
+        adc.rsq2.write(|w| unsafe { w.bits(NTC_CHANNEL) });
+
+        let ctl1 = CTL1_ADCON | CTL1_ETSRC_SWRCST | CTL1_ETERC;
+        adc.ctl1.write(|w| unsafe { w.bits(ctl1) });
+        delay.delay_us(POWER_UP_US);
+
+        adc.ctl1.write(|w| unsafe { w.bits(ctl1 | CTL1_RSTCLB) });
+        while adc.ctl1.read().bits() & CTL1_RSTCLB != 0 {}
+        adc.ctl1.write(|w| unsafe { w.bits(ctl1 | CTL1_CLB) });
+        while adc.ctl1.read().bits() & CTL1_CLB != 0 {}
+
+        NtcSensor {
+            _adc: adc,
+            _pin: pin,
+        }
+    }
+
+    /// One conversion, takes about 25 µs
+    pub fn read_raw(&mut self) -> u16 {
+        let adc = self.regs();
+        adc.stat.modify(|r, w| unsafe { w.bits(r.bits() & !STAT_EOC) });
+        adc.ctl1.modify(|r, w| unsafe { w.bits(r.bits() | CTL1_SWRCST) });
+        while adc.stat.read().bits() & STAT_EOC == 0 {}
+        adc.rdata.read().bits() as u16
+    }
+
+    /// Temperature reading, None when the thermistor is open or shorted
+    pub fn read(&mut self) -> Option<SensorReading> {
+        let celsius = ntc_to_celsius(
+            self.read_raw(),
+            NTC_BETA,
+            NTC_R_NOMINAL_OHM,
+            NTC_R_SERIES_OHM,
+        );
+        if celsius.is_nan() {
+            None
+        } else {
+            Some(SensorReading::from_ntc(celsius))
+        }
+    }
+
+    fn regs(&self) -> &pac::adc0::RegisterBlock {
+        unsafe { &*pac::ADC0::ptr() }
+    }
+}
+
+/// Reads the thermistor, None when there is none or it is disconnected
+pub fn read_fallback() -> Option<SensorReading> {
+    free(|cs| {
+        NTC_SENSOR
+            .borrow(*cs)
+            .borrow_mut()
+            .as_mut()
+            .and_then(NtcSensor::read)
+    })
+}
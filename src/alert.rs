@@ -0,0 +1,228 @@
+use core::cell::RefCell;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::fmt::{self, Write};
+use embedded_hal::digital::v2::OutputPin;
+use heapless::{String, Vec};
+use longan_nano::hal::gpio::gpioa::PA8;
+use longan_nano::hal::gpio::gpiob::PB9;
+use longan_nano::hal::gpio::{Output, PushPull};
+use longan_nano::led::{Led, RED};
+use riscv::interrupt::{free, Mutex};
+
+use crate::calibration::civil_from_days;
+use crate::collections::PowerOfTwoRingBuffer;
+use crate::config::{ThresholdKind, HUM_HIGH_THRESHOLD, TEMP_HIGH_THRESHOLD};
+use crate::util::fmt::{push_tenths, to_tenths};
+
+// Alert outputs. PA5-PA7 would be the natural choice but they are used by
+// the LCD's SPI0, so the on-board red LED and free port B pins are used.
+pub static ALERT_LED: Mutex<RefCell<Option<RED>>> = Mutex::new(RefCell::new(None));
+pub static RELAY_PIN: Mutex<RefCell<Option<PB9<Output<PushPull>>>>> =
+    Mutex::new(RefCell::new(None));
+
+// High while TEMP_HIGH_THRESHOLD or HUM_HIGH_THRESHOLD is exceeded, for an external relay
+pub static ALERT_PIN: Mutex<RefCell<Option<PA8<Output<PushPull>>>>> =
+    Mutex::new(RefCell::new(None));
+
+// Mirrors ALERT_PIN for the display loop
+pub static ALERT_ACTIVE: AtomicBool = AtomicBool::new(false);
+
+// Registered alert outputs, called after a reading when the alert state changes
+pub static ALERT_DISPATCHER: Mutex<RefCell<AlertDispatcher>> =
+    Mutex::new(RefCell::new(AlertDispatcher::new()));
+
+// Finished alarms, written after every reading
+pub static ALARM_LOG: Mutex<RefCell<AlarmLog>> = Mutex::new(RefCell::new(AlarmLog::new()));
+
+// Number of finished alarms kept
+const ALARM_LOG_LEN: usize = 64;
+
+/// Calls every registered output with `true` when an alert becomes active
+/// and with `false` when it clears
+pub struct AlertDispatcher {
+    outputs: Vec<fn(bool), 8>,
+}
+
+impl AlertDispatcher {
+    pub const fn new() -> Self {
+        AlertDispatcher { outputs: Vec::new() }
+    }
+
+    /// Adds an output, returns it back if all 8 slots are taken
+    pub fn register(&mut self, output: fn(bool)) -> Result<(), fn(bool)> {
+        self.outputs.push(output)
+    }
+
+    pub fn dispatch(&self, active: bool) {
+        for output in self.outputs.iter() {
+            output(active);
+        }
+    }
+}
+
+/// Lights the on-board red LED
+pub fn led_alert(active: bool) {
+    free(|cs| {
+        if let Some(ref mut led) = *ALERT_LED.borrow(*cs).borrow_mut() {
+            if active {
+                led.on();
+            } else {
+                led.off();
+            }
+        }
+    });
+}
+
+/// Drives a relay on PB9
+pub fn relay_alert(active: bool) {
+    free(|cs| {
+        if let Some(ref mut pin) = *RELAY_PIN.borrow(*cs).borrow_mut() {
+            if active {
+                pin.set_high().unwrap();
+            } else {
+                pin.set_low().unwrap();
+            }
+        }
+    });
+}
+
+/// Drives ALERT_PIN high when the reading is above either high threshold
+/// and low otherwise
+pub fn update_alert_pin(temperature: f32, humidity: f32) {
+    let active = temperature > TEMP_HIGH_THRESHOLD || humidity > HUM_HIGH_THRESHOLD;
+    ALERT_ACTIVE.store(active, Ordering::Relaxed);
+    free(|cs| {
+        if let Some(ref mut pin) = *ALERT_PIN.borrow(*cs).borrow_mut() {
+            if active {
+                pin.set_high().unwrap();
+            } else {
+                pin.set_low().unwrap();
+            }
+        }
+    });
+}
+
+/// One alarm from start to end
+#[derive(Clone, Copy, Debug)]
+pub struct AlarmEntry {
+    pub start_s: u32,
+    pub end_s: u32,
+    pub kind: ThresholdKind,
+    // Value of the violated threshold
+    pub threshold_exceeded: f32,
+    // Largest distance of a reading past the threshold during the alarm
+    pub max_deviation: f32,
+}
+
+impl AlarmEntry {
+    /// Reading furthest past the threshold
+    pub fn peak(&self) -> f32 {
+        if self.kind.is_lower_limit() {
+            self.threshold_exceeded - self.max_deviation
+        } else {
+            self.threshold_exceeded + self.max_deviation
+        }
+    }
+}
+
+/// Log of the latest alarms and how far past the threshold they went.
+/// Timestamps are Unix time in seconds, or uptime while the clock is not set.
+pub struct AlarmLog {
+    entries: PowerOfTwoRingBuffer<AlarmEntry, ALARM_LOG_LEN>,
+    active: Option<AlarmEntry>,
+}
+
+impl AlarmLog {
+    pub const fn new() -> Self {
+        AlarmLog {
+            entries: PowerOfTwoRingBuffer::new(),
+            active: None,
+        }
+    }
+
+    /// Starts an alarm for the violated threshold. Does nothing if an
+    /// alarm is already active.
+    pub fn alarm_started(
+        &mut self,
+        timestamp_s: u32,
+        reading: f32,
+        kind: ThresholdKind,
+        threshold: f32,
+    ) {
+        if self.active.is_some() {
+            return;
+        }
+        self.active = Some(AlarmEntry {
+            start_s: timestamp_s,
+            end_s: timestamp_s,
+            kind,
+            threshold_exceeded: threshold,
+            max_deviation: 0.0,
+        });
+        self.update(reading);
+    }
+
+    /// Tracks the peak exceedance of the active alarm
+    pub fn update(&mut self, reading: f32) {
+        if let Some(ref mut entry) = self.active {
+            let deviation = if entry.kind.is_lower_limit() {
+                entry.threshold_exceeded - reading
+            } else {
+                reading - entry.threshold_exceeded
+            };
+            entry.max_deviation = entry.max_deviation.max(deviation);
+        }
+    }
+
+    /// Ends the active alarm and moves it to the log, overwriting the
+    /// oldest entry when the log is full
+    pub fn alarm_ended(&mut self, timestamp_s: u32) {
+        if let Some(mut entry) = self.active.take() {
+            entry.end_s = timestamp_s;
+            self.entries.push(entry);
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active.is_some()
+    }
+
+    /// Prints one line per alarm, oldest first, e.g.
+    /// `[2024-01-15 14:30 - 14:45] MAX_T exceeded by 3.2°C (peak: 29.2°C)`
+    pub fn print_log(&self, uart: &mut impl Write) -> fmt::Result {
+        self.print_latest(uart, ALARM_LOG_LEN)
+    }
+
+    /// Prints the latest `count` alarms like print_log, up to 75 bytes each
+    pub fn print_latest(&self, uart: &mut impl Write, count: usize) -> fmt::Result {
+        let skip = self.entries.len().saturating_sub(count);
+        for entry in self.entries.iter().skip(skip) {
+            let (year, month, day) = civil_from_days(entry.start_s / 86400);
+            let start_min = entry.start_s % 86400 / 60;
+            let end_min = entry.end_s % 86400 / 60;
+            write!(
+                uart,
+                "[{:04}-{:02}-{:02} {:02}:{:02} - {:02}:{:02}] {} exceeded by ",
+                year,
+                month,
+                day,
+                start_min / 60,
+                start_min % 60,
+                end_min / 60,
+                end_min % 60,
+                entry.kind.label()
+            )?;
+
+            // At most 28 bytes, `128.0°C (peak: -128.0°C)\r\n`
+            let mut values: String<32> = String::new();
+            let _ = push_tenths(&mut values, to_tenths(entry.max_deviation));
+            let _ = values.push_str(entry.kind.unit());
+            let _ = values.push_str(" (peak: ");
+            let _ = push_tenths(&mut values, to_tenths(entry.peak()));
+            let _ = values.push_str(entry.kind.unit());
+            let _ = values.push_str(")\r\n");
+            uart.write_str(&values)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,141 @@
+use core::cell::RefCell;
+use core::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use embedded_hal::blocking::delay::DelayUs;
+use embedded_hal::digital::v2::InputPin;
+use longan_nano::hal::gpio::gpioa::PA3;
+use longan_nano::hal::gpio::gpiob::PB7;
+use longan_nano::hal::gpio::{Analog, Input, PullUp};
+use longan_nano::hal::pac;
+use riscv::interrupt::{free, Mutex};
+
+use crate::ui::battery::battery_percent;
+
+#[cfg(feature = "ambient_light")]
+compile_error!("battery uses PA3, which is the LDR of ambient_light");
+
+/// Seconds between battery samples, the cell voltage changes slowly
+pub const BATTERY_SAMPLE_INTERVAL: u32 = 10;
+
+// Charge level (%) and charger state of the latest sample
+static BATTERY_PERCENT: AtomicU8 = AtomicU8::new(NO_SAMPLE);
+static CHARGING: AtomicBool = AtomicBool::new(false);
+
+// BATTERY_PERCENT before the first sample
+const NO_SAMPLE: u8 = u8::MAX;
+
+// The cell is halved by two equal resistors to stay below the 3.3 V reference
+const DIVIDER_RATIO: u32 = 2;
+const ADC_REF_MV: u32 = 3300;
+const ADC_MAX: u32 = 4095;
+
+// ADC channel of PA3
+const BATTERY_CHANNEL: u32 = 3;
+
+// RCU_APB2EN ADC1 clock enable
+const APB2EN_ADC1EN: u32 = 1 << 10;
+
+// RCU_CFG0 ADCPSC[1:0] = 11 with ADCPSC[2] = 0: APB2 / 8, 10 MHz
+const CFG0_ADCPSC_MASK: u32 = (0b11 << 14) | (1 << 28);
+const CFG0_ADCPSC_DIV8: u32 = 0b11 << 14;
+
+// ADC_CTL1 bits
+const CTL1_ADCON: u32 = 1 << 0;
+const CTL1_CLB: u32 = 1 << 2;
+const CTL1_RSTCLB: u32 = 1 << 3;
+// ETSRC = 111 with ETERC: regular conversions started by SWRCST
+const CTL1_ETSRC_SWRCST: u32 = 0b111 << 17;
+const CTL1_ETERC: u32 = 1 << 20;
+const CTL1_SWRCST: u32 = 1 << 22;
+
+// ADC_SAMPT1 SPT3 = 111, 239.5 cycles for the 50 kΩ source of the divider
+const SAMPT1_SPT3_239_5: u32 = 0b111 << 9;
+
+// ADC_STAT end of conversion flag
+const STAT_EOC: u32 = 1 << 1;
+
+// Time from ADCON to the ADC being ready for calibration
+const POWER_UP_US: u32 = 10;
+
+// Battery monitor sampled by the main loop, None before init
+pub static VOLTAGE_MONITOR: Mutex<RefCell<Option<VoltageMonitor>>> =
+    Mutex::new(RefCell::new(None));
+
+/// Li-Ion cell voltage on PA3 through a 100 kΩ/100 kΩ divider, read with
+/// channel 3 of ADC1, and the open-drain CHRG output of a TP4056 charger
+/// on PB7, low while charging.
+pub struct VoltageMonitor {
+    _adc: pac::ADC1,
+    _pin: PA3<Analog>,
+    charge_pin: PB7<Input<PullUp>>,
+}
+
+impl VoltageMonitor {
+    /// Powers up and calibrates the ADC
+    pub fn new(
+        adc: pac::ADC1,
+        pin: PA3<Analog>,
+        charge_pin: PB7<Input<PullUp>>,
+        delay: &mut impl DelayUs<u32>,
+    ) -> Self {
+        // RCU is owned by the clock setup, only the ADC1 clock gate and prescaler are touched here
+        let rcu = unsafe { &*pac::RCU::ptr() };
+        rcu.cfg0.modify(|r, w| unsafe {
+            w.bits((r.bits() & !CFG0_ADCPSC_MASK) | CFG0_ADCPSC_DIV8)
+        });
+        rcu.apb2en.modify(|r, w| unsafe { w.bits(r.bits() | APB2EN_ADC1EN) });
+
+        adc.sampt1.write(|w| unsafe { w.bits(SAMPT1_SPT3_239_5) });
+        adc.rsq0.write(|w| unsafe { w.bits(0) });
+        adc.rsq2.write(|w| unsafe { w.bits(BATTERY_CHANNEL) });
+
+        let ctl1 = CTL1_ADCON | CTL1_ETSRC_SWRCST | CTL1_ETERC;
+        adc.ctl1.write(|w| unsafe { w.bits(ctl1) });
+        delay.delay_us(POWER_UP_US);
+
+        adc.ctl1.write(|w| unsafe { w.bits(ctl1 | CTL1_RSTCLB) });
+        while adc.ctl1.read().bits() & CTL1_RSTCLB != 0 {}
+        adc.ctl1.write(|w| unsafe { w.bits(ctl1 | CTL1_CLB) });
+        while adc.ctl1.read().bits() & CTL1_CLB != 0 {}
+
+        VoltageMonitor {
+            _adc: adc,
+            _pin: pin,
+            charge_pin,
+        }
+    }
+
+    /// Cell voltage in mV
+    pub fn read_mv(&mut self) -> u32 {
+        let adc = unsafe { &*pac::ADC1::ptr() };
+        adc.stat.modify(|r, w| unsafe { w.bits(r.bits() & !STAT_EOC) });
+        adc.ctl1.modify(|r, w| unsafe { w.bits(r.bits() | CTL1_SWRCST) });
+        while adc.stat.read().bits() & STAT_EOC == 0 {}
+        let raw = adc.rdata.read().bits() & ADC_MAX;
+        raw * ADC_REF_MV * DIVIDER_RATIO / ADC_MAX
+    }
+
+    pub fn is_charging(&self) -> bool {
+        self.charge_pin.is_low().unwrap_or(false)
+    }
+}
+
+/// Reads the battery into the level shown by the icon, called every
+/// BATTERY_SAMPLE_INTERVAL
+pub fn sample() {
+    free(|cs| {
+        if let Some(monitor) = VOLTAGE_MONITOR.borrow(*cs).borrow_mut().as_mut() {
+            let percent = battery_percent(monitor.read_mv() as f32 / 1000.0);
+            BATTERY_PERCENT.store(percent, Ordering::Relaxed);
+            CHARGING.store(monitor.is_charging(), Ordering::Relaxed);
+        }
+    });
+}
+
+/// Charge level (%) and whether the cell is charging, None before the
+/// first sample
+pub fn battery_level() -> Option<(u8, bool)> {
+    match BATTERY_PERCENT.load(Ordering::Relaxed) {
+        NO_SAMPLE => None,
+        percent => Some((percent, CHARGING.load(Ordering::Relaxed))),
+    }
+}
@@ -0,0 +1,133 @@
+use core::cell::RefCell;
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use longan_nano::hal::pac;
+use riscv::interrupt::{free, Mutex};
+
+use crate::config::{BUZZ_HUM_THRESHOLD, BUZZ_TEMP_THRESHOLD};
+use crate::input::button::SAMPLE_RATE_HZ;
+
+/// Length of a beep in TIMER5 ticks, 500 ms at 100 Hz
+pub const BUZZ_TICKS: u32 = 500 * SAMPLE_RATE_HZ / 1000;
+
+// Ticks left of the current beep, counted down by the TIMER5 interrupt
+pub static BUZZ_TICKS_REMAINING: AtomicU32 = AtomicU32::new(0);
+
+// Set while the buzzer must stay silent, e.g. during the self test
+static MUTED: AtomicBool = AtomicBool::new(false);
+
+// Whether the latest reading was past a buzz threshold, to beep only on crossings
+static EXCEEDED: AtomicBool = AtomicBool::new(false);
+
+// Passive piezo on PB8, None until set up in main
+pub static BUZZER: Mutex<RefCell<Option<PwmBuzzer>>> = Mutex::new(RefCell::new(None));
+
+// PWM period in timer ticks, 2 kHz at the 1 MHz counter clock
+const PWM_PERIOD: u16 = 500;
+
+// Timer clock is the 80 MHz system clock: APB1 runs at 40 MHz and timers on
+// a divided APB get twice the bus clock
+const TIMER_CLOCK_MHZ: u16 = 80;
+
+// TIMER_CHCTL1 CH2COMCTL = 110 (PWM mode 0) and CH2COMSEN (shadow register)
+const CHCTL1_CH2_PWM0: u16 = (0b110 << 4) | (1 << 3);
+
+// TIMER_CHCTL2 channel 2 output enable
+const CHCTL2_CH2EN: u16 = 1 << 8;
+
+// TIMER_CTL0 counter enable and TIMER_SWEVG update event
+const CTL0_CEN: u16 = 1 << 0;
+const SWEVG_UPG: u16 = 1 << 0;
+
+// RCU_APB1EN TIMER3 clock enable
+const APB1EN_TIMER3EN: u32 = 1 << 2;
+
+/// Passive piezo buzzer driven by a 2 kHz square wave from TIMER3 channel
+/// 2 on PB8. Silent at zero duty cycle.
+///
+/// PA6 with TIMER0 channel 0 doesn't work on the Longan Nano: PA6 is the
+/// LCD's SPI MISO, claimed by `lcd_pins!`, and has no TIMER0 channel, which
+/// is on PA8, the alert output. PB8 is free and TIMER3 is otherwise unused.
+/// The beep is timed by the 100 Hz TIMER5 tick, TIMER1 only ticks once a
+/// second.
+pub struct PwmBuzzer {
+    _timer: pac::TIMER3,
+}
+
+impl PwmBuzzer {
+    /// PB8 must be in alternate push-pull mode. Starts silent.
+    pub fn new(timer: pac::TIMER3) -> Self {
+        // RCU is owned by the clock setup, only the TIMER3 clock gate is touched here
+        let rcu = unsafe { &*pac::RCU::ptr() };
+        rcu.apb1en.modify(|r, w| unsafe { w.bits(r.bits() | APB1EN_TIMER3EN) });
+
+        timer.psc.write(|w| unsafe { w.bits(TIMER_CLOCK_MHZ - 1) });
+        timer.car.write(|w| unsafe { w.bits(PWM_PERIOD - 1) });
+        timer.ch2cv.write(|w| unsafe { w.bits(0) });
+        timer.chctl1_output().write(|w| unsafe { w.bits(CHCTL1_CH2_PWM0) });
+        timer.chctl2.write(|w| unsafe { w.bits(CHCTL2_CH2EN) });
+        // Load the prescaler now rather than at the first overflow
+        timer.swevg.write(|w| unsafe { w.bits(SWEVG_UPG) });
+        timer.ctl0.write(|w| unsafe { w.bits(CTL0_CEN) });
+
+        PwmBuzzer { _timer: timer }
+    }
+
+    /// Square wave at 50% duty cycle when on, zero duty cycle when off
+    pub fn set_on(&mut self, on: bool) {
+        let duty = if on { PWM_PERIOD / 2 } else { 0 };
+        self.regs().ch2cv.write(|w| unsafe { w.bits(duty) });
+    }
+
+    fn regs(&self) -> &pac::timer1::RegisterBlock {
+        unsafe { &*pac::TIMER3::ptr() }
+    }
+}
+
+/// Beeps for 500 ms. A beep during another one restarts it, they don't add up.
+pub fn beep() {
+    if MUTED.load(Ordering::Relaxed) {
+        return;
+    }
+    BUZZ_TICKS_REMAINING.store(BUZZ_TICKS, Ordering::Relaxed);
+    set_buzzer(true);
+}
+
+/// Silences the buzzer and ignores beeps until unmuted
+pub fn set_muted(muted: bool) {
+    MUTED.store(muted, Ordering::Relaxed);
+    if muted {
+        BUZZ_TICKS_REMAINING.store(0, Ordering::Relaxed);
+        set_buzzer(false);
+    }
+}
+
+/// Beeps when the reading crosses BUZZ_TEMP_THRESHOLD or
+/// BUZZ_HUM_THRESHOLD, called for every reading
+pub fn check_thresholds(temperature: f32, humidity: f32) {
+    let exceeded = temperature > BUZZ_TEMP_THRESHOLD || humidity > BUZZ_HUM_THRESHOLD;
+    if exceeded && !EXCEEDED.swap(exceeded, Ordering::Relaxed) {
+        beep();
+    } else {
+        EXCEEDED.store(exceeded, Ordering::Relaxed);
+    }
+}
+
+/// Counts down the current beep, called from the TIMER5 interrupt
+pub fn buzzer_tick() {
+    let remaining = BUZZ_TICKS_REMAINING.load(Ordering::Relaxed);
+    if remaining == 0 {
+        return;
+    }
+    BUZZ_TICKS_REMAINING.store(remaining - 1, Ordering::Relaxed);
+    if remaining == 1 {
+        set_buzzer(false);
+    }
+}
+
+fn set_buzzer(on: bool) {
+    free(|cs| {
+        if let Some(ref mut buzzer) = *BUZZER.borrow(*cs).borrow_mut() {
+            buzzer.set_on(on);
+        }
+    });
+}
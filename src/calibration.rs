@@ -0,0 +1,296 @@
+use core::cell::RefCell;
+use core::fmt::{self, Write};
+use core::sync::atomic::{AtomicI8, Ordering};
+use heapless::{String, Vec};
+use riscv::interrupt::{free, Mutex};
+
+use crate::types::SensorReading;
+use crate::util::fmt::{push_tenths, round_i32, to_tenths};
+
+/// Default self-heating of an enclosed sensor in °C at 100% MCU duty
+/// cycle, determined experimentally
+pub const SELF_HEATING_COEFF: f32 = 3.0;
+
+// Self-heating coefficient in use, changed with set_self_heating_coeff
+static SELF_HEATING: Mutex<RefCell<f32>> = Mutex::new(RefCell::new(SELF_HEATING_COEFF));
+
+pub fn set_self_heating_coeff(coeff: f32) {
+    free(|cs| {
+        SELF_HEATING.borrow(*cs).replace(coeff);
+    });
+}
+
+/// Removes the heat of the MCU from a temperature measured inside an
+/// enclosure. `duty_cycle_percent` is the share of time the MCU was
+/// running at full speed. Apply after the calibration offsets. No
+/// correction is done in freezing conditions.
+pub fn apply_self_heating_correction(temp_c: f32, duty_cycle_percent: u8) -> f32 {
+    if temp_c <= 0.0 {
+        return temp_c;
+    }
+
+    let coeff = free(|cs| *SELF_HEATING.borrow(*cs).borrow());
+    temp_c - coeff * duty_cycle_percent as f32 / 100.0
+}
+
+/// Offsets added to raw readings
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct CalibrationOffset {
+    pub temp: f32,
+    pub humidity: f32,
+}
+
+impl CalibrationOffset {
+    pub const ZERO: CalibrationOffset = CalibrationOffset {
+        temp: 0.0,
+        humidity: 0.0,
+    };
+
+    pub fn apply(&self, raw: &SensorReading) -> SensorReading {
+        SensorReading::new(raw.temperature + self.temp, raw.humidity + self.humidity)
+    }
+}
+
+/// Temperature offset in 0.1°C added to every reading, loaded from the
+/// boot config
+pub static TEMP_OFFSET_TENTH: AtomicI8 = AtomicI8::new(0);
+
+/// Humidity offset in % added to every reading, loaded from the boot config
+pub static HUM_OFFSET: AtomicI8 = AtomicI8::new(0);
+
+/// Offset stepped with `+` and `-` in the UART calibration mode
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum OffsetKind {
+    Temp,
+    Humidity,
+}
+
+pub fn calibration_offset() -> CalibrationOffset {
+    CalibrationOffset {
+        temp: TEMP_OFFSET_TENTH.load(Ordering::Relaxed) as f32 / 10.0,
+        humidity: HUM_OFFSET.load(Ordering::Relaxed) as f32,
+    }
+}
+
+/// Rounds the offsets to the stored resolution, 0.1°C and 1%, limited to
+/// the range of an i8
+pub fn set_calibration_offset(offset: CalibrationOffset) {
+    TEMP_OFFSET_TENTH.store(clamp_i8(to_tenths(offset.temp)), Ordering::Relaxed);
+    HUM_OFFSET.store(clamp_i8(round_i32(offset.humidity)), Ordering::Relaxed);
+}
+
+/// Moves one offset by `delta` steps of 0.1°C or 1%, stops at the end of
+/// the i8 range
+pub fn step_offset(kind: OffsetKind, delta: i8) {
+    let offset = match kind {
+        OffsetKind::Temp => &TEMP_OFFSET_TENTH,
+        OffsetKind::Humidity => &HUM_OFFSET,
+    };
+    let _ = offset.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |v| {
+        Some(v.saturating_add(delta))
+    });
+}
+
+/// Offsets in use with their signs, e.g. `T-0.3 H+2`
+pub fn offsets_text() -> String<16> {
+    let tenths = TEMP_OFFSET_TENTH.load(Ordering::Relaxed);
+    let humidity = HUM_OFFSET.load(Ordering::Relaxed);
+    let mut text = String::new();
+    // At most 11 characters, `T-12.8 H-128`
+    text.push_str(if tenths < 0 { "T" } else { "T+" }).ok();
+    push_tenths(&mut text, tenths as i32).ok();
+    let _ = write!(text, " H{:+}", humidity);
+    text
+}
+
+fn clamp_i8(value: i32) -> i8 {
+    value.max(i8::MIN as i32).min(i8::MAX as i32) as i8
+}
+
+/// Single-point calibration against a reference instrument: the offsets
+/// that make `current_raw` read as the reference values
+pub fn quick_calibrate(
+    reference_temp: f32,
+    reference_humidity: f32,
+    current_raw: &SensorReading,
+) -> CalibrationOffset {
+    CalibrationOffset {
+        temp: reference_temp - current_raw.temperature,
+        humidity: reference_humidity - current_raw.humidity,
+    }
+}
+
+// Number of calibration records kept
+const CALIBRATION_HISTORY_LEN: usize = 5;
+
+/// One applied calibration
+#[derive(Clone, Copy, Debug)]
+pub struct CalibrationRecord {
+    // Unix time of the calibration, or uptime while the clock is not set
+    pub timestamp_s: u32,
+    pub temp_offset: f32,
+    pub humidity_offset: f32,
+    pub calibrated_by_wizard: bool,
+}
+
+/// The latest calibrations, oldest first
+pub struct CalibrationHistory {
+    records: Vec<CalibrationRecord, CALIBRATION_HISTORY_LEN>,
+}
+
+impl CalibrationHistory {
+    pub const fn new() -> Self {
+        CalibrationHistory {
+            records: Vec::new(),
+        }
+    }
+
+    /// Adds a record, dropping the oldest one when the history is full
+    pub fn add(&mut self, record: CalibrationRecord) {
+        if self.records.is_full() {
+            self.records.remove(0);
+        }
+        // Can't fail, there is room after the removal above
+        let _ = self.records.push(record);
+    }
+
+    /// Prints one line per record, e.g. `[2024-01-15 14:30] T+0.5°C H-2.0% (wizard)`
+    pub fn print_history(&self, uart: &mut impl Write) -> fmt::Result {
+        for record in self.records.iter() {
+            let (year, month, day) = civil_from_days(record.timestamp_s / 86400);
+            let minutes = record.timestamp_s % 86400 / 60;
+            write!(
+                uart,
+                "[{:04}-{:02}-{:02} {:02}:{:02}] ",
+                year,
+                month,
+                day,
+                minutes / 60,
+                minutes % 60
+            )?;
+
+            // At most 19 bytes, `T-12.8°C H-128.0%`
+            let mut offsets: String<24> = String::new();
+            let _ = offsets.push('T');
+            let _ = push_signed_tenths(&mut offsets, to_tenths(record.temp_offset));
+            let _ = offsets.push_str("°C H");
+            let _ = push_signed_tenths(&mut offsets, to_tenths(record.humidity_offset));
+            let _ = offsets.push('%');
+            uart.write_str(&offsets)?;
+
+            if record.calibrated_by_wizard {
+                uart.write_str(" (wizard)")?;
+            }
+            uart.write_str("\r\n")?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for CalibrationHistory {
+    fn default() -> Self {
+        CalibrationHistory::new()
+    }
+}
+
+/// Calibrations applied since boot
+pub static CALIBRATION_HISTORY: Mutex<RefCell<CalibrationHistory>> =
+    Mutex::new(RefCell::new(CalibrationHistory::new()));
+
+/// Adds the offsets in use to CALIBRATION_HISTORY. `calibrated_by_wizard`
+/// is true for offsets stepped in the calibration mode.
+pub fn record_calibration(timestamp_s: u32, calibrated_by_wizard: bool) {
+    let offset = calibration_offset();
+    free(|cs| {
+        CALIBRATION_HISTORY
+            .borrow(*cs)
+            .borrow_mut()
+            .add(CalibrationRecord {
+                timestamp_s,
+                temp_offset: offset.temp,
+                humidity_offset: offset.humidity,
+                calibrated_by_wizard,
+            });
+    });
+}
+
+// Pushes a value given in tenths with its sign, `+0.5` or `-2.0`
+fn push_signed_tenths(text: &mut impl Write, tenths: i32) -> fmt::Result {
+    if tenths >= 0 {
+        text.write_char('+')?;
+    }
+    push_tenths(text, tenths)
+}
+
+/// Converts days since 1970-01-01 to a (year, month, day) date
+pub fn civil_from_days(days: u32) -> (u32, u32, u32) {
+    // Algorithm from Howard Hinnant's date library, shifted so that eras start on March 1st
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quick_calibrate_moves_raw_reading_onto_reference() {
+        let raw = SensorReading::new(22.0, 48.0);
+        let offset = quick_calibrate(21.5, 45.0, &raw);
+        assert_eq!(
+            offset,
+            CalibrationOffset {
+                temp: -0.5,
+                humidity: -3.0
+            }
+        );
+        assert_eq!(offset.apply(&raw), SensorReading::new(21.5, 45.0));
+    }
+
+    #[test]
+    fn offsets_are_stored_rounded() {
+        set_calibration_offset(CalibrationOffset {
+            temp: -0.46,
+            humidity: 2.6,
+        });
+        assert_eq!(
+            calibration_offset(),
+            CalibrationOffset {
+                temp: -0.5,
+                humidity: 3.0
+            }
+        );
+        assert_eq!(offsets_text().as_str(), "T-0.5 H+3");
+    }
+
+    #[test]
+    fn history_keeps_latest_records() {
+        let mut history = CalibrationHistory::new();
+        for i in 0..7 {
+            history.add(CalibrationRecord {
+                timestamp_s: 86_400 + 14 * 3600 + 30 * 60 + i * 60,
+                temp_offset: 0.5,
+                humidity_offset: -2.0,
+                calibrated_by_wizard: i % 2 == 0,
+            });
+        }
+
+        let mut text: String<256> = String::new();
+        history.print_history(&mut text).unwrap();
+        let mut lines = text.lines();
+        assert_eq!(
+            lines.next(),
+            Some("[1970-01-02 14:32] T+0.5°C H-2.0% (wizard)")
+        );
+        assert_eq!(lines.next(), Some("[1970-01-02 14:33] T+0.5°C H-2.0%"));
+        assert_eq!(lines.count(), 3);
+    }
+}
@@ -0,0 +1,63 @@
+/// Fixed size ring buffer with O(1) push and pop. `N` must be a power of
+/// two so that indices can wrap with a bitmask instead of a modulo.
+/// When full, pushing overwrites the oldest item.
+pub struct PowerOfTwoRingBuffer<T: Copy, const N: usize> {
+    buf: [Option<T>; N],
+    read: u32,
+    write: u32,
+}
+
+impl<T: Copy, const N: usize> PowerOfTwoRingBuffer<T, N> {
+    // Evaluated when the buffer is created, fails the build if N is not a power of two
+    const POWER_OF_TWO: () = assert!(N.is_power_of_two(), "capacity must be a power of two");
+
+    const MASK: u32 = (N - 1) as u32;
+
+    pub const fn new() -> Self {
+        #[allow(clippy::let_unit_value)]
+        let _ = Self::POWER_OF_TWO;
+        PowerOfTwoRingBuffer {
+            buf: [None; N],
+            read: 0,
+            write: 0,
+        }
+    }
+
+    pub fn push(&mut self, item: T) {
+        if self.len() == N {
+            self.read = self.read.wrapping_add(1);
+        }
+        self.buf[(self.write & Self::MASK) as usize] = Some(item);
+        self.write = self.write.wrapping_add(1);
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        if self.is_empty() {
+            return None;
+        }
+        let item = self.buf[(self.read & Self::MASK) as usize].take();
+        self.read = self.read.wrapping_add(1);
+        item
+    }
+
+    /// Items from oldest to newest without removing them
+    pub fn iter(&self) -> impl Iterator<Item = &T> + '_ {
+        (0..self.len() as u32).filter_map(move |i| {
+            self.buf[(self.read.wrapping_add(i) & Self::MASK) as usize].as_ref()
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.write.wrapping_sub(self.read) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T: Copy, const N: usize> Default for PowerOfTwoRingBuffer<T, N> {
+    fn default() -> Self {
+        PowerOfTwoRingBuffer::new()
+    }
+}
@@ -0,0 +1,244 @@
+use heapless::String;
+
+use crate::calibration::OffsetKind;
+
+/// Single byte commands accepted on the UART
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Command {
+    // Read the sensor without waiting for the update interval
+    Read,
+    // Restart the min/max tracking from the current reading
+    ResetMinMax,
+    // Print the reading history as CSV
+    PrintHistory,
+    // Switch the main page to the next layout
+    NextLayout,
+    // Enter the calibration mode, see CalibrationKey
+    Calibrate,
+    // Start or stop sending SensorFrames at 10 Hz
+    StreamFrames,
+    // Print the latest calibrations
+    CalibrationHistory,
+    // Print the finished alarms
+    AlarmLog,
+    // Print how the read failures are distributed over time
+    FailurePattern,
+    // Time format_i32 against write! on the target
+    Benchmark,
+    Help,
+}
+
+/// Keys of the calibration mode, taken before the commands while it is on
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CalibrationKey {
+    // `t` or `h`, the offset stepped by + and -
+    Select(OffsetKind),
+    // Move the selected offset by one step of 0.1°C or 1%
+    Step(i8),
+    // Save the offsets to flash and leave the calibration mode
+    Write,
+}
+
+impl CalibrationKey {
+    pub fn from_byte(byte: u8) -> Option<CalibrationKey> {
+        match byte {
+            b't' => Some(CalibrationKey::Select(OffsetKind::Temp)),
+            b'h' => Some(CalibrationKey::Select(OffsetKind::Humidity)),
+            b'+' => Some(CalibrationKey::Step(1)),
+            b'-' => Some(CalibrationKey::Step(-1)),
+            b'W' => Some(CalibrationKey::Write),
+            _ => None,
+        }
+    }
+}
+
+/// Response to `?`
+pub const HELP: &str = "r - read sensor now\r\n\
+                        z - reset min/max to current reading\r\n\
+                        p - print last 60 readings as CSV\r\n\
+                        l - switch display layout\r\n\
+                        C - calibrate: t/h select, +/- step, W save\r\n\
+                        B - start/stop binary frames at 10 Hz\r\n\
+                        H - print calibration history\r\n\
+                        A - print alarm log\r\n\
+                        F - classify the sensor read failures\r\n\
+                        b - benchmark integer formatting in CPU cycles\r\n\
+                        T<us> - sensor transition timeout, 101-9999\r\n\
+                        R<0.1C> <%> - calibrate against a reference, e.g. R215 45\r\n\
+                        S<0.1C> - self-heating at full MCU load, default 30\r\n\
+                        D<unix s> - set the clock, UTC\r\n\
+                        P<name> - alert profile: indoor, outdoor, greenhouse, serverroom\r\n\
+                        :dumpprotocol - edges of the last read as Saleae CSV\r\n\
+                        :ber - share of sensor bits close to the 0/1 threshold\r\n\
+                        :validateprotocol - read and check the timing against the golden trace\r\n\
+                        :climatezone - microclimate of the last 24 hours\r\n\
+                        :inject <fault> <reads> - fake checksum, timeout, stuck or powerloss reads\r\n\
+                        :mux <0-7> - route a sensor channel to the signal pin\r\n\
+                        :fullreport - device, readings, health, configuration and alarms\r\n\
+                        :dumpcsv - print the CSV log of the SPI flash\r\n\
+                        :eraselog - erase the CSV log of the SPI flash\r\n\
+                        ? - this list";
+
+/// Ends every command response
+pub const RESPONSE_END: &str = "\r\nOK\r\n";
+
+/// Ends the response to a command that was rejected
+pub const ERROR_END: &str = "\r\nERROR\r\n";
+
+impl Command {
+    pub fn from_byte(byte: u8) -> Option<Command> {
+        match byte {
+            b'r' => Some(Command::Read),
+            b'z' => Some(Command::ResetMinMax),
+            b'p' => Some(Command::PrintHistory),
+            b'l' => Some(Command::NextLayout),
+            b'C' => Some(Command::Calibrate),
+            b'B' => Some(Command::StreamFrames),
+            b'H' => Some(Command::CalibrationHistory),
+            b'A' => Some(Command::AlarmLog),
+            b'F' => Some(Command::FailurePattern),
+            b'b' => Some(Command::Benchmark),
+            b'?' => Some(Command::Help),
+            _ => None,
+        }
+    }
+}
+
+/// Commands followed by an argument and ended by a carriage return or a
+/// line feed, e.g. `T500\r`
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum LineCommand {
+    // `T<us>`, the transition timeout of the sensor reads
+    SensorTimeout,
+    // `R<tenths> <percent>`, single-point calibration against a reference
+    SetReference,
+    // `S<tenths>`, the self-heating coefficient in 0.1°C at full MCU load
+    SelfHeating,
+    // `D<unix s>`, the wall clock shown on the clock page
+    SetClock,
+    // `P<name>`, the MonitoringProfile whose thresholds the alerts use
+    SetProfile,
+    // `:<name> <args>`, a WordCommand
+    Word,
+}
+
+impl LineCommand {
+    pub fn from_byte(byte: u8) -> Option<LineCommand> {
+        match byte {
+            b'T' => Some(LineCommand::SensorTimeout),
+            b'R' => Some(LineCommand::SetReference),
+            b'S' => Some(LineCommand::SelfHeating),
+            b'D' => Some(LineCommand::SetClock),
+            b'P' => Some(LineCommand::SetProfile),
+            b':' => Some(LineCommand::Word),
+            _ => None,
+        }
+    }
+}
+
+/// Commands too rare for a byte of their own, given by name after `:`,
+/// e.g. `:dumpprotocol\r`
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum WordCommand {
+    // Print the edges of the latest sensor read as Saleae Logic CSV
+    DumpProtocol,
+    // Print the bit error rate of the sensor reads since boot
+    BitErrorRate,
+    // Read the sensor and compare the timing with the golden trace
+    ValidateProtocol,
+    // Print the climate zone and the 24 hour statistics behind it
+    ClimateZone,
+    // Replace the next sensor reads with a fault, with fault_injection
+    InjectFault,
+    // Select a channel of the sensor multiplexer, with sensor_mux
+    SelectMuxChannel,
+    // Print the status report of a service visit
+    FullReport,
+    // Print the CSV log of the SPI flash, with spi_flash
+    DumpCsv,
+    // Erase the CSV log of the SPI flash, with spi_flash
+    EraseLog,
+}
+
+impl WordCommand {
+    /// Splits the line of `:` into the command and the rest of the line
+    pub fn parse(line: &str) -> Option<(WordCommand, &str)> {
+        let (name, args) = match line.find(' ') {
+            Some(i) => (&line[..i], line[i + 1..].trim()),
+            None => (line, ""),
+        };
+        let command = match name {
+            "dumpprotocol" => WordCommand::DumpProtocol,
+            "ber" => WordCommand::BitErrorRate,
+            "validateprotocol" => WordCommand::ValidateProtocol,
+            "climatezone" => WordCommand::ClimateZone,
+            "inject" => WordCommand::InjectFault,
+            "mux" => WordCommand::SelectMuxChannel,
+            "fullreport" => WordCommand::FullReport,
+            "dumpcsv" => WordCommand::DumpCsv,
+            "eraselog" => WordCommand::EraseLog,
+            _ => return None,
+        };
+        Some((command, args))
+    }
+}
+
+// Longest argument of a LineCommand, e.g. `:inject checksum 10`
+const MAX_ARG_LEN: usize = 24;
+
+/// Argument of a LineCommand while it is being received
+pub struct LineBuffer {
+    command: LineCommand,
+    arg: String<MAX_ARG_LEN>,
+    // The argument didn't fit, the command is rejected at the end of the line
+    overflow: bool,
+}
+
+impl LineBuffer {
+    pub fn new(command: LineCommand) -> Self {
+        LineBuffer {
+            command,
+            arg: String::new(),
+            overflow: false,
+        }
+    }
+
+    /// Adds a byte of the argument
+    pub fn push(&mut self, byte: u8) {
+        if self.arg.push(byte as char).is_err() {
+            self.overflow = true;
+        }
+    }
+
+    pub fn command(&self) -> LineCommand {
+        self.command
+    }
+
+    /// Argument received so far, None when it was too long
+    pub fn arg(&self) -> Option<&str> {
+        if self.overflow {
+            None
+        } else {
+            Some(self.arg.trim())
+        }
+    }
+}
+
+/// True for the bytes that end the argument of a LineCommand
+pub fn is_line_end(byte: u8) -> bool {
+    byte == b'\r' || byte == b'\n'
+}
+
+/// Reference temperature and humidity of `R`, given in 0.1°C and % and
+/// separated by a space or a comma
+pub fn parse_reference(arg: &str) -> Option<(f32, f32)> {
+    let mut parts = arg
+        .split(|c: char| c == ' ' || c == ',')
+        .filter(|part| !part.is_empty());
+    let tenths: i32 = parts.next()?.parse().ok()?;
+    let humidity: i32 = parts.next()?.parse().ok()?;
+    if parts.next().is_some() {
+        return None;
+    }
+    Some((tenths as f32 / 10.0, humidity as f32))
+}
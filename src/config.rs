@@ -0,0 +1,243 @@
+use core::cell::RefCell;
+use riscv::interrupt::{free, Mutex};
+
+use crate::crc::crc8;
+use crate::storage::fmc::{self, FlashError};
+
+/// Temperature (°C) above which the alert output pin is driven high
+pub const TEMP_HIGH_THRESHOLD: f32 = 30.0;
+
+/// Relative humidity (%) above which the alert output pin is driven high
+pub const HUM_HIGH_THRESHOLD: f32 = 80.0;
+
+/// Temperature (°C) whose crossing sounds the buzzer
+pub const BUZZ_TEMP_THRESHOLD: f32 = 30.0;
+
+/// Relative humidity (%) whose crossing sounds the buzzer
+pub const BUZZ_HUM_THRESHOLD: f32 = 80.0;
+
+/// Limits outside of which a reading is considered an alert
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct AlertThresholds {
+    pub min_temp: f32,
+    pub max_temp: f32,
+    pub min_humidity: f32,
+    pub max_humidity: f32,
+}
+
+impl AlertThresholds {
+    /// True when the reading is outside the thresholds
+    pub fn is_exceeded(&self, temperature: f32, humidity: f32) -> bool {
+        temperature < self.min_temp
+            || temperature > self.max_temp
+            || humidity < self.min_humidity
+            || humidity > self.max_humidity
+    }
+
+    /// The first threshold the reading violates with the threshold value
+    /// and the offending reading
+    pub fn violation(&self, temperature: f32, humidity: f32) -> Option<(ThresholdKind, f32, f32)> {
+        if temperature > self.max_temp {
+            Some((ThresholdKind::MaxTemp, self.max_temp, temperature))
+        } else if temperature < self.min_temp {
+            Some((ThresholdKind::MinTemp, self.min_temp, temperature))
+        } else if humidity > self.max_humidity {
+            Some((ThresholdKind::MaxHumidity, self.max_humidity, humidity))
+        } else if humidity < self.min_humidity {
+            Some((ThresholdKind::MinHumidity, self.min_humidity, humidity))
+        } else {
+            None
+        }
+    }
+}
+
+/// Which of the alert thresholds was violated
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ThresholdKind {
+    MinTemp,
+    MaxTemp,
+    MinHumidity,
+    MaxHumidity,
+}
+
+impl ThresholdKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ThresholdKind::MinTemp => "MIN_T",
+            ThresholdKind::MaxTemp => "MAX_T",
+            ThresholdKind::MinHumidity => "MIN_H",
+            ThresholdKind::MaxHumidity => "MAX_H",
+        }
+    }
+
+    pub fn unit(&self) -> &'static str {
+        match self {
+            ThresholdKind::MinTemp | ThresholdKind::MaxTemp => "°C",
+            ThresholdKind::MinHumidity | ThresholdKind::MaxHumidity => "%",
+        }
+    }
+
+    /// True for the lower limits, where the reading falls below the threshold
+    pub fn is_lower_limit(&self) -> bool {
+        matches!(self, ThresholdKind::MinTemp | ThresholdKind::MinHumidity)
+    }
+}
+
+// Profile whose thresholds are used for alerts, selected with the `P` command
+static MONITORING_PROFILE: Mutex<RefCell<MonitoringProfile>> =
+    Mutex::new(RefCell::new(MonitoringProfile::Indoor));
+
+pub fn monitoring_profile() -> MonitoringProfile {
+    free(|cs| *MONITORING_PROFILE.borrow(*cs).borrow())
+}
+
+pub fn set_monitoring_profile(profile: MonitoringProfile) {
+    free(|cs| {
+        MONITORING_PROFILE.borrow(*cs).replace(profile);
+    });
+}
+
+/// Deployment environment presets for the alert thresholds
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MonitoringProfile {
+    Indoor,
+    Outdoor,
+    Greenhouse,
+    ServerRoom,
+}
+
+impl MonitoringProfile {
+    /// Default alert thresholds for the profile
+    pub fn defaults(&self) -> AlertThresholds {
+        match self {
+            MonitoringProfile::Indoor => AlertThresholds {
+                min_temp: 18.0,
+                max_temp: 26.0,
+                min_humidity: 30.0,
+                max_humidity: 60.0,
+            },
+            MonitoringProfile::Outdoor => AlertThresholds {
+                min_temp: -20.0,
+                max_temp: 35.0,
+                min_humidity: 10.0,
+                max_humidity: 95.0,
+            },
+            MonitoringProfile::Greenhouse => AlertThresholds {
+                min_temp: 15.0,
+                max_temp: 35.0,
+                min_humidity: 60.0,
+                max_humidity: 90.0,
+            },
+            MonitoringProfile::ServerRoom => AlertThresholds {
+                min_temp: 18.0,
+                max_temp: 27.0,
+                min_humidity: 40.0,
+                max_humidity: 60.0,
+            },
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MonitoringProfile::Indoor => "indoor",
+            MonitoringProfile::Outdoor => "outdoor",
+            MonitoringProfile::Greenhouse => "greenhouse",
+            MonitoringProfile::ServerRoom => "serverroom",
+        }
+    }
+
+    /// Parses a profile name as given to the `P<name>` command
+    pub fn from_name(name: &str) -> Option<MonitoringProfile> {
+        match name {
+            "indoor" => Some(MonitoringProfile::Indoor),
+            "outdoor" => Some(MonitoringProfile::Outdoor),
+            "greenhouse" => Some(MonitoringProfile::Greenhouse),
+            "serverroom" => Some(MonitoringProfile::ServerRoom),
+            _ => None,
+        }
+    }
+}
+
+// Size of BootConfig in flash
+const BOOT_CONFIG_LEN: usize = 6;
+
+// Saving erases the whole page, the config has to fit in it
+const _: () = assert!(BOOT_CONFIG_LEN <= fmc::PAGE_SIZE as usize);
+
+/// Settings kept in internal flash over resets, validated by a CRC-8
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct BootConfig {
+    pub update_interval_s: u8,
+    pub display_brightness: u8,
+    pub temp_offset_tenth_deg: i8,
+    pub hum_offset_percent: i8,
+    // TemperatureUnit::to_u8 of the displayed unit
+    pub temperature_unit: u8,
+    // CRC-8 of the fields above
+    pub crc8: u8,
+}
+
+impl BootConfig {
+    /// Used when the flash page is erased or corrupted
+    pub const DEFAULT: BootConfig = BootConfig {
+        update_interval_s: 3,
+        display_brightness: 100,
+        temp_offset_tenth_deg: 0,
+        hum_offset_percent: 0,
+        temperature_unit: 0,
+        crc8: 0,
+    };
+
+    fn to_bytes(&self) -> [u8; BOOT_CONFIG_LEN] {
+        [
+            self.update_interval_s,
+            self.display_brightness,
+            self.temp_offset_tenth_deg as u8,
+            self.hum_offset_percent as u8,
+            self.temperature_unit,
+            self.crc8,
+        ]
+    }
+
+    fn from_bytes(bytes: &[u8; BOOT_CONFIG_LEN]) -> Self {
+        BootConfig {
+            update_interval_s: bytes[0],
+            display_brightness: bytes[1],
+            temp_offset_tenth_deg: bytes[2] as i8,
+            hum_offset_percent: bytes[3] as i8,
+            temperature_unit: bytes[4],
+            crc8: bytes[5],
+        }
+    }
+
+    // CRC-8 of every field except the CRC itself
+    fn compute_crc(&self) -> u8 {
+        crc8(&self.to_bytes()[..BOOT_CONFIG_LEN - 1])
+    }
+}
+
+/// Boot configuration from the last flash page, or the defaults if the
+/// CRC doesn't match
+pub fn load_boot_config() -> BootConfig {
+    let mut bytes = [0u8; BOOT_CONFIG_LEN];
+    fmc::read(fmc::last_page_addr(), &mut bytes);
+    let config = BootConfig::from_bytes(&bytes);
+    if config.crc8 == config.compute_crc() {
+        config
+    } else {
+        BootConfig::DEFAULT
+    }
+}
+
+/// Erases the last flash page and writes the configuration with a fresh CRC
+pub fn save_boot_config(cfg: &BootConfig) -> Result<(), FlashError> {
+    let mut config = *cfg;
+    config.crc8 = config.compute_crc();
+    let addr = fmc::last_page_addr();
+    // The CPU stalls on flash access while programming, keep interrupts out of it
+    free(|_| {
+        fmc::erase_page(addr)?;
+        fmc::program(addr, &config.to_bytes())
+    })
+}
@@ -0,0 +1,50 @@
+// Polynomial 0x31 with its bits reversed, for shifting right
+const POLY_REFLECTED: u8 = 0x8C;
+
+/// CRC of every byte value, generated at compile time and kept in flash
+pub const CRC8_TABLE: [u8; 256] = crc8_table();
+
+const fn crc8_table() -> [u8; 256] {
+    let mut table = [0u8; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u8;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ POLY_REFLECTED
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/// CRC-8 of the Dallas/Maxim 1-Wire bus: polynomial 0x31, initial value
+/// 0, bits taken least significant first. One table lookup per byte.
+pub fn crc8(data: &[u8]) -> u8 {
+    data.iter().fold(0, |crc, &byte| CRC8_TABLE[(crc ^ byte) as usize])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc8_check_value() {
+        assert_eq!(crc8(b"123456789"), 0xA1);
+    }
+
+    #[test]
+    fn crc8_of_1wire_rom_code() {
+        // Example ROM code of Maxim application note 27
+        let rom = [0x02, 0x1C, 0xB8, 0x01, 0x00, 0x00, 0x00];
+        assert_eq!(crc8(&rom), 0xA2);
+        // Data followed by its CRC checks to zero
+        assert_eq!(crc8(&[0x02, 0x1C, 0xB8, 0x01, 0x00, 0x00, 0x00, 0xA2]), 0);
+    }
+}
@@ -0,0 +1,364 @@
+use core::fmt::{self, Write};
+use embedded_graphics::pixelcolor::Rgb565;
+use embedded_graphics::prelude::*;
+
+use crate::util::fmt::{push_tenths, to_tenths};
+
+/// How the relative humidity feels to a person
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum HumidityCategory {
+    TooDry,
+    Dry,
+    Comfortable,
+    Humid,
+    VeryHumid,
+    Oppressive,
+}
+
+impl HumidityCategory {
+    /// Short label that fits next to the humidity value on the LCD
+    pub fn label(&self) -> &'static str {
+        match self {
+            HumidityCategory::TooDry => "V.Dry",
+            HumidityCategory::Dry => "Dry",
+            HumidityCategory::Comfortable => "OK",
+            HumidityCategory::Humid => "Humid",
+            HumidityCategory::VeryHumid => "V.Humid",
+            HumidityCategory::Oppressive => "Muggy",
+        }
+    }
+
+    pub fn color(&self) -> Rgb565 {
+        match self {
+            HumidityCategory::TooDry => Rgb565::RED,
+            HumidityCategory::Dry => Rgb565::YELLOW,
+            HumidityCategory::Comfortable => Rgb565::GREEN,
+            HumidityCategory::Humid => Rgb565::CYAN,
+            HumidityCategory::VeryHumid => Rgb565::BLUE,
+            HumidityCategory::Oppressive => Rgb565::MAGENTA,
+        }
+    }
+}
+
+/// Maps relative humidity (%) to a descriptive category
+pub fn humidity_category(rh: f32) -> HumidityCategory {
+    if rh < 25.0 {
+        HumidityCategory::TooDry
+    } else if rh < 35.0 {
+        HumidityCategory::Dry
+    } else if rh < 55.0 {
+        HumidityCategory::Comfortable
+    } else if rh < 70.0 {
+        HumidityCategory::Humid
+    } else if rh <= 80.0 {
+        HumidityCategory::VeryHumid
+    } else {
+        HumidityCategory::Oppressive
+    }
+}
+
+/// Average of the readings during one hour
+#[derive(Clone, Copy, Debug)]
+pub struct HourlyAverage {
+    pub temperature: f32,
+    pub humidity: f32,
+    // Number of readings in the average, 0 when the hour has no data
+    pub samples: u16,
+}
+
+const EMPTY_HOUR: HourlyAverage = HourlyAverage {
+    temperature: 0.0,
+    humidity: 0.0,
+    samples: 0,
+};
+
+/// Hourly averages of the last 24 hours, indexed by the hour of day
+pub struct HourlyLog {
+    hours: [HourlyAverage; 24],
+    // Hour of the latest reading
+    current_hour: Option<usize>,
+}
+
+impl HourlyLog {
+    pub const fn new() -> Self {
+        HourlyLog {
+            hours: [EMPTY_HOUR; 24],
+            current_hour: None,
+        }
+    }
+
+    /// Adds a reading taken during `hour` (0-23). The first reading of an
+    /// hour drops the average from the day before, and those of any hours
+    /// skipped since the previous reading.
+    pub fn push(&mut self, hour: usize, temperature: f32, humidity: f32) {
+        let hour = hour % 24;
+        if self.current_hour != Some(hour) {
+            let mut stale = self.current_hour.map_or(hour, |previous| (previous + 1) % 24);
+            loop {
+                self.hours[stale] = EMPTY_HOUR;
+                if stale == hour {
+                    break;
+                }
+                stale = (stale + 1) % 24;
+            }
+            self.current_hour = Some(hour);
+        }
+
+        let average = &mut self.hours[hour];
+        average.samples = average.samples.saturating_add(1);
+        let n = average.samples as f32;
+        average.temperature += (temperature - average.temperature) / n;
+        average.humidity += (humidity - average.humidity) / n;
+    }
+
+    pub fn hours(&self) -> &[HourlyAverage; 24] {
+        &self.hours
+    }
+
+    /// Number of hours with at least one reading
+    pub fn hours_with_data(&self) -> usize {
+        self.hours.iter().filter(|h| h.samples > 0).count()
+    }
+}
+
+impl Default for HourlyLog {
+    fn default() -> Self {
+        HourlyLog::new()
+    }
+}
+
+/// Simplified Köppen-style characterisation of the local microclimate
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ClimateZone {
+    // Small diurnal range, high humidity
+    Oceanic,
+    // Large diurnal range, moderate humidity
+    Continental,
+    // Low humidity
+    Arid,
+    // Small diurnal range, warm and very humid
+    Tropical,
+    InsufficientData,
+}
+
+impl ClimateZone {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ClimateZone::Oceanic => "Oceanic",
+            ClimateZone::Continental => "Continental",
+            ClimateZone::Arid => "Arid",
+            ClimateZone::Tropical => "Tropical",
+            ClimateZone::InsufficientData => "Insufficient data",
+        }
+    }
+}
+
+/// 24 hour statistics the climate classification is based on
+#[derive(Clone, Copy, Debug)]
+pub struct ClimateStats {
+    pub diurnal_range: f32,
+    pub mean_temperature: f32,
+    pub temperature_variance: f32,
+    pub mean_humidity: f32,
+}
+
+// Diurnal temperature range (°C) under which the climate is considered maritime
+const SMALL_DIURNAL_RANGE: f32 = 8.0;
+
+// Humidity limits (%) of the climate zones
+const ARID_HUMIDITY: f32 = 40.0;
+const OCEANIC_HUMIDITY: f32 = 60.0;
+const TROPICAL_HUMIDITY: f32 = 75.0;
+
+// Köppen group A requires the coldest month to stay above 18°C
+const TROPICAL_MIN_TEMPERATURE: f32 = 18.0;
+
+/// Statistics of a full day of hourly averages, `None` if any hour is missing
+pub fn climate_stats(hourly: &[HourlyAverage; 24]) -> Option<ClimateStats> {
+    if hourly.iter().any(|h| h.samples == 0) {
+        return None;
+    }
+
+    let n = hourly.len() as f32;
+    let mut min_t = hourly[0].temperature;
+    let mut max_t = hourly[0].temperature;
+    let mut sum_t = 0.0;
+    let mut sum_h = 0.0;
+    for h in hourly.iter() {
+        min_t = min_t.min(h.temperature);
+        max_t = max_t.max(h.temperature);
+        sum_t += h.temperature;
+        sum_h += h.humidity;
+    }
+    let mean_t = sum_t / n;
+
+    let temperature_variance = hourly
+        .iter()
+        .map(|h| (h.temperature - mean_t) * (h.temperature - mean_t))
+        .sum::<f32>()
+        / n;
+
+    Some(ClimateStats {
+        diurnal_range: max_t - min_t,
+        mean_temperature: mean_t,
+        temperature_variance,
+        mean_humidity: sum_h / n,
+    })
+}
+
+/// Classifies the microclimate from 24 hourly averages
+pub fn classify_climate(hourly: &[HourlyAverage; 24]) -> ClimateZone {
+    let stats = match climate_stats(hourly) {
+        Some(stats) => stats,
+        None => return ClimateZone::InsufficientData,
+    };
+
+    if stats.mean_humidity < ARID_HUMIDITY {
+        ClimateZone::Arid
+    } else if stats.diurnal_range < SMALL_DIURNAL_RANGE {
+        if stats.mean_humidity >= TROPICAL_HUMIDITY
+            && stats.mean_temperature >= TROPICAL_MIN_TEMPERATURE
+        {
+            ClimateZone::Tropical
+        } else if stats.mean_humidity >= OCEANIC_HUMIDITY {
+            ClimateZone::Oceanic
+        } else {
+            ClimateZone::Continental
+        }
+    } else {
+        ClimateZone::Continental
+    }
+}
+
+/// Writes the `:climatezone` report: the classification and the 24 hour
+/// statistics it is based on
+pub fn write_climate_report(log: &HourlyLog, out: &mut impl Write) -> fmt::Result {
+    write!(out, "Zone: {}\r\n", classify_climate(log.hours()).label())?;
+    match climate_stats(log.hours()) {
+        Some(stats) => {
+            out.write_str("Diurnal range: ")?;
+            push_tenths(out, to_tenths(stats.diurnal_range))?;
+            out.write_str(" C\r\nMean T: ")?;
+            push_tenths(out, to_tenths(stats.mean_temperature))?;
+            out.write_str(" C\r\nT variance: ")?;
+            push_tenths(out, to_tenths(stats.temperature_variance))?;
+            out.write_str(" C2\r\nMean RH: ")?;
+            push_tenths(out, to_tenths(stats.mean_humidity))?;
+            out.write_str(" %")
+        }
+        None => write!(out, "Hours with data: {}/24", log.hours_with_data()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn humidity_category_edges() {
+        let edges = [
+            (24.9, HumidityCategory::TooDry),
+            (25.0, HumidityCategory::Dry),
+            (34.9, HumidityCategory::Dry),
+            (35.0, HumidityCategory::Comfortable),
+            (54.9, HumidityCategory::Comfortable),
+            (55.0, HumidityCategory::Humid),
+            (69.9, HumidityCategory::Humid),
+            (70.0, HumidityCategory::VeryHumid),
+            (80.0, HumidityCategory::VeryHumid),
+            (80.1, HumidityCategory::Oppressive),
+        ];
+        for &(rh, category) in edges.iter() {
+            assert_eq!(humidity_category(rh), category, "{}%", rh);
+        }
+    }
+
+    #[test]
+    fn humidity_category_out_of_range() {
+        assert_eq!(humidity_category(0.0), HumidityCategory::TooDry);
+        assert_eq!(humidity_category(100.0), HumidityCategory::Oppressive);
+    }
+
+    // A day rising linearly from `min` at midnight to `min + range` at noon
+    // and back, at a constant humidity
+    fn day(min: f32, range: f32, humidity: f32) -> [HourlyAverage; 24] {
+        let mut hourly = [HourlyAverage {
+            temperature: 0.0,
+            humidity,
+            samples: 60,
+        }; 24];
+        for (hour, h) in hourly.iter_mut().enumerate() {
+            let distance = (hour as i32 - 12).abs() as f32;
+            h.temperature = min + range * (12.0 - distance) / 12.0;
+        }
+        hourly
+    }
+
+    #[test]
+    fn climate_stats_of_two_level_day() {
+        let mut hourly = day(10.0, 0.0, 50.0);
+        for h in hourly[12..].iter_mut() {
+            h.temperature = 20.0;
+            h.humidity = 70.0;
+        }
+
+        let stats = climate_stats(&hourly).unwrap();
+        assert_eq!(stats.diurnal_range, 10.0);
+        assert_eq!(stats.mean_temperature, 15.0);
+        assert_eq!(stats.temperature_variance, 25.0);
+        assert_eq!(stats.mean_humidity, 60.0);
+    }
+
+    #[test]
+    fn climate_stats_of_triangle_day() {
+        let stats = climate_stats(&day(12.0, 6.0, 70.0)).unwrap();
+        assert_eq!(stats.diurnal_range, 6.0);
+        assert!((stats.mean_temperature - 15.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn known_days_are_classified() {
+        let days = [
+            (day(12.0, 4.0, 70.0), ClimateZone::Oceanic),
+            (day(26.0, 4.0, 85.0), ClimateZone::Tropical),
+            // Humid but too cool for the tropical group
+            (day(10.0, 4.0, 85.0), ClimateZone::Oceanic),
+            (day(10.0, 25.0, 20.0), ClimateZone::Arid),
+            (day(5.0, 15.0, 50.0), ClimateZone::Continental),
+            // Small range without the humidity of a maritime climate
+            (day(15.0, 4.0, 50.0), ClimateZone::Continental),
+        ];
+        for (hourly, zone) in days.iter() {
+            assert_eq!(classify_climate(hourly), *zone);
+        }
+    }
+
+    #[test]
+    fn hourly_log_drops_skipped_hours() {
+        let mut log = HourlyLog::new();
+        for hour in 0..24 {
+            log.push(hour, 20.0, 50.0);
+            log.push(hour, 22.0, 60.0);
+        }
+        assert_eq!(log.hours()[5].temperature, 21.0);
+        assert_eq!(log.hours()[5].humidity, 55.0);
+        assert_eq!(log.hours_with_data(), 24);
+
+        // Next day, no readings from 02 to 04
+        log.push(0, 10.0, 40.0);
+        log.push(1, 10.0, 40.0);
+        log.push(5, 10.0, 40.0);
+        assert_eq!(log.hours_with_data(), 21);
+        assert_eq!(log.hours()[5].temperature, 10.0);
+        assert_eq!(log.hours()[6].temperature, 21.0);
+    }
+
+    #[test]
+    fn missing_hour_is_insufficient_data() {
+        let mut hourly = day(12.0, 4.0, 70.0);
+        hourly[7].samples = 0;
+
+        assert!(climate_stats(&hourly).is_none());
+        assert_eq!(classify_climate(&hourly), ClimateZone::InsufficientData);
+    }
+}
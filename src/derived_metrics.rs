@@ -0,0 +1,223 @@
+use embedded_graphics::pixelcolor::Rgb565;
+
+use crate::display_config::{
+    BANNER_COMFY_COLOR, BANNER_DRY_COLOR, BANNER_HOT_COLOR, BANNER_HUMID_COLOR,
+};
+use crate::types::SensorReading;
+
+// Limits of the comfort zone, loosely following ASHRAE 55 for indoor spaces
+const COMFORT_MAX_TEMP: f32 = 26.0;
+const COMFORT_MIN_HUMIDITY: f32 = 30.0;
+const COMFORT_MAX_HUMIDITY: f32 = 60.0;
+
+/// Overall comfort of the environment for a person
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ComfortLevel {
+    Dry,
+    Comfortable,
+    Humid,
+    Hot,
+}
+
+impl ComfortLevel {
+    /// Banner text
+    pub fn label(&self) -> &'static str {
+        match self {
+            ComfortLevel::Dry => "DRY",
+            ComfortLevel::Comfortable => "COMFY",
+            ComfortLevel::Humid => "HUMID",
+            ComfortLevel::Hot => "HOT",
+        }
+    }
+
+    /// Banner fill color
+    pub fn color(&self) -> Rgb565 {
+        match self {
+            ComfortLevel::Dry => BANNER_DRY_COLOR,
+            ComfortLevel::Comfortable => BANNER_COMFY_COLOR,
+            ComfortLevel::Humid => BANNER_HUMID_COLOR,
+            ComfortLevel::Hot => BANNER_HOT_COLOR,
+        }
+    }
+}
+
+/// Classifies temperature (°C) and relative humidity (%). Heat takes
+/// precedence over humidity.
+pub fn comfort_level(t: f32, rh: f32) -> ComfortLevel {
+    if t > COMFORT_MAX_TEMP {
+        ComfortLevel::Hot
+    } else if rh > COMFORT_MAX_HUMIDITY {
+        ComfortLevel::Humid
+    } else if rh < COMFORT_MIN_HUMIDITY {
+        ComfortLevel::Dry
+    } else {
+        ComfortLevel::Comfortable
+    }
+}
+
+// Magnus formula coefficients from Alduchov and Eskridge (1996)
+#[cfg(feature = "precise-dewpoint")]
+const MAGNUS_B: f32 = 17.625;
+#[cfg(feature = "precise-dewpoint")]
+const MAGNUS_C: f32 = 243.04;
+
+/// Dew point (°C) from temperature (°C) and relative humidity (%). Uses
+/// the linear approximation `t - (100 - rh) / 5`, within about 1°C above
+/// 50% humidity, unless the `precise-dewpoint` feature is enabled.
+#[cfg(not(feature = "precise-dewpoint"))]
+pub fn dew_point(t_celsius: f32, rh_percent: f32) -> f32 {
+    t_celsius - (100.0 - rh_percent) / 5.0
+}
+
+/// Dew point (°C) from temperature (°C) and relative humidity (%) using
+/// the Magnus formula
+#[cfg(feature = "precise-dewpoint")]
+pub fn dew_point(t_celsius: f32, rh_percent: f32) -> f32 {
+    // ln(0) is undefined, 0% humidity has no dew point
+    let rh = rh_percent.max(0.1) / 100.0;
+    let gamma = libm::logf(rh) + MAGNUS_B * t_celsius / (MAGNUS_C + t_celsius);
+    MAGNUS_C * gamma / (MAGNUS_B - gamma)
+}
+
+// Lowe (1977) polynomial for the saturation vapor pressure over water in
+// hPa, fitted to the Tetens/Goff-Gratch values used by the WMO. Highest
+// power first, accurate to 0.1% between -50 and 50°C. Trimmed to f32
+// precision.
+const SVP_COEFFS: [f32; 7] = [
+    6.136821e-11,
+    2.034081e-8,
+    3.0312404e-6,
+    2.6506485e-4,
+    1.4289458e-2,
+    4.4365185e-1,
+    6.1078,
+];
+
+// Limits of the VPD zones in kPa
+const VPD_LOW_KPA: f32 = 0.4;
+const VPD_HIGH_KPA: f32 = 1.6;
+
+/// Saturation vapor pressure (kPa) at the given temperature (°C). A
+/// polynomial instead of the exponential in the Tetens formula, so no libm.
+pub fn saturation_vapor_pressure(t_celsius: f32) -> f32 {
+    let hpa = SVP_COEFFS.iter().fold(0.0, |acc, c| acc * t_celsius + c);
+    hpa / 10.0
+}
+
+// Water vapor density from vapor pressure through the ideal gas law:
+// 1000 g/kg / R_v (461.5 J/(kg K)) * 1000 Pa/kPa / 100 %
+const AH_FACTOR: f32 = 21.668;
+
+/// Absolute humidity (g/m³): mass of water vapor in a cubic meter of air
+pub fn absolute_humidity_g_m3(t_celsius: f32, rh_percent: f32) -> f32 {
+    let vapor_kpa = saturation_vapor_pressure(t_celsius) * rh_percent;
+    AH_FACTOR * vapor_kpa / (t_celsius + 273.15)
+}
+
+/// Vapor pressure deficit (kPa): how much more water the air could hold
+pub fn vpd_kpa(t: f32, rh: f32) -> f32 {
+    saturation_vapor_pressure(t) * (1.0 - rh / 100.0)
+}
+
+/// Plant growth zone of the vapor pressure deficit
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum VpdZone {
+    // Below 0.4 kPa, leaves can't transpire, risk of mold
+    Low,
+    Optimal,
+    // Above 1.6 kPa, plants close their stomata
+    High,
+}
+
+impl VpdZone {
+    pub fn from_kpa(vpd: f32) -> VpdZone {
+        if vpd < VPD_LOW_KPA {
+            VpdZone::Low
+        } else if vpd > VPD_HIGH_KPA {
+            VpdZone::High
+        } else {
+            VpdZone::Optimal
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            VpdZone::Low => "LOW",
+            VpdZone::Optimal => "OK",
+            VpdZone::High => "HIGH",
+        }
+    }
+}
+
+// Rothfusz regression of Steadman's heat index table, in °F and % as
+// published by the NWS, rounded to the digits an f32 holds
+const HI_C1: f32 = -42.379;
+const HI_C2: f32 = 2.0490152;
+const HI_C3: f32 = 10.143332;
+const HI_C4: f32 = -0.2247554;
+const HI_C5: f32 = -6.83783e-3;
+const HI_C6: f32 = -5.481717e-2;
+const HI_C7: f32 = 1.22874e-3;
+const HI_C8: f32 = 8.5282e-4;
+const HI_C9: f32 = -1.99e-6;
+
+// Below these the regression is not valid and the heat index is not used
+const HI_MIN_TEMP: f32 = 27.0;
+const HI_MIN_HUMIDITY: f32 = 40.0;
+
+/// Apparent temperature (°C) from temperature (°C) and relative humidity
+/// (%), None outside the domain of the regression (below 27°C or 40%)
+pub fn heat_index(t_celsius: f32, rh: f32) -> Option<f32> {
+    if t_celsius < HI_MIN_TEMP || rh < HI_MIN_HUMIDITY {
+        return None;
+    }
+
+    let t = t_celsius * 9.0 / 5.0 + 32.0;
+    let hi = HI_C1
+        + HI_C2 * t
+        + HI_C3 * rh
+        + HI_C4 * t * rh
+        + HI_C5 * t * t
+        + HI_C6 * rh * rh
+        + HI_C7 * t * t * rh
+        + HI_C8 * t * rh * rh
+        + HI_C9 * t * t * rh * rh;
+
+    Some((hi - 32.0) * 5.0 / 9.0)
+}
+
+/// Values derived from a reading, computed once per display update
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct DerivedMetrics {
+    pub dew_point: f32,
+    pub comfort: ComfortLevel,
+    pub heat_index: Option<f32>,
+}
+
+impl DerivedMetrics {
+    pub fn from_reading(reading: &SensorReading) -> Self {
+        DerivedMetrics {
+            dew_point: dew_point(reading.temperature, reading.humidity),
+            comfort: comfort_level(reading.temperature, reading.humidity),
+            heat_index: heat_index(reading.temperature, reading.humidity),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heat_index_matches_nws_table() {
+        // 95°F and 60% is 113°F in the NWS heat index table
+        let hi = heat_index(35.0, 60.0).unwrap();
+        assert!(hi > 44.5 && hi < 45.5, "heat index {}", hi);
+    }
+
+    #[test]
+    fn heat_index_outside_domain() {
+        assert_eq!(heat_index(26.9, 60.0), None);
+        assert_eq!(heat_index(35.0, 39.9), None);
+    }
+}
@@ -0,0 +1,18 @@
+/// Hardware timestamps of the edges on the sensor line, in microseconds.
+/// Timestamps are 16 bit and wrap, only differences within one frame are
+/// meaningful.
+pub trait EdgeCapture {
+    /// Starts capturing the next edge in the given direction, so that an
+    /// edge before the following `wait_edge` isn't lost. Nothing to do for
+    /// captures that look at the pin level.
+    fn arm(&mut self, _rising: bool) {}
+
+    /// Waits for the next rising or falling edge and returns its captured
+    /// time, or None if there was none within `timeout_us`
+    fn wait_edge(&mut self, rising: bool, timeout_us: u32) -> Option<u16>;
+}
+
+#[cfg(feature = "hal")]
+pub use super::polled::PolledCapture;
+#[cfg(feature = "hal")]
+pub use super::timer4::Timer4Capture;
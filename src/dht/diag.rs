@@ -0,0 +1,125 @@
+use core::cell::RefCell;
+use core::fmt::{self, Write};
+#[cfg(feature = "hal")]
+use longan_nano::hal::pac;
+use riscv::interrupt::Mutex;
+
+use super::protocol::MAX_TRANSITIONS;
+
+// Number of edges kept, enough for the start signal, response and 40 bits
+const CAPTURE_LEN: usize = MAX_TRANSITIONS + 2;
+
+// mtime ticks per microsecond, the core timer runs at sysclk / 4
+const MTIME_TICKS_PER_US: u32 = crate::SYSCLK_MHZ / 4;
+
+// Edges of the latest sensor read
+pub static PROTOCOL_CAPTURE: Mutex<RefCell<ProtocolCapture>> =
+    Mutex::new(RefCell::new(ProtocolCapture::new()));
+
+/// Pin level after a transition and when it happened, relative to the
+/// start of the capture
+#[derive(Clone, Copy, Debug)]
+pub struct ProtocolEdge {
+    pub time_us: u32,
+    pub level: bool,
+}
+
+/// Timing of one sensor read as seen by the driver, for comparison with a
+/// logic analyzer capture of the same read
+pub struct ProtocolCapture {
+    edges: [ProtocolEdge; CAPTURE_LEN],
+    len: usize,
+    start_ticks: u32,
+}
+
+impl ProtocolCapture {
+    pub const fn new() -> Self {
+        ProtocolCapture {
+            edges: [ProtocolEdge {
+                time_us: 0,
+                level: false,
+            }; CAPTURE_LEN],
+            len: 0,
+            start_ticks: 0,
+        }
+    }
+
+    /// Drops the previous capture, the next edge is at time 0
+    pub fn start(&mut self) {
+        self.len = 0;
+        self.start_ticks = mtime_ticks();
+    }
+
+    /// Records a transition to `level`. Edges past the buffer are dropped.
+    pub fn record(&mut self, level: bool) {
+        if self.len == CAPTURE_LEN {
+            return;
+        }
+        let time_us = mtime_ticks().wrapping_sub(self.start_ticks) / MTIME_TICKS_PER_US;
+        self.edges[self.len] = ProtocolEdge { time_us, level };
+        self.len += 1;
+    }
+
+    pub fn edges(&self) -> &[ProtocolEdge] {
+        &self.edges[..self.len]
+    }
+
+    /// Widths in µs of the line levels between the edges, laid out like
+    /// `validate::GOLDEN_TRACE`. None unless the read got every edge.
+    pub fn trace(&self) -> Option<[u32; MAX_TRANSITIONS]> {
+        if self.len < MAX_TRANSITIONS + 1 {
+            return None;
+        }
+        let mut trace = [0; MAX_TRANSITIONS];
+        for (width, pair) in trace.iter_mut().zip(self.edges().windows(2)) {
+            *width = pair[1].time_us.wrapping_sub(pair[0].time_us);
+        }
+        Some(trace)
+    }
+
+    /// Writes the capture in the Saleae Logic CSV format, for the
+    /// `dumpprotocol` command
+    pub fn write_csv(&self, out: &mut impl Write) -> fmt::Result {
+        out.write_str("time,channel1\n")?;
+        for edge in self.edges() {
+            writeln!(
+                out,
+                "{}.{:06},{}",
+                edge.time_us / 1_000_000,
+                edge.time_us % 1_000_000,
+                edge.level as u8
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for ProtocolCapture {
+    fn default() -> Self {
+        ProtocolCapture::new()
+    }
+}
+
+/// Starts a new capture if capturing is enabled
+pub fn capture_start() {
+    #[cfg(feature = "protocol_capture")]
+    riscv::interrupt::free(|cs| PROTOCOL_CAPTURE.borrow(*cs).borrow_mut().start());
+}
+
+/// Records an edge if capturing is enabled
+pub fn capture_edge(_level: bool) {
+    #[cfg(feature = "protocol_capture")]
+    riscv::interrupt::free(|cs| PROTOCOL_CAPTURE.borrow(*cs).borrow_mut().record(_level));
+}
+
+// Low word of the core timer, wraps after 214 s which is plenty for one read
+#[cfg(feature = "hal")]
+fn mtime_ticks() -> u32 {
+    unsafe { (*pac::CTIMER::ptr()).mtime_lo.read().bits() }
+}
+
+// No core timer on the host, every edge is at time 0
+#[cfg(not(feature = "hal"))]
+fn mtime_ticks() -> u32 {
+    0
+}
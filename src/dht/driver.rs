@@ -0,0 +1,95 @@
+use embedded_hal::blocking::delay::DelayUs;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+use super::capture::EdgeCapture;
+use super::machine::Frame;
+use super::sm::{DhtSm, Timing};
+use super::variant::DhtVariant;
+use super::{IntoInputPin, IntoOutputPin, SensorError};
+use crate::sensor::WeatherSensor;
+use crate::types::SensorReading;
+
+/// Default timeout for a single pin transition, in microseconds
+pub const DEFAULT_TIMEOUT_US: u32 = 500;
+
+/// DHT sensor on a single data pin. The pin is switched between its
+/// output type `OUT` for the start signal and its input type `IN` for the
+/// response, so the driver owns whichever of the two the pin currently is.
+///
+/// The driver keeps no global state: the start signal is timed with the
+/// delay passed to `read`, the response with the edge capture given to `new`.
+///
+/// `V` is the sensor model, `Dht11` or `Dht22`.
+pub struct Dht<IN, OUT, CAP, V> {
+    // None only while a read is in progress
+    sm: Option<DhtSm<IN, OUT, V>>,
+    capture: CAP,
+    timing: Timing,
+    last_frame: Option<Frame>,
+}
+
+impl<IN, OUT, CAP, V> Dht<IN, OUT, CAP, V>
+where
+    IN: InputPin + IntoOutputPin<Output = OUT>,
+    OUT: OutputPin + IntoInputPin<Input = IN>,
+    CAP: EdgeCapture,
+    V: DhtVariant,
+{
+    /// `capture` timestamps the edges on the same pin as `out_pin`
+    pub fn new(out_pin: OUT, capture: CAP) -> Self {
+        Dht {
+            sm: Some(DhtSm::new(out_pin)),
+            capture,
+            timing: Timing {
+                timeout_us: DEFAULT_TIMEOUT_US,
+            },
+            last_frame: None,
+        }
+    }
+
+    pub fn set_timeout_us(&mut self, timeout_us: u32) {
+        self.timing.timeout_us = timeout_us;
+    }
+
+    /// Sends the start signal and reads one frame. Blocks for about 270 ms.
+    pub fn read(&mut self, delay: &mut impl DelayUs<u32>) -> Result<SensorReading, SensorError> {
+        let mut sm = self.sm.take().ok_or(SensorError::PinError)?;
+
+        // The sensor sends the whole frame right after the start signal, so run until it is complete
+        loop {
+            sm = sm.advance(delay, &mut self.capture, self.timing);
+
+            if let DhtSm::Completing { frame, result, .. } = &sm {
+                let (frame, result) = (*frame, *result);
+                self.last_frame = Some(frame);
+
+                // Back to idle for the next read
+                self.sm = Some(sm.advance(delay, &mut self.capture, self.timing));
+                return result;
+            }
+
+            // Setting the pin high failed, the state machine stays idle
+            if let DhtSm::Idle { .. } = &sm {
+                self.sm = Some(sm);
+                return Err(SensorError::PinError);
+            }
+        }
+    }
+
+    /// Bits and pulse widths of the latest read, complete or not
+    pub fn last_frame(&self) -> Option<&Frame> {
+        self.last_frame.as_ref()
+    }
+}
+
+impl<IN, OUT, CAP, V> WeatherSensor for Dht<IN, OUT, CAP, V>
+where
+    IN: InputPin + IntoOutputPin<Output = OUT>,
+    OUT: OutputPin + IntoInputPin<Input = IN>,
+    CAP: EdgeCapture,
+    V: DhtVariant,
+{
+    fn read(&mut self, delay: &mut impl DelayUs<u32>) -> Result<SensorReading, SensorError> {
+        Dht::read(self, delay)
+    }
+}
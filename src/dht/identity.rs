@@ -0,0 +1,47 @@
+use core::fmt;
+
+use super::protocol::{BIT_THRESHOLD_US, FRAME_BITS};
+
+// Pulse widths are rounded to this many microseconds before hashing so
+// that small jitter between reads does not change the identity
+const QUANTUM_US: u32 = 4;
+
+
+/// 64-bit identity of the connected sensor. DHT sensors have no ROM code
+/// to read, so the identity is a fingerprint of the sensor's bit timing
+/// (its internal oscillator) taken from the first complete frame.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SensorIdentity(pub u64);
+
+impl SensorIdentity {
+    pub fn from_pulse_widths(pulse_widths_us: &[u32; FRAME_BITS]) -> SensorIdentity {
+        // Average width of 0-bits and 1-bits, independent of the data itself
+        let (mut zero_sum, mut zero_n, mut one_sum, mut one_n) = (0, 0, 0, 0);
+        for &w in pulse_widths_us.iter() {
+            if w < BIT_THRESHOLD_US {
+                zero_sum += w;
+                zero_n += 1;
+            } else {
+                one_sum += w;
+                one_n += 1;
+            }
+        }
+        let zero_avg = zero_sum / zero_n.max(1) / QUANTUM_US;
+        let one_avg = one_sum / one_n.max(1) / QUANTUM_US;
+
+        // FNV-1a over the quantized averages
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for byte in zero_avg.to_le_bytes().iter().chain(one_avg.to_le_bytes().iter()) {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+
+        SensorIdentity(hash)
+    }
+}
+
+impl fmt::Display for SensorIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
@@ -0,0 +1,163 @@
+use core::marker::PhantomData;
+
+use super::protocol::{FRAME_BITS, PULLHIGH_DELAY_MS, RELEASE_DELAY_US, SKIP_TRANSITIONS};
+use super::variant::DhtVariant;
+use super::SensorError;
+use crate::types::SensorReading;
+
+/// Bits of one sensor frame collected so far
+#[derive(Clone, Copy)]
+pub struct Frame {
+    // Storing read data, first byte for humidity, 3rd and 4th for temperature
+    pub data: [u8; 5],
+    // Measured pulse width of each data bit in microseconds
+    pub pulse_widths_us: [u32; FRAME_BITS],
+    // Number of bits read
+    pub bit: u8,
+}
+
+/// Step of the protocol a `DhtStateMachine` is in
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum State {
+    // Host keeps the line high before the start signal
+    PullHigh,
+    // Host pulls the line low as the start signal
+    PullLow,
+    // Host releases the line to the sensor
+    Release,
+    // Sensor response before the first bit
+    WaitAck,
+    ReadBit { index: u8 },
+    // All bits read, checksum not yet checked
+    Verify,
+    Complete(SensorReading),
+    Failed(SensorError),
+}
+
+/// Protocol of one read without any hardware. `step` is fed the level of
+/// the line and how long it took to get there since the previous step:
+/// for the host phases the level the host drove and for how long, for the
+/// response each edge and the time since the edge before it.
+///
+/// `V` is the sensor model, it sets the start signal and the decoding.
+pub struct DhtStateMachine<V> {
+    state: State,
+    frame: Frame,
+    // Level of the line after the latest step
+    line_high: bool,
+    // Edges of the sensor response seen in WaitAck
+    ack_edges: u8,
+    timeout_us: u32,
+    _variant: PhantomData<V>,
+}
+
+impl<V: DhtVariant> DhtStateMachine<V> {
+    /// `timeout_us` is the longest the line may stay at one level during the response
+    pub fn new(timeout_us: u32) -> Self {
+        DhtStateMachine {
+            state: State::PullHigh,
+            frame: Frame {
+                data: [0; 5],
+                pulse_widths_us: [0; FRAME_BITS],
+                bit: 0,
+            },
+            line_high: true,
+            ack_edges: 0,
+            timeout_us,
+            _variant: PhantomData,
+        }
+    }
+
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    /// Bits and pulse widths collected so far
+    pub fn frame(&self) -> &Frame {
+        &self.frame
+    }
+
+    /// Level of the line after the latest step, the next edge goes the other way
+    pub fn line_high(&self) -> bool {
+        self.line_high
+    }
+
+    /// Processes the current state. Returns true once the machine has
+    /// reached `Complete` or `Failed`, after which further steps do nothing.
+    ///
+    /// A step with the same level as before, or one longer than the
+    /// timeout, means the line got stuck and fails the read.
+    pub fn step(&mut self, pin_high: bool, elapsed_us: u32) -> bool {
+        let stuck = pin_high == self.line_high || elapsed_us > self.timeout_us;
+
+        self.state = match self.state {
+            State::PullHigh if pin_high && elapsed_us >= PULLHIGH_DELAY_MS * 1000 => {
+                State::PullLow
+            }
+            State::PullLow if !pin_high && elapsed_us >= V::start_low_ms() as u32 * 1000 => {
+                State::Release
+            }
+            State::Release if pin_high && elapsed_us >= RELEASE_DELAY_US => State::WaitAck,
+            State::PullHigh | State::PullLow | State::Release => self.state,
+
+            // Release high, ack low, ack high and the low before the first bit
+            State::WaitAck if stuck => State::Failed(SensorError::Timeout { at_bit: 0 }),
+            State::WaitAck => {
+                self.ack_edges += 1;
+                if self.ack_edges as usize == SKIP_TRANSITIONS {
+                    State::ReadBit { index: 0 }
+                } else {
+                    State::WaitAck
+                }
+            }
+
+            // A line stuck high means the sensor has stopped sending
+            State::ReadBit { index } if stuck && self.line_high => {
+                State::Failed(SensorError::InsufficientBits { collected: index })
+            }
+            State::ReadBit { index } if stuck => {
+                State::Failed(SensorError::Timeout { at_bit: index })
+            }
+            // End of the low pulse before the bit
+            State::ReadBit { index } if pin_high => State::ReadBit { index },
+            // Length of the high pulse tells the bit value
+            State::ReadBit { index } => {
+                self.push_bit(index, elapsed_us);
+                if index as usize + 1 == FRAME_BITS {
+                    State::Verify
+                } else {
+                    State::ReadBit { index: index + 1 }
+                }
+            }
+
+            State::Verify => self.verify(),
+            State::Complete(_) | State::Failed(_) => return true,
+        };
+        self.line_high = pin_high;
+
+        // The sensor sends nothing more after the last bit, verify right away
+        if self.state == State::Verify {
+            self.state = self.verify();
+        }
+
+        matches!(self.state, State::Complete(_) | State::Failed(_))
+    }
+
+    // shove each bit into the storage bytes
+    fn push_bit(&mut self, index: u8, width_us: u32) {
+        let byte = (index / 8) as usize;
+        self.frame.pulse_widths_us[index as usize] = width_us;
+        self.frame.data[byte] <<= 1;
+        if width_us > V::bit_threshold_us() as u32 {
+            self.frame.data[byte] |= 1;
+        }
+        self.frame.bit = index + 1;
+    }
+
+    fn verify(&self) -> State {
+        match V::decode(self.frame.data) {
+            Ok(reading) => State::Complete(reading),
+            Err(error) => State::Failed(error),
+        }
+    }
+}
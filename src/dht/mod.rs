@@ -0,0 +1,122 @@
+use core::fmt;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+#[cfg(feature = "hal")]
+use longan_nano::hal::gpio::gpioa::PA0;
+#[cfg(all(feature = "hal", feature = "second_sensor"))]
+use longan_nano::hal::gpio::gpiob::PB6;
+#[cfg(all(feature = "hal", feature = "second_sensor"))]
+use longan_nano::hal::gpio::OpenDrain;
+#[cfg(feature = "hal")]
+use longan_nano::hal::gpio::{Input, Output, PullUp, PushPull};
+
+pub mod capture;
+pub mod diag;
+pub mod driver;
+pub mod identity;
+pub mod machine;
+#[cfg(feature = "hal")]
+mod polled;
+pub mod protocol;
+pub mod quality;
+pub mod sm;
+#[cfg(feature = "hal")]
+mod timer4;
+pub mod validate;
+pub mod variant;
+
+pub use self::driver::Dht;
+pub use self::variant::{Dht11, Dht22, DhtVariant};
+
+/// Ways reading the sensor can fail
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SensorError {
+    // No response to the start signal, or the line was stuck low while reading bit at_bit
+    Timeout { at_bit: u8 },
+    // All 40 bits were read but the checksum byte did not match the sum of the data bytes
+    ChecksumMismatch { expected: u8, got: u8 },
+    // The sensor released the line before sending all 40 bits
+    InsufficientBits { collected: u8 },
+    // Driving or reading the pin failed, or the sensor is not set up
+    PinError,
+}
+
+// For the error reports on the UART
+impl fmt::Display for SensorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SensorError::Timeout { at_bit } => write!(f, "timeout at bit {}", at_bit),
+            SensorError::ChecksumMismatch { expected, got } => {
+                write!(f, "checksum {:02X}, expected {:02X}", got, expected)
+            }
+            SensorError::InsufficientBits { collected } => {
+                write!(f, "only {} of 40 bits", collected)
+            }
+            SensorError::PinError => f.write_str("pin error"),
+        }
+    }
+}
+
+/// Output pin that can be turned into an input, for HALs where the pin
+/// mode is part of the type
+pub trait IntoInputPin {
+    type Input: InputPin;
+
+    fn into_input_pin(self) -> Self::Input;
+}
+
+/// Input pin that can be turned into an output
+pub trait IntoOutputPin {
+    type Output: OutputPin;
+
+    fn into_output_pin(self) -> Self::Output;
+}
+
+// Sensor data pin on the Longan Nano
+#[cfg(feature = "hal")]
+pub type OutPin = PA0<Output<PushPull>>;
+#[cfg(feature = "hal")]
+pub type InPin = PA0<Input<PullUp>>;
+
+#[cfg(feature = "hal")]
+impl IntoInputPin for OutPin {
+    type Input = InPin;
+
+    fn into_input_pin(self) -> InPin {
+        self.into_pull_up_input()
+    }
+}
+
+#[cfg(feature = "hal")]
+impl IntoOutputPin for InPin {
+    type Output = OutPin;
+
+    fn into_output_pin(self) -> OutPin {
+        self.into_push_pull_output()
+    }
+}
+
+// Data pin of the second sensor. PA0 is the only pin with a free timer
+// channel, the second sensor's edges are timed by polling.
+#[cfg(all(feature = "hal", feature = "second_sensor"))]
+pub type SecondOutPin = PB6<Output<OpenDrain>>;
+#[cfg(all(feature = "hal", feature = "second_sensor"))]
+pub type SecondInPin = PB6<Input<PullUp>>;
+
+#[cfg(all(feature = "hal", feature = "second_sensor"))]
+impl IntoInputPin for SecondOutPin {
+    type Input = SecondInPin;
+
+    fn into_input_pin(self) -> SecondInPin {
+        self.into_pull_up_input()
+    }
+}
+
+#[cfg(all(feature = "hal", feature = "second_sensor"))]
+impl IntoOutputPin for SecondInPin {
+    type Output = SecondOutPin;
+
+    fn into_output_pin(self) -> SecondOutPin {
+        self.into_open_drain_output()
+    }
+}
@@ -0,0 +1,42 @@
+use longan_nano::hal::pac;
+use riscv::register::mcycle;
+
+use super::capture::EdgeCapture;
+use crate::SYSCLK_MHZ;
+
+/// Edge timestamps for a sensor on a port B pin without a free timer
+/// channel. The pin is polled and the cycle counter read when its level
+/// changes, so the timestamps are a few microseconds late. Good enough
+/// for the 26/70 µs bits of a DHT while interrupts are disabled.
+pub struct PolledCapture {
+    // GPIOB_ISTAT bit of the pin
+    mask: u32,
+}
+
+impl PolledCapture {
+    /// `pin` is the pin number on port B, it must be in an input mode
+    /// whenever an edge is waited for
+    pub fn new(pin: u8) -> Self {
+        PolledCapture { mask: 1 << pin }
+    }
+}
+
+impl EdgeCapture for PolledCapture {
+    fn wait_edge(&mut self, rising: bool, timeout_us: u32) -> Option<u16> {
+        let gpiob = unsafe { &*pac::GPIOB::ptr() };
+        // The low half of the counter is enough for the timeout and avoids a
+        // 64-bit division on every poll
+        let start = mcycle::read() as u32;
+        let timeout_cycles = timeout_us * SYSCLK_MHZ;
+
+        loop {
+            let high = gpiob.istat.read().bits() & self.mask != 0;
+            if high == rising {
+                return Some((mcycle::read64() / SYSCLK_MHZ as u64) as u16);
+            }
+            if (mcycle::read() as u32).wrapping_sub(start) > timeout_cycles {
+                return None;
+            }
+        }
+    }
+}
@@ -0,0 +1,33 @@
+//! Timing of the DHT11 single-wire protocol. Section numbers refer to the
+//! Aosong DHT11 datasheet.
+
+/// Line held high before the start signal so the sensor is idle, in
+/// milliseconds. The sensor needs at least 1 s after power-on and should
+/// not be read more than once per second (section 5).
+pub const PULLHIGH_DELAY_MS: u32 = 250;
+
+/// Start signal: the host pulls the line low for at least 18 ms so the
+/// sensor detects it (section 5.2, figure 3)
+pub const PULLLOW_DELAY_MS: u32 = 20;
+
+/// Host pulls the line back up and waits 20-40 µs for the sensor's
+/// response (section 5.2)
+pub const RELEASE_DELAY_US: u32 = 40;
+
+/// Edges of the response before the first data bit: the host's release
+/// falling into the sensor's 80 µs low, its 80 µs high and the 50 µs low
+/// that starts the first bit (section 5.2, figure 3)
+pub const SKIP_TRANSITIONS: usize = 4;
+
+/// Data bits in a frame: humidity, temperature and checksum bytes
+/// (section 5.1)
+pub const FRAME_BITS: usize = 40;
+
+/// Edges of a complete response: the skipped ones, a rising and falling
+/// edge per bit and the release of the line after the last bit. 85 in the
+/// reference implementation's MAXTIMINGS.
+pub const MAX_TRANSITIONS: usize = SKIP_TRANSITIONS + 2 * FRAME_BITS + 1;
+
+/// High pulse length in µs separating the bits: 26-28 µs means 0, 70 µs
+/// means 1 (section 5.3, figures 4 and 5)
+pub const BIT_THRESHOLD_US: u32 = 48;
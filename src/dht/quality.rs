@@ -0,0 +1,109 @@
+use core::fmt;
+use core::ops::RangeInclusive;
+
+use super::protocol::{BIT_THRESHOLD_US, FRAME_BITS};
+
+// Pulse widths (us) within spec for a 0-bit and a 1-bit
+const GOOD_ZERO_US: RangeInclusive<u32> = 20..=30;
+const GOOD_ONE_US: RangeInclusive<u32> = 60..=80;
+
+// Bits closer than this to BIT_THRESHOLD_US could have been decoded either way
+const MARGINAL_MARGIN_US: u32 = 5;
+
+/// Bit error rate above which the sensor health is degraded to Warning
+pub const MAX_BER: f32 = 0.05;
+
+/// Sensor health shown on the metrics page
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SensorHealth {
+    Ok,
+    // Bit error rate above MAX_BER, the reads still pass but barely
+    Warning,
+}
+
+impl SensorHealth {
+    pub fn label(&self) -> &'static str {
+        match self {
+            SensorHealth::Ok => "OK",
+            SensorHealth::Warning => "WARN",
+        }
+    }
+}
+
+/// Read quality in percent: the share of the 40 data bits whose pulse
+/// width was within the specified timing window. A falling trend is an
+/// early sign of a degrading sensor.
+pub fn compute_read_quality(pulse_widths_us: &[u32; FRAME_BITS]) -> u8 {
+    let good = pulse_widths_us
+        .iter()
+        .filter(|&&w| GOOD_ZERO_US.contains(&w) || GOOD_ONE_US.contains(&w))
+        .count();
+
+    (good * 100 / pulse_widths_us.len()) as u8
+}
+
+/// Share of received bits whose pulse width was close to the 0/1 threshold
+#[derive(Clone, Copy, Debug)]
+pub struct BitErrorRate {
+    pub marginal_bits: u32,
+    pub total_bits: u32,
+}
+
+impl BitErrorRate {
+    pub const fn new() -> Self {
+        BitErrorRate {
+            marginal_bits: 0,
+            total_bits: 0,
+        }
+    }
+
+    /// Adds the bits of one frame
+    pub fn update(&mut self, pulse_widths_us: &[u32; FRAME_BITS]) {
+        let marginal = pulse_widths_us
+            .iter()
+            .filter(|&&w| w.abs_diff(BIT_THRESHOLD_US) < MARGINAL_MARGIN_US)
+            .count();
+
+        self.marginal_bits = self.marginal_bits.saturating_add(marginal as u32);
+        self.total_bits = self.total_bits.saturating_add(pulse_widths_us.len() as u32);
+    }
+
+    pub fn ber(&self) -> f32 {
+        self.marginal_bits as f32 / self.total_bits.max(1) as f32
+    }
+
+    pub fn is_degraded(&self) -> bool {
+        self.ber() > MAX_BER
+    }
+
+    /// Warning once the bit error rate is past MAX_BER
+    pub fn health(&self) -> SensorHealth {
+        if self.is_degraded() {
+            SensorHealth::Warning
+        } else {
+            SensorHealth::Ok
+        }
+    }
+}
+
+impl Default for BitErrorRate {
+    fn default() -> Self {
+        BitErrorRate::new()
+    }
+}
+
+// Written as e.g. "BER: 0.0023 (2.3 per 1000 bits)" for the `:ber` command.
+// Both numbers are the marginal bits per 10000, formatted without floats.
+impl fmt::Display for BitErrorRate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let per_10000 = self.marginal_bits as u64 * 10_000 / self.total_bits.max(1) as u64;
+        write!(
+            f,
+            "BER: {}.{:04} ({}.{} per 1000 bits)",
+            per_10000 / 10_000,
+            per_10000 % 10_000,
+            per_10000 / 10,
+            per_10000 % 10
+        )
+    }
+}
@@ -0,0 +1,263 @@
+use embedded_hal::blocking::delay::DelayUs;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+
+use super::capture::EdgeCapture;
+use super::diag::{capture_edge, capture_start};
+use super::machine::{DhtStateMachine, Frame, State};
+use super::protocol::{PULLHIGH_DELAY_MS, RELEASE_DELAY_US};
+use super::variant::DhtVariant;
+use super::{IntoInputPin, IntoOutputPin, SensorError};
+use crate::types::SensorReading;
+
+/// Timing of a read
+#[derive(Clone, Copy)]
+pub struct Timing {
+    // Longest wait for a single edge
+    pub timeout_us: u32,
+}
+
+/// One read of the sensor as a state machine. Each state owns exactly the
+/// pin it needs, in the mode it needs, and `advance` consumes the state so
+/// the pin moves linearly from one step to the next. The protocol itself
+/// is left to the `DhtStateMachine` carried along, these states only drive
+/// the pin and feed it what happened on the line.
+///
+/// `Listening` and `Decoding` are timing critical and have to be advanced
+/// back to back, the sensor does not wait between bits. Their pulse widths
+/// come from hardware edge timestamps, not from delays.
+pub enum DhtSm<IN, OUT, V> {
+    Idle { out_pin: OUT },
+    Requesting { out_pin: OUT, machine: DhtStateMachine<V> },
+    Listening { in_pin: IN, machine: DhtStateMachine<V> },
+    // last_edge_us is the timestamp of the latest edge fed to the machine
+    Decoding {
+        in_pin: IN,
+        machine: DhtStateMachine<V>,
+        last_edge_us: Option<u16>,
+    },
+    Completing {
+        in_pin: IN,
+        frame: Frame,
+        result: Result<SensorReading, SensorError>,
+    },
+}
+
+impl<IN, OUT, V> DhtSm<IN, OUT, V>
+where
+    IN: InputPin + IntoOutputPin<Output = OUT>,
+    OUT: OutputPin + IntoInputPin<Input = IN>,
+    V: DhtVariant,
+{
+    pub fn new(out_pin: OUT) -> Self {
+        DhtSm::Idle { out_pin }
+    }
+
+    pub fn advance(
+        self,
+        delay: &mut impl DelayUs<u32>,
+        capture: &mut impl EdgeCapture,
+        timing: Timing,
+    ) -> Self {
+        match self {
+            // Keep the line high before the start signal
+            DhtSm::Idle { mut out_pin } => {
+                if out_pin.set_high().is_err() {
+                    return DhtSm::Idle { out_pin };
+                }
+                delay.delay_us(PULLHIGH_DELAY_MS * 1000);
+
+                let mut machine = DhtStateMachine::new(timing.timeout_us);
+                machine.step(true, PULLHIGH_DELAY_MS * 1000);
+                DhtSm::Requesting { out_pin, machine }
+            }
+
+            // Start signal, then release the line to the sensor
+            DhtSm::Requesting {
+                mut out_pin,
+                mut machine,
+            } => {
+                capture_start();
+                let _ = out_pin.set_low();
+                capture_edge(false);
+                let start_low_us = V::start_low_ms() as u32 * 1000;
+                delay.delay_us(start_low_us);
+                machine.step(false, start_low_us);
+
+                // The sensor pulls the line low 20-40 µs after it is released,
+                // before the response is listened for
+                capture.arm(false);
+                let _ = out_pin.set_high();
+                capture_edge(true);
+                delay.delay_us(RELEASE_DELAY_US);
+                machine.step(true, RELEASE_DELAY_US);
+
+                DhtSm::Listening {
+                    in_pin: out_pin.into_input_pin(),
+                    machine,
+                }
+            }
+
+            // Sensor response up to the first bit
+            DhtSm::Listening {
+                in_pin,
+                mut machine,
+            } => {
+                let mut last_edge_us = None;
+                while machine.state() == State::WaitAck {
+                    feed_edge(&mut machine, capture, &mut last_edge_us, timing);
+                }
+                match machine.state() {
+                    State::ReadBit { .. } => DhtSm::Decoding {
+                        in_pin,
+                        machine,
+                        last_edge_us,
+                    },
+                    _ => complete(in_pin, &machine),
+                }
+            }
+
+            // All 40 bits, each a low pulse and a high pulse
+            DhtSm::Decoding {
+                in_pin,
+                mut machine,
+                mut last_edge_us,
+            } => {
+                while !feed_edge(&mut machine, capture, &mut last_edge_us, timing) {}
+                complete(in_pin, &machine)
+            }
+
+            // Hand the line back to the output side for the next read
+            DhtSm::Completing { in_pin, .. } => DhtSm::Idle {
+                out_pin: in_pin.into_output_pin(),
+            },
+        }
+    }
+}
+
+// Waits for the edge the machine expects next and steps it with the time
+// since the previous edge. Returns true once the machine is done.
+fn feed_edge<V: DhtVariant>(
+    machine: &mut DhtStateMachine<V>,
+    capture: &mut impl EdgeCapture,
+    last_edge_us: &mut Option<u16>,
+    timing: Timing,
+) -> bool {
+    let rising = !machine.line_high();
+    match wait_edge(capture, rising, timing) {
+        Some(edge_us) => {
+            let elapsed_us = last_edge_us.map_or(0, |last| edge_us.wrapping_sub(last) as u32);
+            *last_edge_us = Some(edge_us);
+            machine.step(rising, elapsed_us)
+        }
+        // The line stayed where it was for longer than the timeout
+        None => machine.step(!rising, timing.timeout_us + 1),
+    }
+}
+
+// Final state of a read once the machine is done
+fn complete<IN, OUT, V: DhtVariant>(
+    in_pin: IN,
+    machine: &DhtStateMachine<V>,
+) -> DhtSm<IN, OUT, V> {
+    let result = match machine.state() {
+        State::Complete(reading) => Ok(reading),
+        State::Failed(error) => Err(error),
+        // Not reached, the machine is only completed once it is done
+        _ => Err(SensorError::PinError),
+    };
+    DhtSm::Completing {
+        in_pin,
+        frame: *machine.frame(),
+        result,
+    }
+}
+
+// Waits for the next edge in the given direction and returns its hardware
+// timestamp, or None if the line did not change within the timeout
+fn wait_edge(capture: &mut impl EdgeCapture, rising: bool, timing: Timing) -> Option<u16> {
+    let edge_us = capture.wait_edge(rising, timing.timeout_us)?;
+    capture_edge(rising);
+    Some(edge_us)
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::vec::Vec;
+
+    use super::*;
+    use crate::dht::variant::Dht11;
+    use crate::testing::{response_edges, MockDelay, MockPin, ScriptedCapture};
+
+    // 45% and 23.4°C with its checksum
+    const FRAME: [u8; 5] = [45, 0, 23, 4, 72];
+
+    // Runs one read from idle to completion on the given edges
+    fn run(edges: Vec<u16>) -> (Result<SensorReading, SensorError>, [u8; 5], u8) {
+        let mut capture = ScriptedCapture::new(edges);
+        let mut delay = MockDelay::new();
+        let timing = Timing { timeout_us: 500 };
+        let mut sm: DhtSm<MockPin, MockPin, Dht11> = DhtSm::new(MockPin::new());
+        loop {
+            sm = sm.advance(&mut delay, &mut capture, timing);
+            if let DhtSm::Completing { frame, result, .. } = sm {
+                return (result, frame.data, frame.bit);
+            }
+        }
+    }
+
+    #[test]
+    fn edge_timings_to_results() {
+        let mut corrupted = FRAME;
+        corrupted[4] = 73;
+
+        // The sensor stops after five bits with the line held low
+        let mut stuck_low = response_edges(FRAME, 5);
+        stuck_low.pop();
+
+        // The 80 µs low of the response stretched past the timeout
+        let mut slow_ack = response_edges(FRAME, 40);
+        for edge in slow_ack.iter_mut().skip(1) {
+            *edge = edge.wrapping_add(600);
+        }
+
+        let cases: [(Vec<u16>, Result<SensorReading, SensorError>, u8); 6] = [
+            (
+                response_edges(FRAME, 40),
+                Ok(SensorReading::new(23.4, 45.0)),
+                40,
+            ),
+            (
+                response_edges(corrupted, 40),
+                Err(SensorError::ChecksumMismatch {
+                    expected: 72,
+                    got: 73,
+                }),
+                40,
+            ),
+            (Vec::new(), Err(SensorError::Timeout { at_bit: 0 }), 0),
+            (
+                response_edges(FRAME, 12),
+                Err(SensorError::InsufficientBits { collected: 12 }),
+                12,
+            ),
+            (stuck_low, Err(SensorError::Timeout { at_bit: 5 }), 5),
+            (slow_ack, Err(SensorError::Timeout { at_bit: 0 }), 0),
+        ];
+
+        for (i, (edges, expected, bits)) in cases.iter().enumerate() {
+            let (result, _, collected) = run(edges.clone());
+            assert_eq!(result, *expected, "case {}", i);
+            assert_eq!(collected, *bits, "case {}", i);
+        }
+    }
+
+    #[test]
+    fn pulse_widths_decide_the_bits() {
+        let (result, data, _) = run(response_edges(FRAME, 40));
+
+        assert!(result.is_ok());
+        assert_eq!(data, FRAME);
+    }
+}
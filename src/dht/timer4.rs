@@ -0,0 +1,99 @@
+use longan_nano::hal::pac;
+
+use super::capture::EdgeCapture;
+
+// Timer clock is the 80 MHz system clock: APB1 runs at 40 MHz and timers on
+// a divided APB get twice the bus clock
+const TIMER_CLOCK_MHZ: u16 = 80;
+
+// TIMER_CHCTL0 CH0MS = 01: channel 0 is an input mapped on its own pin
+const CHCTL0_CH0MS_CI0: u16 = 0b01;
+
+// TIMER_CHCTL2 bits of channel 0
+const CHCTL2_CH0EN: u16 = 1 << 0;
+const CHCTL2_CH0P: u16 = 1 << 1;
+
+// TIMER_INTF channel 0 capture flag, cleared by writing 0
+const INTF_CH0IF: u16 = 1 << 1;
+
+// TIMER_CTL0 counter enable and TIMER_SWEVG update event
+const CTL0_CEN: u16 = 1 << 0;
+const SWEVG_UPG: u16 = 1 << 0;
+
+// RCU_APB1EN TIMER4 clock enable
+const APB1EN_TIMER4EN: u32 = 1 << 3;
+
+/// Input capture on channel 0 of TIMER4, which is on the sensor pin PA0.
+/// TIMER0 has no channel on PA0, its channels are on PA8-PA11.
+///
+/// The counter runs free at 1 MHz, so every captured value is a timestamp
+/// in microseconds taken by the hardware at the edge. The polarity is
+/// switched between the edges, the channel captures one edge direction at
+/// a time.
+pub struct Timer4Capture {
+    _timer: pac::TIMER4,
+}
+
+impl Timer4Capture {
+    /// The sensor pin must be in an input mode
+    pub fn new(timer: pac::TIMER4) -> Self {
+        // RCU is owned by the clock setup, only the TIMER4 clock gate is touched here
+        let rcu = unsafe { &*pac::RCU::ptr() };
+        rcu.apb1en.modify(|r, w| unsafe { w.bits(r.bits() | APB1EN_TIMER4EN) });
+
+        timer.psc.write(|w| unsafe { w.bits(TIMER_CLOCK_MHZ - 1) });
+        timer.car.write(|w| unsafe { w.bits(0xffff) });
+        timer.chctl0_input().write(|w| unsafe { w.bits(CHCTL0_CH0MS_CI0) });
+        timer.chctl2.write(|w| unsafe { w.bits(CHCTL2_CH0EN) });
+        // Load the prescaler now rather than at the first overflow
+        timer.swevg.write(|w| unsafe { w.bits(SWEVG_UPG) });
+        timer.ctl0.write(|w| unsafe { w.bits(CTL0_CEN) });
+
+        Timer4Capture { _timer: timer }
+    }
+
+    fn regs(&self) -> &pac::timer1::RegisterBlock {
+        unsafe { &*pac::TIMER4::ptr() }
+    }
+}
+
+// CHCTL2 polarity of channel 0 for capturing edges in the given direction
+fn polarity(rising: bool) -> u16 {
+    if rising {
+        0
+    } else {
+        CHCTL2_CH0P
+    }
+}
+
+impl EdgeCapture for Timer4Capture {
+    fn arm(&mut self, rising: bool) {
+        let timer = self.regs();
+        timer.chctl2.write(|w| unsafe { w.bits(CHCTL2_CH0EN | polarity(rising)) });
+        timer.intf.modify(|r, w| unsafe { w.bits(r.bits() & !INTF_CH0IF) });
+    }
+
+    // The flag is not cleared here, the edge may already have been captured
+    // since the capture was armed for it
+    fn wait_edge(&mut self, rising: bool, timeout_us: u32) -> Option<u16> {
+        // Armed for the other direction, e.g. after a timeout
+        if self.regs().chctl2.read().bits() & CHCTL2_CH0P != polarity(rising) {
+            self.arm(rising);
+        }
+
+        let timer = self.regs();
+        let start = timer.cnt.read().bits();
+        while timer.intf.read().bits() & INTF_CH0IF == 0 {
+            let elapsed = timer.cnt.read().bits().wrapping_sub(start);
+            if elapsed as u32 > timeout_us {
+                return None;
+            }
+        }
+        let edge_us = timer.ch0cv.read().bits();
+
+        // Armed for the opposite edge right away, it follows within microseconds
+        timer.intf.modify(|r, w| unsafe { w.bits(r.bits() & !INTF_CH0IF) });
+        timer.chctl2.write(|w| unsafe { w.bits(CHCTL2_CH0EN | polarity(!rising)) });
+        Some(edge_us)
+    }
+}
@@ -0,0 +1,157 @@
+use core::fmt::{self, Write};
+
+use super::protocol::{FRAME_BITS, MAX_TRANSITIONS};
+use super::variant::DhtVariant;
+use super::SensorError;
+use crate::types::SensorReading;
+
+/// Allowed deviation from the golden trace in factory testing
+pub const TOLERANCE_US: u32 = 10;
+
+/// Compares measured transition pulse widths against a golden trace.
+/// Returns `true` when both have the same number of transitions and every
+/// width is within `tolerance_us`.
+pub fn compare_trace(actual: &[u32], golden: &[u32], tolerance_us: u32) -> bool {
+    actual.len() == golden.len()
+        && actual
+            .iter()
+            .zip(golden.iter())
+            .all(|(&a, &g)| a.abs_diff(g) <= tolerance_us)
+}
+
+// Index in a trace of the high pulse of the first bit, after the start
+// signal, the wait for the response, the 80 µs low and high of the
+// response and the low before the bit
+const FIRST_BIT_HIGH: usize = 5;
+
+// Index of the first width set by the sensor, entry 0 is the host's start
+// signal whose length comes from the driver
+const FIRST_SENSOR_WIDTH: usize = 1;
+
+/// Synthetic reference trace of a DHT11 reading 38% and 22.7°C, written
+/// by hand with the widths scattered within the datasheet's ranges, not
+/// recorded from a sensor. Swap in a `:dumpprotocol` capture of a known
+/// good sensor before relying on it on the line. Each entry is the width
+/// in µs of the line level ending at one of the MAX_TRANSITIONS
+/// transitions: the host's 20 ms start signal (not sensor output), the
+/// wait for the response, the sensor's 80 µs low and 80 µs high, then the
+/// 50 µs low and the high pulse of each bit, and the 50 µs low after the
+/// last bit.
+pub const GOLDEN_TRACE: [u32; MAX_TRANSITIONS] = [
+    20012, 31, 82, 79, 52, 26, 50, 27, 54, 68, 49, 28, 49, 26, 53, 68, 53, 69, 49, 24, 52, 27, 49,
+    25, 49, 28, 52, 24, 53, 24, 50, 28, 49, 28, 53, 27, 49, 25, 49, 28, 50, 26, 52, 69, 53, 24, 53,
+    70, 53, 69, 49, 28, 53, 25, 51, 24, 53, 24, 53, 24, 53, 25, 52, 72, 52, 70, 52, 72, 52, 26, 51,
+    69, 50, 25, 49, 28, 51, 28, 52, 26, 54, 71, 51, 72, 51,
+];
+
+/// Decodes the data bits of a trace laid out like GOLDEN_TRACE, each from
+/// the width of its high pulse
+pub fn decode_trace<V: DhtVariant>(
+    trace: &[u32; MAX_TRANSITIONS],
+) -> Result<SensorReading, SensorError> {
+    let mut data = [0u8; 5];
+    for index in 0..FRAME_BITS {
+        let high_us = trace[FIRST_BIT_HIGH + 2 * index];
+        data[index / 8] <<= 1;
+        if high_us > V::bit_threshold_us() as u32 {
+            data[index / 8] |= 1;
+        }
+    }
+    V::decode(data)
+}
+
+/// Per data bit, whether both its low and its high pulse are within
+/// `tolerance_us` of `golden`
+pub fn bit_results(
+    actual: &[u32; MAX_TRANSITIONS],
+    golden: &[u32; MAX_TRANSITIONS],
+    tolerance_us: u32,
+) -> [bool; FRAME_BITS] {
+    let mut results = [false; FRAME_BITS];
+    for (index, result) in results.iter_mut().enumerate() {
+        let high = FIRST_BIT_HIGH + 2 * index;
+        *result = compare_trace(
+            &actual[high - 1..=high],
+            &golden[high - 1..=high],
+            tolerance_us,
+        );
+    }
+    results
+}
+
+/// Writes the `:validateprotocol` report of a measured trace against
+/// GOLDEN_TRACE: the response, one `.` (pass) or `X` (fail) per data bit
+/// and the overall result. The host's start signal is not compared.
+pub fn write_report(trace: &[u32; MAX_TRANSITIONS], out: &mut impl Write) -> fmt::Result {
+    let response = FIRST_SENSOR_WIDTH..FIRST_BIT_HIGH - 1;
+    let response_ok = compare_trace(
+        &trace[response.clone()],
+        &GOLDEN_TRACE[response],
+        TOLERANCE_US,
+    );
+    write!(out, "Response: {}\r\nBits: ", pass_fail(response_ok))?;
+    for &ok in bit_results(trace, &GOLDEN_TRACE, TOLERANCE_US).iter() {
+        out.write_char(if ok { '.' } else { 'X' })?;
+    }
+    let ok = compare_trace(
+        &trace[FIRST_SENSOR_WIDTH..],
+        &GOLDEN_TRACE[FIRST_SENSOR_WIDTH..],
+        TOLERANCE_US,
+    );
+    write!(out, "\r\nProtocol: {}", pass_fail(ok))
+}
+
+fn pass_fail(ok: bool) -> &'static str {
+    if ok {
+        "PASS"
+    } else {
+        "FAIL"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dht::variant::Dht11;
+
+    #[test]
+    fn golden_trace_decodes_to_recorded_reading() {
+        assert_eq!(
+            decode_trace::<Dht11>(&GOLDEN_TRACE),
+            Ok(SensorReading::new(22.7, 38.0))
+        );
+    }
+
+    #[test]
+    fn trace_within_tolerance_matches() {
+        let mut actual = GOLDEN_TRACE;
+        actual[9] += TOLERANCE_US;
+        actual[40] -= TOLERANCE_US;
+        assert!(compare_trace(&actual, &GOLDEN_TRACE, TOLERANCE_US));
+
+        actual[9] += 1;
+        assert!(!compare_trace(&actual, &GOLDEN_TRACE, TOLERANCE_US));
+    }
+
+    #[test]
+    fn start_signal_is_not_validated() {
+        let mut actual = GOLDEN_TRACE;
+        actual[0] = 18000;
+        actual[FIRST_BIT_HIGH + 2] += TOLERANCE_US + 1;
+        let mut report: heapless::String<128> = heapless::String::new();
+        write_report(&actual, &mut report).unwrap();
+        assert_eq!(
+            report.as_str(),
+            "Response: PASS\r\nBits: .X......................................\r\nProtocol: FAIL"
+        );
+    }
+
+    #[test]
+    fn truncated_trace_does_not_match() {
+        assert!(!compare_trace(
+            &GOLDEN_TRACE[..MAX_TRANSITIONS - 1],
+            &GOLDEN_TRACE,
+            TOLERANCE_US
+        ));
+    }
+}
@@ -0,0 +1,98 @@
+use super::protocol::{BIT_THRESHOLD_US, PULLLOW_DELAY_MS};
+use super::SensorError;
+use crate::types::{Celsius, RelativeHumidity, SensorReading};
+
+/// Differences between the sensors of the DHT family. The framing is the
+/// same, the start signal and the meaning of the data bytes are not.
+pub trait DhtVariant {
+    /// Length of the start signal in milliseconds
+    fn start_low_ms() -> u8;
+
+    /// High pulse length in µs above which a bit is 1
+    fn bit_threshold_us() -> u8;
+
+    /// Verifies the checksum and converts the frame to a reading
+    fn decode(data: [u8; 5]) -> Result<SensorReading, SensorError>;
+}
+
+/// DHT11: whole degrees and percent, tenths of a degree in the 4th byte
+pub struct Dht11;
+
+/// DHT22 (AM2302): 16-bit humidity and temperature in tenths, sign in the
+/// top bit of the temperature
+pub struct Dht22;
+
+// The DHT22 datasheet asks for at least 1 ms of start signal, 2 ms leaves some margin
+const DHT22_START_LOW_MS: u8 = 2;
+
+impl DhtVariant for Dht11 {
+    fn start_low_ms() -> u8 {
+        PULLLOW_DELAY_MS as u8
+    }
+
+    fn bit_threshold_us() -> u8 {
+        BIT_THRESHOLD_US as u8
+    }
+
+    fn decode(data: [u8; 5]) -> Result<SensorReading, SensorError> {
+        verify_checksum(&data)?;
+
+        // Integer part in the 3rd byte, tenths in the low bits of the 4th
+        let tenths = data[2] as i16 * 10 + (data[3] & 0x7f) as i16;
+
+        // The left-most digit indicate the negative sign.
+        let t = if data[3] >= 128 {
+            Celsius(-tenths)
+        } else {
+            Celsius(tenths)
+        };
+
+        // Humidity rounded to whole percent, the DHT11 always sends 0 tenths
+        let h = RelativeHumidity(data[0].saturating_add((data[1] >= 5) as u8));
+
+        Ok(SensorReading::from_fixed(t, h))
+    }
+}
+
+impl DhtVariant for Dht22 {
+    fn start_low_ms() -> u8 {
+        DHT22_START_LOW_MS
+    }
+
+    // Same bit timing as the DHT11
+    fn bit_threshold_us() -> u8 {
+        BIT_THRESHOLD_US as u8
+    }
+
+    fn decode(data: [u8; 5]) -> Result<SensorReading, SensorError> {
+        verify_checksum(&data)?;
+
+        let hum_tenths = u16::from_be_bytes([data[0], data[1]]);
+        let temp_tenths = u16::from_be_bytes([data[2] & 0x7f, data[3]]) as i16;
+        let t = if data[2] & 0x80 != 0 {
+            Celsius(-temp_tenths)
+        } else {
+            Celsius(temp_tenths)
+        };
+
+        // Rounded to whole percent, the sensor is only accurate to ±2% anyway
+        let h = RelativeHumidity(((hum_tenths + 5) / 10).min(100) as u8);
+
+        Ok(SensorReading::from_fixed(t, h))
+    }
+}
+
+// The checksum byte is the low byte of the sum of the other four
+fn verify_checksum(data: &[u8; 5]) -> Result<(), SensorError> {
+    let checksum = data[0]
+        .wrapping_add(data[1])
+        .wrapping_add(data[2])
+        .wrapping_add(data[3]);
+    if data[4] != checksum {
+        return Err(SensorError::ChecksumMismatch {
+            expected: checksum,
+            got: data[4],
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,217 @@
+use core::cell::RefCell;
+use core::fmt::{self, Write};
+use core::sync::atomic::{AtomicU8, Ordering};
+use embedded_hal::digital::v2::{OutputPin, ToggleableOutputPin};
+use heapless::{HistoryBuffer, String};
+use longan_nano::hal::gpio::gpioa::PA2;
+use longan_nano::hal::gpio::{Output, PushPull};
+use riscv::interrupt::{free, Mutex};
+use riscv::register::mcycle;
+
+use crate::util::fmt::format_i32;
+
+// Blinks while the sensor keeps failing, None when PA2 is used by the multiplexer
+pub static STATUS_LED: Mutex<RefCell<Option<PA2<Output<PushPull>>>>> =
+    Mutex::new(RefCell::new(None));
+
+// Failed reads in a row, written after every read
+pub static CONSECUTIVE_FAILURES: AtomicU8 = AtomicU8::new(0);
+
+// Failed reads in a row after which the status LED starts blinking
+const BLINK_AFTER_FAILURES: u8 = 3;
+
+// Number of latest read results kept for analysis
+const HISTORY_LEN: usize = 100;
+
+// Minimum number of results before any pattern is reported
+const MIN_RESULTS: usize = 10;
+
+// This many failures in a row at the end of the history means the sensor is gone
+const PERMANENT_WINDOW: usize = 10;
+
+// Autocorrelation above these values is considered significant
+const CLUSTER_THRESHOLD: f32 = 0.5;
+const PERIOD_THRESHOLD: f32 = 0.5;
+
+/// Classification of how sensor read failures are distributed over time
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FailurePattern {
+    // Not enough failures (or results) to say anything
+    NoFailures,
+    // Failures spread evenly, typically electrical noise
+    Random,
+    // Failures repeat at a regular interval, typically power supply ripple
+    Periodic { period_s: u32 },
+    // Failures come in bursts, typically a temperature dependent contact issue
+    Clustered,
+    // No successful reads lately, broken sensor or wiring
+    Permanent,
+}
+
+// For the `F` command
+impl fmt::Display for FailurePattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FailurePattern::NoFailures => f.write_str("no failures"),
+            FailurePattern::Random => f.write_str("random"),
+            FailurePattern::Periodic { period_s } => write!(f, "periodic, every {} s", period_s),
+            FailurePattern::Clustered => f.write_str("clustered"),
+            FailurePattern::Permanent => f.write_str("permanent"),
+        }
+    }
+}
+
+/// Collects read results and classifies the failures in them
+pub struct FailurePatternAnalyzer {
+    // (success, time in seconds) of the latest reads
+    results: HistoryBuffer<(bool, u32), HISTORY_LEN>,
+}
+
+impl FailurePatternAnalyzer {
+    pub const fn new() -> Self {
+        FailurePatternAnalyzer {
+            results: HistoryBuffer::new(),
+        }
+    }
+
+    pub fn record_result(&mut self, success: bool, time_s: u32) {
+        self.results.write((success, time_s));
+    }
+
+    pub fn analyze(&self) -> FailurePattern {
+        let n = self.results.len();
+        let failures = self.results.oldest_ordered().filter(|r| !r.0).count();
+        if n < MIN_RESULTS || failures == 0 {
+            return FailurePattern::NoFailures;
+        }
+
+        if self
+            .results
+            .oldest_ordered()
+            .skip(n - PERMANENT_WINDOW)
+            .all(|r| !r.0)
+        {
+            return FailurePattern::Permanent;
+        }
+
+        // Bursts show up as strong correlation between neighbouring results
+        if self.autocorrelation(1) > CLUSTER_THRESHOLD {
+            return FailurePattern::Clustered;
+        }
+
+        // Search for the lag with the strongest correlation
+        let mut best_lag = 0;
+        let mut best_r = PERIOD_THRESHOLD;
+        for lag in 2..=n / 2 {
+            let r = self.autocorrelation(lag);
+            if r > best_r {
+                best_r = r;
+                best_lag = lag;
+            }
+        }
+
+        if best_lag > 0 {
+            let mut times = self.results.oldest_ordered().map(|r| r.1);
+            let first = times.next().unwrap_or(0);
+            let last = times.last().unwrap_or(first);
+            let interval_s = last.wrapping_sub(first) / (n as u32 - 1);
+            return FailurePattern::Periodic {
+                period_s: interval_s * best_lag as u32,
+            };
+        }
+
+        FailurePattern::Random
+    }
+
+    // Normalised autocorrelation of the failure indicator series at the given lag
+    fn autocorrelation(&self, lag: usize) -> f32 {
+        let n = self.results.len();
+        let value = |r: &(bool, u32)| if r.0 { 0.0 } else { 1.0 };
+        let mean = self.results.oldest_ordered().map(value).sum::<f32>() / n as f32;
+
+        let variance: f32 = self
+            .results
+            .oldest_ordered()
+            .map(|r| (value(r) - mean) * (value(r) - mean))
+            .sum();
+        if variance == 0.0 {
+            return 0.0;
+        }
+
+        let covariance: f32 = self
+            .results
+            .oldest_ordered()
+            .zip(self.results.oldest_ordered().skip(lag))
+            .map(|(a, b)| (value(a) - mean) * (value(b) - mean))
+            .sum();
+
+        covariance / variance
+    }
+}
+
+/// Counts failed reads in a row. A successful read resets the count and
+/// turns the status LED off.
+pub fn record_read_status(ok: bool) {
+    if ok {
+        CONSECUTIVE_FAILURES.store(0, Ordering::Relaxed);
+        free(|cs| {
+            if let Some(ref mut led) = *STATUS_LED.borrow(*cs).borrow_mut() {
+                led.set_low().ok();
+            }
+        });
+    } else {
+        let failures = CONSECUTIVE_FAILURES.load(Ordering::Relaxed);
+        CONSECUTIVE_FAILURES.store(failures.saturating_add(1), Ordering::Relaxed);
+    }
+}
+
+/// Toggles the status LED once BLINK_AFTER_FAILURES reads in a row have
+/// failed. Called on every TIMER1 tick, so the LED blinks at about 1 Hz.
+pub fn blink_status_led() {
+    if CONSECUTIVE_FAILURES.load(Ordering::Relaxed) < BLINK_AFTER_FAILURES {
+        return;
+    }
+    free(|cs| {
+        if let Some(ref mut led) = *STATUS_LED.borrow(*cs).borrow_mut() {
+            led.toggle().ok();
+        }
+    });
+}
+
+// Values the formatting benchmark goes through, the range of the displayed readings
+const BENCH_VALUES: [i32; 8] = [-400, -12, 0, 7, 45, 99, 235, 1000];
+
+// Passes over BENCH_VALUES per formatter
+const BENCH_ROUNDS: u32 = 10;
+
+/// Cycles spent formatting BENCH_VALUES with format_i32 and with write!,
+/// in that order, for the `b` command. format_i32 is expected to take less
+/// than half the cycles. Takes about 1 ms, run it outside critical sections.
+pub fn bench_format_i32() -> (u32, u32) {
+    let mut sink = 0;
+
+    let start = mcycle::read() as u32;
+    for _ in 0..BENCH_ROUNDS {
+        for value in BENCH_VALUES.iter() {
+            let mut num_buf = [0u8; 12];
+            // Volatile so the formatting isn't folded into constants
+            let value = unsafe { core::ptr::read_volatile(value) };
+            sink += format_i32(value, &mut num_buf).len();
+        }
+    }
+    let fast = (mcycle::read() as u32).wrapping_sub(start);
+
+    let start = mcycle::read() as u32;
+    for _ in 0..BENCH_ROUNDS {
+        for value in BENCH_VALUES.iter() {
+            let mut text: String<12> = String::new();
+            let value = unsafe { core::ptr::read_volatile(value) };
+            let _ = write!(text, "{}", value);
+            sink += text.len();
+        }
+    }
+    let slow = (mcycle::read() as u32).wrapping_sub(start);
+
+    unsafe { core::ptr::write_volatile(&mut sink, 0) };
+    (fast, slow)
+}
@@ -0,0 +1,96 @@
+use core::sync::atomic::{AtomicU32, Ordering};
+use longan_nano::hal::pac;
+
+/// Seconds without a button press or a significant change of the reading
+/// before the backlight dims
+pub const IDLE_TIMEOUT_S: u32 = 30;
+
+/// Brightness (%) while idle
+pub const DIM_PERCENT: u8 = 10;
+
+/// Brightness (%) while in use
+pub const FULL_PERCENT: u8 = 100;
+
+// TIMER1 ticks since the last activity, one per second
+static IDLE_TICKS: AtomicU32 = AtomicU32::new(0);
+
+// PWM period in timer ticks, 1 kHz at the 1 MHz counter clock
+const PWM_PERIOD: u16 = 1000;
+
+// TIMER0 is on APB2, which runs undivided at the 80 MHz system clock
+const TIMER_CLOCK_MHZ: u16 = 80;
+
+// TIMER_CHCTL1 CH3COMCTL = 110 (PWM mode 0) and CH3COMSEN (shadow register)
+const CHCTL1_CH3_PWM0: u16 = (0b110 << 12) | (1 << 11);
+
+// TIMER_CHCTL2 channel 3 output enable
+const CHCTL2_CH3EN: u16 = 1 << 12;
+
+// TIMER_CCHP primary output enable, needed on the advanced timer
+const CCHP_POEN: u16 = 1 << 15;
+
+// TIMER_CTL0 counter enable and TIMER_SWEVG update event
+const CTL0_CEN: u16 = 1 << 0;
+const SWEVG_UPG: u16 = 1 << 0;
+
+// RCU_APB2EN TIMER0 clock enable
+const APB2EN_TIMER0EN: u32 = 1 << 11;
+
+/// Counts a second without activity, called from the TIMER1 interrupt
+pub fn idle_tick() {
+    IDLE_TICKS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Restarts the idle timeout
+pub fn reset_idle() {
+    IDLE_TICKS.store(0, Ordering::Relaxed);
+}
+
+/// True once nothing has happened for IDLE_TIMEOUT_S
+pub fn is_idle() -> bool {
+    IDLE_TICKS.load(Ordering::Relaxed) > IDLE_TIMEOUT_S
+}
+
+/// LCD backlight driven by PWM on TIMER0 channel 3 (PA11). The stock
+/// Longan Nano has the backlight wired to the supply, the BLK pad of the
+/// panel has to be connected to PA11 for dimming to have an effect.
+///
+/// PA11 is also the USB D- line of the USB-C connector. It is the only
+/// TIMER0 channel left: PA8 is the alert output and PA9/PA10 are the UART.
+/// The firmware does not use USB, but with BLK connected flash over the
+/// debug probe rather than USB DFU.
+pub struct Backlight {
+    _timer: pac::TIMER0,
+}
+
+impl Backlight {
+    /// PA11 must be in alternate push-pull mode. Starts at full brightness.
+    pub fn new(timer: pac::TIMER0) -> Self {
+        // RCU is owned by the clock setup, only the TIMER0 clock gate is touched here
+        let rcu = unsafe { &*pac::RCU::ptr() };
+        rcu.apb2en.modify(|r, w| unsafe { w.bits(r.bits() | APB2EN_TIMER0EN) });
+
+        timer.psc.write(|w| unsafe { w.bits(TIMER_CLOCK_MHZ - 1) });
+        timer.car.write(|w| unsafe { w.bits(PWM_PERIOD - 1) });
+        timer.chctl1_output().write(|w| unsafe { w.bits(CHCTL1_CH3_PWM0) });
+        timer.chctl2.write(|w| unsafe { w.bits(CHCTL2_CH3EN) });
+        timer.cchp.write(|w| unsafe { w.bits(CCHP_POEN) });
+        // Load the prescaler now rather than at the first overflow
+        timer.swevg.write(|w| unsafe { w.bits(SWEVG_UPG) });
+        timer.ctl0.write(|w| unsafe { w.bits(CTL0_CEN) });
+
+        let mut backlight = Backlight { _timer: timer };
+        backlight.set_percent(FULL_PERCENT);
+        backlight
+    }
+
+    /// Duty cycle of the backlight, 0-100 %
+    pub fn set_percent(&mut self, percent: u8) {
+        let duty = (PWM_PERIOD as u32 * percent.min(100) as u32 / 100) as u16;
+        self.regs().ch3cv.write(|w| unsafe { w.bits(duty) });
+    }
+
+    fn regs(&self) -> &pac::timer0::RegisterBlock {
+        unsafe { &*pac::TIMER0::ptr() }
+    }
+}
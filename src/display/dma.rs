@@ -0,0 +1,217 @@
+use core::convert::Infallible;
+use core::ptr::{addr_of, addr_of_mut};
+use core::sync::atomic::{AtomicBool, Ordering};
+use embedded_graphics::{pixelcolor::Rgb565, prelude::*};
+use longan_nano::hal::pac;
+use longan_nano::lcd::Lcd;
+
+use super::screen_size;
+use crate::display_config::{BG_COLOR, GRAPH_GRID_COLOR, TEXT_COLOR};
+
+// Pixels of the panel in either orientation
+const FRAME_PIXELS: usize = 160 * 80;
+
+// Bytes of the longest row in RGB565, a landscape row
+const MAX_ROW_BYTES: usize = 160 * 2;
+
+// Colors a pixel of the frame buffer can take. Holds every color the UI
+// draws with, a color missing from it is drawn as the closest entry. BG_COLOR
+// comes first so that the zeroed buffer is a blank screen. At most 16
+// entries, the index has to fit in 4 bits.
+const PALETTE: [Rgb565; 12] = [
+    BG_COLOR,
+    TEXT_COLOR,
+    GRAPH_GRID_COLOR,
+    Rgb565::WHITE,
+    Rgb565::RED,
+    Rgb565::GREEN,
+    Rgb565::BLUE,
+    Rgb565::YELLOW,
+    Rgb565::CYAN,
+    Rgb565::MAGENTA,
+    // Text and grid colors of the other theme, see display_config
+    Rgb565::new(50, 50, 50),
+    Rgb565::new(16, 32, 16),
+];
+
+// Whole frame rendered by the main loop as palette indices, two pixels per
+// byte with the left one in the low nibble. A frame of RGB565 pixels would
+// need 25.6 KB, more than the 20 KB RAM of the GD32VF103C8.
+static mut FRAME_BUF: [u8; FRAME_PIXELS / 2] = [0; FRAME_PIXELS / 2];
+
+// RGB565 pixels of one row high byte first, as the ST7735 expects when the 8
+// bit SPI reads them in memory order. One row is filled while the other is
+// sent by DMA.
+static mut ROW_BUF: [[u8; MAX_ROW_BYTES]; 2] = [[0; MAX_ROW_BYTES]; 2];
+
+/// Set by the DMA interrupt when a row has been sent
+pub static DMA_DONE: AtomicBool = AtomicBool::new(true);
+
+// Address of the SPI0 data register, the DMA destination
+const SPI0_DATA_ADDR: u32 = 0x4001_300C;
+
+// SPI_CTL1 transmit DMA enable and SPI_STAT transmit ongoing
+const SPI_CTL1_DMATEN: u16 = 1 << 1;
+const SPI_STAT_TRANS: u16 = 1 << 7;
+
+// DMA_CH2CTL: enable, full transfer interrupt, memory to peripheral, memory
+// address increment. Both widths are left at 8 bit.
+const DMA_CHCTL_CHEN: u32 = 1 << 0;
+const DMA_CHCTL_FTFIE: u32 = 1 << 1;
+const DMA_CHCTL_DIR: u32 = 1 << 4;
+const DMA_CHCTL_MNAGA: u32 = 1 << 7;
+
+// DMA_INTC global flag clear of channel 2, clears all of its flags
+const DMA_INTC_GIFC2: u32 = 1 << 8;
+
+// RCU_AHBEN DMA0 clock enable
+const AHBEN_DMA0EN: u32 = 1 << 0;
+
+/// LCD drawn through a frame buffer in RAM. Drawing only touches the buffer,
+/// `flush` sends the frame row by row with DMA on channel 2 of DMA0, the
+/// SPI0 transmit request, and sleeps while each row is sent.
+pub struct DmaLcd {
+    lcd: Lcd,
+    // Latest color drawn and its palette index, text is drawn in runs of one color
+    last_color: (Rgb565, u8),
+}
+
+impl DmaLcd {
+    pub fn new(lcd: Lcd) -> Self {
+        // RCU is owned by the clock setup, only the DMA0 clock gate is touched here
+        let rcu = unsafe { &*pac::RCU::ptr() };
+        rcu.ahben.modify(|r, w| unsafe { w.bits(r.bits() | AHBEN_DMA0EN) });
+
+        DmaLcd {
+            lcd,
+            last_color: (PALETTE[0], 0),
+        }
+    }
+
+    /// Sends the frame buffer to the LCD. Returns once the last byte has
+    /// been shifted out, the core sleeps in wfi during the transfers.
+    pub fn flush(&mut self) {
+        let size = screen_size();
+        self.lcd
+            .set_address_window(0, 0, size.width as u16 - 1, size.height as u16 - 1)
+            .ok();
+        // RAMWR without pixels, leaves DC high for the data sent by DMA
+        self.lcd.write_pixels(core::iter::empty()).ok();
+
+        let spi = unsafe { &*pac::SPI0::ptr() };
+        spi.ctl1.modify(|r, w| unsafe { w.bits(r.bits() | SPI_CTL1_DMATEN) });
+
+        // Only the main loop flushes, and the row being filled is never the one being sent
+        let rows = unsafe { &mut *addr_of_mut!(ROW_BUF) };
+        let row_bytes = size.width as usize * 2;
+        for y in 0..size.height as usize {
+            let row = &mut rows[y % 2][..row_bytes];
+            fill_row(row, y * size.width as usize);
+            wait_dma();
+            start_dma(row);
+        }
+        wait_dma();
+
+        // The last byte is still shifting out when DMA finishes, the LCD
+        // driver must not toggle DC before it is gone
+        while spi.stat.read().bits() & SPI_STAT_TRANS != 0 {}
+        spi.ctl1.modify(|r, w| unsafe { w.bits(r.bits() & !SPI_CTL1_DMATEN) });
+        let dma = unsafe { &*pac::DMA0::ptr() };
+        dma.ch2ctl.write(|w| unsafe { w.bits(0) });
+    }
+
+    // Palette index of a color, the closest entry for one not in the palette
+    fn palette_index(&mut self, color: Rgb565) -> u8 {
+        if self.last_color.0 == color {
+            return self.last_color.1;
+        }
+        let index = PALETTE
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, entry)| color_distance(**entry, color))
+            .map_or(0, |(i, _)| i as u8);
+        self.last_color = (color, index);
+        index
+    }
+}
+
+// Squared distance of two colors, green halved to the 5 bit scale of the others
+fn color_distance(a: Rgb565, b: Rgb565) -> u32 {
+    let dr = a.r() as i32 - b.r() as i32;
+    let dg = (a.g() as i32 - b.g() as i32) / 2;
+    let db = a.b() as i32 - b.b() as i32;
+    (dr * dr + dg * dg + db * db) as u32
+}
+
+// Expands the pixels of one row starting at pixel `first` of the frame
+fn fill_row(row: &mut [u8], first: usize) {
+    let buf = unsafe { &*addr_of!(FRAME_BUF) };
+    for (i, out) in row.chunks_exact_mut(2).enumerate() {
+        let pixel = first + i;
+        let index = (buf[pixel / 2] >> (pixel % 2 * 4)) & 0x0F;
+        out.copy_from_slice(&PALETTE[index as usize].into_storage().to_be_bytes());
+    }
+}
+
+// Starts sending `row` to the SPI0 data register
+fn start_dma(row: &[u8]) {
+    let dma = unsafe { &*pac::DMA0::ptr() };
+    DMA_DONE.store(false, Ordering::Relaxed);
+    dma.ch2ctl.write(|w| unsafe { w.bits(0) });
+    dma.ch2paddr.write(|w| unsafe { w.bits(SPI0_DATA_ADDR) });
+    dma.ch2maddr.write(|w| unsafe { w.bits(row.as_ptr() as u32) });
+    dma.ch2cnt.write(|w| unsafe { w.bits(row.len() as u32) });
+    dma.ch2ctl.write(|w| unsafe {
+        w.bits(DMA_CHCTL_CHEN | DMA_CHCTL_FTFIE | DMA_CHCTL_DIR | DMA_CHCTL_MNAGA)
+    });
+}
+
+// Sleeps until the row in flight has been sent. Other interrupts wake the
+// core too, so it sleeps again until DMA_DONE is set.
+fn wait_dma() {
+    while !DMA_DONE.load(Ordering::Acquire) {
+        unsafe { riscv::asm::wfi() };
+    }
+}
+
+impl OriginDimensions for DmaLcd {
+    fn size(&self) -> Size {
+        screen_size()
+    }
+}
+
+impl DrawTarget for DmaLcd {
+    type Color = Rgb565;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let size = screen_size();
+        for Pixel(point, color) in pixels {
+            if point.x < 0 || point.y < 0 {
+                continue;
+            }
+            let (x, y) = (point.x as u32, point.y as u32);
+            if x < size.width && y < size.height {
+                let index = self.palette_index(color);
+                let pixel = (y * size.width + x) as usize;
+                let shift = pixel % 2 * 4;
+                // Only the main loop draws, and never while a transfer is running
+                let byte = unsafe { &mut FRAME_BUF[pixel / 2] };
+                *byte = (*byte & !(0x0F << shift)) | (index << shift);
+            }
+        }
+        Ok(())
+    }
+}
+
+//Interrupt handler for the end of a row transfer
+#[allow(non_snake_case)]
+#[no_mangle]
+fn DMA0_CHANNEL2() {
+    let dma = unsafe { &*pac::DMA0::ptr() };
+    dma.intc.write(|w| unsafe { w.bits(DMA_INTC_GIFC2) });
+    DMA_DONE.store(true, Ordering::Release);
+}
@@ -0,0 +1,350 @@
+use core::cell::RefCell;
+use embedded_graphics::{
+    mono_font::{
+        iso_8859_1::FONT_6X10, MonoFont, MonoTextStyle, MonoTextStyleBuilder,
+    },
+    pixelcolor::Rgb565,
+    prelude::*,
+    primitives::{PrimitiveStyle, Rectangle},
+    text::{Alignment, Baseline, Text, TextStyleBuilder},
+};
+use heapless::String;
+use riscv::interrupt::{free, Mutex};
+
+use super::{layout_point, screen_size, temperature_unit, TemperatureUnit};
+use crate::derived::humidity_category;
+use crate::derived_metrics::{ComfortLevel, DerivedMetrics};
+use crate::display_config::{ALERT_COLOR, BG_COLOR, LINE_SPACING, TEXT_COLOR, UI_FONT};
+use crate::types::SensorReading;
+use crate::ui::widgets::draw_7segment_number;
+use crate::util::fmt::{format_i32, push_tenths, round_i32, to_tenths};
+
+// The DHT11 measures humidity in whole percent, so no decimal is shown
+const SHOW_HUMIDITY_DECIMAL: bool = false;
+
+// Height of the comfort banner at the bottom of the screen
+const BANNER_HEIGHT: u32 = 14;
+
+// Padded length of the heat index text, enough spaces to blank "HI: 113°F"
+const HI_TEXT_LEN: usize = 9;
+
+// Size of one 7-segment digit of the large temperature
+const LARGE_DIGIT_SIZE: Size = Size::new(20, 40);
+
+/// Draws a reading and the values derived from it on the main page
+pub trait DisplayLayout {
+    fn render(
+        &self,
+        data: &SensorReading,
+        derived: &DerivedMetrics,
+        lcd: &mut impl DrawTarget<Color = Rgb565>,
+    );
+
+    /// Shown in place of the reading when the latest read failed
+    fn render_error(&self, lcd: &mut impl DrawTarget<Color = Rgb565>);
+}
+
+/// Layouts of the main page, selectable at runtime
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum LayoutKind {
+    Simple,
+    LargeNumeric,
+}
+
+impl LayoutKind {
+    /// Layout after this one, wrapping to the first
+    pub fn next(&self) -> LayoutKind {
+        match self {
+            LayoutKind::Simple => LayoutKind::LargeNumeric,
+            LayoutKind::LargeNumeric => LayoutKind::Simple,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            LayoutKind::Simple => "simple",
+            LayoutKind::LargeNumeric => "large",
+        }
+    }
+}
+
+impl DisplayLayout for LayoutKind {
+    fn render(
+        &self,
+        data: &SensorReading,
+        derived: &DerivedMetrics,
+        lcd: &mut impl DrawTarget<Color = Rgb565>,
+    ) {
+        match self {
+            LayoutKind::Simple => SimpleLayout.render(data, derived, lcd),
+            LayoutKind::LargeNumeric => LargeNumericLayout.render(data, derived, lcd),
+        }
+    }
+
+    fn render_error(&self, lcd: &mut impl DrawTarget<Color = Rgb565>) {
+        match self {
+            LayoutKind::Simple => SimpleLayout.render_error(lcd),
+            LayoutKind::LargeNumeric => LargeNumericLayout.render_error(lcd),
+        }
+    }
+}
+
+// Layout of the main page, changed with the `l` command
+static LAYOUT: Mutex<RefCell<LayoutKind>> = Mutex::new(RefCell::new(LayoutKind::Simple));
+
+/// Layout currently used for the main page
+pub fn active_layout() -> LayoutKind {
+    free(|cs| *LAYOUT.borrow(*cs).borrow())
+}
+
+/// Switches to the next layout and returns it
+pub fn next_layout() -> LayoutKind {
+    free(|cs| {
+        let mut layout = LAYOUT.borrow(*cs).borrow_mut();
+        *layout = layout.next();
+        *layout
+    })
+}
+
+/// Temperature and humidity on two lines with the humidity category and
+/// dew point next to them
+pub struct SimpleLayout;
+
+impl DisplayLayout for SimpleLayout {
+    fn render(
+        &self,
+        data: &SensorReading,
+        derived: &DerivedMetrics,
+        lcd: &mut impl DrawTarget<Color = Rgb565>,
+    ) {
+        let style = text_style(&UI_FONT, TEXT_COLOR);
+        let unit = temperature_unit();
+        let mut num_buf = [0u8; 12];
+
+        let mut t_as_text: String<10> = String::new();
+        push_tenths(&mut t_as_text, to_tenths(unit.convert(data.temperature))).unwrap();
+        t_as_text.push_str(unit.suffix()).unwrap();
+        t_as_text.push(' ').unwrap(); // Push extra spaces to overwrite last print if string gets shorter (e.g. 12.0°C -> 9.0°C )
+        t_as_text.push(' ').unwrap();
+
+        Text::new(t_as_text.as_str(), layout_point(40, 35), style)
+            .draw(lcd)
+            .ok();
+
+        let mut h_as_text: String<10> = String::new();
+        let h_tenths = to_tenths(data.humidity);
+        if !data.has_humidity() {
+            h_as_text.push_str("--").unwrap();
+        } else if SHOW_HUMIDITY_DECIMAL {
+            push_tenths(&mut h_as_text, h_tenths).unwrap();
+        } else {
+            h_as_text
+                .push_str(format_i32((h_tenths + 5) / 10, &mut num_buf))
+                .unwrap();
+        }
+        h_as_text.push('%').unwrap();
+        h_as_text.push(' ').unwrap(); // Push extra spaces to overwrite last print if string gets shorter (e.g. 15% -> 9%)
+        Text::new(h_as_text.as_str(), layout_point(40, 35 + LINE_SPACING), style)
+            .draw(lcd)
+            .ok();
+
+        // Descriptive humidity category next to the percentage, padded to overwrite
+        // longer labels. A thermistor reading shows where it came from instead.
+        let (label, color) = if data.has_humidity() {
+            let category = humidity_category(data.humidity);
+            (category.label(), category.color())
+        } else {
+            ("NTC", TEXT_COLOR)
+        };
+        let mut category_text: String<7> = String::new();
+        category_text.push_str(label).unwrap();
+        while category_text.push(' ').is_ok() {}
+        Text::new(
+            category_text.as_str(),
+            layout_point(90, 35 + LINE_SPACING),
+            text_style(&UI_FONT, color),
+        )
+        .draw(lcd)
+        .ok();
+
+        // Everything below needs the humidity
+        if !data.has_humidity() {
+            return;
+        }
+
+        // Dew point in small font above the temperature, padded like the rows below
+        let mut dp_as_text: String<12> = String::new();
+        dp_as_text.push_str("Dp: ").unwrap();
+        dp_as_text
+            .push_str(format_i32(round_i32(unit.convert(derived.dew_point)), &mut num_buf))
+            .unwrap();
+        dp_as_text.push_str(unit.suffix()).unwrap();
+        while dp_as_text.push(' ').is_ok() {}
+        Text::new(
+            dp_as_text.as_str(),
+            layout_point(40, 10),
+            text_style(&FONT_6X10, TEXT_COLOR),
+        )
+        .draw(lcd)
+        .ok();
+
+        // Heat index next to the dew point, blanked when out of its domain. No
+        // room below the humidity row with the larger fonts, the banner is there.
+        let mut hi_as_text: String<12> = String::new();
+        if let Some(hi) = derived.heat_index {
+            hi_as_text.push_str("HI: ").unwrap();
+            hi_as_text
+                .push_str(format_i32(round_i32(unit.convert(hi)), &mut num_buf))
+                .unwrap();
+            hi_as_text.push_str(unit.suffix()).unwrap();
+        }
+        while hi_as_text.len() < HI_TEXT_LEN && hi_as_text.push(' ').is_ok() {}
+        Text::new(
+            hi_as_text.as_str(),
+            layout_point(112, 10),
+            text_style(&FONT_6X10, TEXT_COLOR),
+        )
+        .draw(lcd)
+        .ok();
+
+        draw_comfort_banner(lcd, derived.comfort);
+    }
+
+    fn render_error(&self, lcd: &mut impl DrawTarget<Color = Rgb565>) {
+        draw_sensor_error(lcd, layout_point(40, 35), 35 + LINE_SPACING);
+    }
+}
+
+/// Whole degrees as large 7-segment digits with the tenths next to them,
+/// humidity and dew point in a small row underneath
+pub struct LargeNumericLayout;
+
+impl DisplayLayout for LargeNumericLayout {
+    fn render(
+        &self,
+        data: &SensorReading,
+        derived: &DerivedMetrics,
+        lcd: &mut impl DrawTarget<Color = Rgb565>,
+    ) {
+        let unit = temperature_unit();
+        let tenths = to_tenths(unit.convert(data.temperature));
+        let abs = tenths.unsigned_abs();
+        let small_style = text_style(&FONT_6X10, TEXT_COLOR);
+
+        // Sign in front of the digits, a space overwrites an old minus
+        let sign = if tenths < 0 { "-" } else { " " };
+        Text::new(sign, layout_point(10, 28), small_style).draw(lcd).ok();
+
+        // The DHT sensors don't go past 99°C, in Fahrenheit a third digit is needed
+        let (digits, max) = match unit {
+            TemperatureUnit::Celsius => (2, 99),
+            TemperatureUnit::Fahrenheit => (3, 999),
+        };
+        draw_7segment_number(
+            lcd,
+            (abs / 10).min(max),
+            digits,
+            layout_point(20, 5),
+            LARGE_DIGIT_SIZE,
+            TEXT_COLOR,
+        );
+        let digit_gap = LARGE_DIGIT_SIZE.width as i32 / 4;
+        let digits_width = digits as i32 * (LARGE_DIGIT_SIZE.width as i32 + digit_gap);
+
+        let mut num_buf = [0u8; 12];
+        let mut fraction: String<6> = String::new();
+        fraction.push('.').unwrap();
+        fraction
+            .push_str(format_i32((abs % 10) as i32, &mut num_buf))
+            .unwrap();
+        fraction.push_str(unit.suffix()).unwrap();
+        Text::new(
+            fraction.as_str(),
+            layout_point(20 + digits_width, 45),
+            text_style(&UI_FONT, TEXT_COLOR),
+        )
+        .draw(lcd)
+        .ok();
+
+        // Humidity and dew point, padded to overwrite a longer previous row. A
+        // thermistor reading has neither.
+        let mut row: String<26> = String::new();
+        if data.has_humidity() {
+            row.push_str("RH ").unwrap();
+            row.push_str(format_i32(round_i32(data.humidity), &mut num_buf))
+                .unwrap();
+            row.push_str("%  Dp ").unwrap();
+            row.push_str(format_i32(round_i32(unit.convert(derived.dew_point)), &mut num_buf))
+                .unwrap();
+            row.push_str(unit.suffix()).unwrap();
+        } else {
+            row.push_str("RH --%  NTC").unwrap();
+        }
+        while row.push(' ').is_ok() {}
+        Text::new(row.as_str(), layout_point(20, 60), small_style)
+            .draw(lcd)
+            .ok();
+
+        if data.has_humidity() {
+            draw_comfort_banner(lcd, derived.comfort);
+        }
+    }
+
+    fn render_error(&self, lcd: &mut impl DrawTarget<Color = Rgb565>) {
+        draw_sensor_error(lcd, layout_point(20, 45), 60);
+    }
+}
+
+// "SENSOR ERR" in red at the temperature position, with the humidity row
+// whose baseline is at humidity_y blanked
+fn draw_sensor_error(lcd: &mut impl DrawTarget<Color = Rgb565>, position: Point, humidity_y: i32) {
+    Text::new("SENSOR ERR", position, text_style(&UI_FONT, ALERT_COLOR))
+        .draw(lcd)
+        .ok();
+
+    let font_height = UI_FONT.character_size.height;
+    let top = humidity_y - UI_FONT.baseline as i32;
+    Rectangle::new(Point::new(0, top), Size::new(screen_size().width, font_height))
+        .into_styled(PrimitiveStyle::with_fill(BG_COLOR))
+        .draw(lcd)
+        .ok();
+}
+
+// Text style on a black background so redrawn text overwrites the old one
+fn text_style(font: &'static MonoFont<'static>, color: Rgb565) -> MonoTextStyle<'static, Rgb565> {
+    MonoTextStyleBuilder::new()
+        .font(font)
+        .text_color(color)
+        .background_color(BG_COLOR)
+        .build()
+}
+
+// Colored banner across the bottom of the screen with the comfort level centered in it
+fn draw_comfort_banner(lcd: &mut impl DrawTarget<Color = Rgb565>, level: ComfortLevel) {
+    draw_banner(lcd, level.label(), level.color());
+}
+
+/// Warning in place of the comfort banner while the two sensors disagree
+pub fn draw_discrepancy_banner(lcd: &mut impl DrawTarget<Color = Rgb565>) {
+    draw_banner(lcd, "! DISCREPANCY", ALERT_COLOR);
+}
+
+// Banner across the bottom of the screen filled with color and the label centered in it
+fn draw_banner(lcd: &mut impl DrawTarget<Color = Rgb565>, label: &str, color: Rgb565) {
+    let size = screen_size();
+    let top_left = Point::new(0, (size.height - BANNER_HEIGHT) as i32);
+    Rectangle::new(top_left, Size::new(size.width, BANNER_HEIGHT))
+        .into_styled(PrimitiveStyle::with_fill(color))
+        .draw(lcd)
+        .ok();
+
+    let character_style = MonoTextStyle::new(&FONT_6X10, BG_COLOR);
+    let text_style = TextStyleBuilder::new()
+        .alignment(Alignment::Center)
+        .baseline(Baseline::Middle)
+        .build();
+    let center = top_left + Point::new(size.width as i32 / 2, BANNER_HEIGHT as i32 / 2);
+    Text::with_text_style(label, center, character_style, text_style)
+        .draw(lcd)
+        .ok();
+}
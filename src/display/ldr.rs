@@ -0,0 +1,101 @@
+use core::cell::RefCell;
+use embedded_hal::blocking::delay::DelayUs;
+use longan_nano::hal::gpio::gpioa::PA3;
+use longan_nano::hal::gpio::Analog;
+use longan_nano::hal::pac;
+use riscv::interrupt::{free, Mutex};
+
+use super::LDR_READING;
+
+// ADC channel of PA3
+const LDR_CHANNEL: u32 = 3;
+
+// RCU_APB2EN ADC1 clock enable
+const APB2EN_ADC1EN: u32 = 1 << 10;
+
+// RCU_CFG0 ADCPSC[1:0] = 11 with ADCPSC[2] = 0: APB2 / 8, 10 MHz, the same
+// as the thermistor sets for ADC0
+const CFG0_ADCPSC_MASK: u32 = (0b11 << 14) | (1 << 28);
+const CFG0_ADCPSC_DIV8: u32 = 0b11 << 14;
+
+// ADC_CTL1 bits
+const CTL1_ADCON: u32 = 1 << 0;
+const CTL1_CLB: u32 = 1 << 2;
+const CTL1_RSTCLB: u32 = 1 << 3;
+// ETSRC = 111 with ETERC: regular conversions started by SWRCST
+const CTL1_ETSRC_SWRCST: u32 = 0b111 << 17;
+const CTL1_ETERC: u32 = 1 << 20;
+const CTL1_SWRCST: u32 = 1 << 22;
+
+// ADC_SAMPT1 SPT3 = 111, 239.5 cycles, the LDR divider is high impedance in the dark
+const SAMPT1_SPT3_239_5: u32 = 0b111 << 9;
+
+// ADC_STAT end of conversion flag
+const STAT_EOC: u32 = 1 << 1;
+
+// Time from ADCON to the ADC being ready for calibration
+const POWER_UP_US: u32 = 10;
+
+// LDR sampled for the backlight, None before init
+pub static LDR: Mutex<RefCell<Option<Ldr>>> = Mutex::new(RefCell::new(None));
+
+/// Light-dependent resistor between 3.3 V and PA3 with a 10 kΩ resistor to
+/// ground, read through channel 3 of ADC1. ADC0 is left to the thermistor.
+/// More light reads higher.
+pub struct Ldr {
+    _adc: pac::ADC1,
+    _pin: PA3<Analog>,
+}
+
+impl Ldr {
+    /// Powers up and calibrates the ADC
+    pub fn new(adc: pac::ADC1, pin: PA3<Analog>, delay: &mut impl DelayUs<u32>) -> Self {
+        // RCU is owned by the clock setup, only the ADC1 clock gate and prescaler are touched here
+        let rcu = unsafe { &*pac::RCU::ptr() };
+        rcu.cfg0.modify(|r, w| unsafe {
+            w.bits((r.bits() & !CFG0_ADCPSC_MASK) | CFG0_ADCPSC_DIV8)
+        });
+        rcu.apb2en.modify(|r, w| unsafe { w.bits(r.bits() | APB2EN_ADC1EN) });
+
+        adc.sampt1.write(|w| unsafe { w.bits(SAMPT1_SPT3_239_5) });
+        // One conversion in the regular sequence, the LDR channel
+        adc.rsq0.write(|w| unsafe { w.bits(0) });
+        adc.rsq2.write(|w| unsafe { w.bits(LDR_CHANNEL) });
+
+        let ctl1 = CTL1_ADCON | CTL1_ETSRC_SWRCST | CTL1_ETERC;
+        adc.ctl1.write(|w| unsafe { w.bits(ctl1) });
+        delay.delay_us(POWER_UP_US);
+
+        adc.ctl1.write(|w| unsafe { w.bits(ctl1 | CTL1_RSTCLB) });
+        while adc.ctl1.read().bits() & CTL1_RSTCLB != 0 {}
+        adc.ctl1.write(|w| unsafe { w.bits(ctl1 | CTL1_CLB) });
+        while adc.ctl1.read().bits() & CTL1_CLB != 0 {}
+
+        Ldr {
+            _adc: adc,
+            _pin: pin,
+        }
+    }
+
+    /// One conversion, takes about 25 µs
+    pub fn read_raw(&mut self) -> u16 {
+        let adc = self.regs();
+        adc.stat.modify(|r, w| unsafe { w.bits(r.bits() & !STAT_EOC) });
+        adc.ctl1.modify(|r, w| unsafe { w.bits(r.bits() | CTL1_SWRCST) });
+        while adc.stat.read().bits() & STAT_EOC == 0 {}
+        adc.rdata.read().bits() as u16
+    }
+
+    fn regs(&self) -> &pac::adc1::RegisterBlock {
+        unsafe { &*pac::ADC1::ptr() }
+    }
+}
+
+/// Reads the LDR into LDR_READING, called every LDR_SAMPLE_INTERVAL
+pub fn sample() {
+    free(|cs| {
+        if let Some(ldr) = LDR.borrow(*cs).borrow_mut().as_mut() {
+            LDR_READING.borrow(*cs).replace(ldr.read_raw());
+        }
+    });
+}
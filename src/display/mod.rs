@@ -0,0 +1,158 @@
+use core::cell::RefCell;
+use embedded_graphics::{
+    pixelcolor::Rgb565,
+    prelude::*,
+    primitives::{PrimitiveStyle, Rectangle},
+};
+use longan_nano::lcd::Lcd;
+use riscv::interrupt::{free, Mutex};
+use st7735_lcd::Orientation;
+
+use crate::display_config::BG_COLOR;
+
+pub mod backlight;
+#[cfg(feature = "lcd_dma")]
+pub mod dma;
+pub mod layout;
+#[cfg(feature = "ambient_light")]
+pub mod ldr;
+pub mod unit;
+pub mod writer;
+
+pub use self::layout::{DisplayLayout, LayoutKind};
+pub use self::unit::{temperature_unit, TemperatureUnit};
+pub use self::writer::LcdWriter;
+
+/// What the main loop draws on: the LCD directly, or its DMA frame buffer
+/// with the lcd_dma feature
+#[cfg(not(feature = "lcd_dma"))]
+pub type Screen = Lcd;
+#[cfg(feature = "lcd_dma")]
+pub type Screen = dma::DmaLcd;
+
+// Latest raw ADC reading of the ambient light sensor (LDR)
+pub static LDR_READING: Mutex<RefCell<u16>> = Mutex::new(RefCell::new(0));
+
+// Seconds between LDR samples, slow enough to avoid backlight flicker
+#[cfg(feature = "ambient_light")]
+pub const LDR_SAMPLE_INTERVAL: u32 = 5;
+
+// (ADC reading, backlight PWM %) points of the brightness curve
+const BRIGHTNESS_POINTS: [(u16, u8); 5] = [
+    (99, 20),
+    (100, 40),
+    (500, 70),
+    (3000, 95),
+    (3001, 100),
+];
+
+/// Piecewise-linear mapping from ambient light to backlight brightness
+pub struct BrightnessCurve;
+
+impl BrightnessCurve {
+    /// Backlight PWM duty cycle (%) for the given LDR ADC reading
+    pub fn compute(ldr_reading: u16) -> u8 {
+        interpolate(ldr_reading, &BRIGHTNESS_POINTS)
+    }
+}
+
+/// Backlight brightness (%) while in use: from the latest LDR reading with
+/// the ambient_light feature, full brightness otherwise
+pub fn active_brightness() -> u8 {
+    if cfg!(feature = "ambient_light") {
+        BrightnessCurve::compute(free(|cs| *LDR_READING.borrow(*cs).borrow()))
+    } else {
+        backlight::FULL_PERCENT
+    }
+}
+
+/// Linear interpolation between points sorted by x. Values outside the
+/// points are clamped to the first or last y.
+pub fn interpolate(x: u16, points: &[(u16, u8)]) -> u8 {
+    let (first, last) = match (points.first(), points.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return 0,
+    };
+
+    if x <= first.0 {
+        return first.1;
+    }
+    if x >= last.0 {
+        return last.1;
+    }
+
+    for pair in points.windows(2) {
+        let (x0, y0) = pair[0];
+        let (x1, y1) = pair[1];
+        if x <= x1 {
+            let dx = (x1 - x0) as i32;
+            let dy = y1 as i32 - y0 as i32;
+            return (y0 as i32 + dy * (x - x0) as i32 / dx) as u8;
+        }
+    }
+
+    last.1
+}
+
+/// How the board is mounted. The panel is 160x80 in landscape.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum DisplayOrientation {
+    Landscape,
+    Portrait,
+}
+
+/// Orientation the display is set to at boot
+pub const DISPLAY_ORIENTATION: DisplayOrientation = DisplayOrientation::Landscape;
+
+// Panel size in landscape orientation
+const LANDSCAPE_SIZE: Size = Size::new(160, 80);
+
+// Offset of the visible 160x80 area inside the controller's RAM in landscape
+const LANDSCAPE_OFFSET: (u16, u16) = (1, 26);
+
+/// Sets the MADCTL row/column exchange and mirroring for the orientation
+/// and moves the RAM offset to match
+pub fn set_orientation(lcd: &mut Lcd, orientation: DisplayOrientation) {
+    let (dx, dy) = LANDSCAPE_OFFSET;
+    match orientation {
+        DisplayOrientation::Landscape => {
+            lcd.set_orientation(&Orientation::Landscape).unwrap();
+            lcd.set_offset(dx, dy);
+        }
+        DisplayOrientation::Portrait => {
+            lcd.set_orientation(&Orientation::Portrait).unwrap();
+            lcd.set_offset(dy, dx);
+        }
+    }
+}
+
+/// Visible screen size in the configured orientation
+pub fn screen_size() -> Size {
+    match DISPLAY_ORIENTATION {
+        DisplayOrientation::Landscape => LANDSCAPE_SIZE,
+        DisplayOrientation::Portrait => Size::new(LANDSCAPE_SIZE.height, LANDSCAPE_SIZE.width),
+    }
+}
+
+/// Fills only the given rectangle with BG_COLOR. Clearing the whole screen
+/// shows as a black flash.
+pub fn clear_region(lcd: &mut impl DrawTarget<Color = Rgb565>, top_left: Point, size: Size) {
+    Rectangle::new(top_left, size)
+        .into_styled(PrimitiveStyle::with_fill(BG_COLOR))
+        .draw(lcd)
+        .ok();
+}
+
+/// Maps a position of the landscape layout to the configured orientation.
+/// In portrait x and y swap places and the result is kept on screen.
+pub fn layout_point(x: i32, y: i32) -> Point {
+    let size = screen_size();
+    let (x, y) = match DISPLAY_ORIENTATION {
+        DisplayOrientation::Landscape => (x, y),
+        DisplayOrientation::Portrait => (y, x),
+    };
+    Point::new(
+        x.max(0).min(size.width as i32 - 1),
+        y.max(0).min(size.height as i32 - 1),
+    )
+}
@@ -0,0 +1,67 @@
+use core::cell::RefCell;
+use riscv::interrupt::{free, Mutex};
+
+/// Unit temperatures are shown in. Readings are always stored in Celsius.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TemperatureUnit {
+    Celsius,
+    Fahrenheit,
+}
+
+impl TemperatureUnit {
+    /// The other unit
+    pub fn toggled(&self) -> TemperatureUnit {
+        match self {
+            TemperatureUnit::Celsius => TemperatureUnit::Fahrenheit,
+            TemperatureUnit::Fahrenheit => TemperatureUnit::Celsius,
+        }
+    }
+
+    /// Converts a temperature in Celsius to this unit
+    pub fn convert(&self, celsius: f32) -> f32 {
+        match self {
+            TemperatureUnit::Celsius => celsius,
+            TemperatureUnit::Fahrenheit => to_fahrenheit(celsius),
+        }
+    }
+
+    pub fn suffix(&self) -> &'static str {
+        match self {
+            TemperatureUnit::Celsius => "°C",
+            TemperatureUnit::Fahrenheit => "°F",
+        }
+    }
+
+    /// Value stored in BootConfig
+    pub fn to_u8(&self) -> u8 {
+        match self {
+            TemperatureUnit::Celsius => 0,
+            TemperatureUnit::Fahrenheit => 1,
+        }
+    }
+
+    /// Unit stored in BootConfig, Celsius for unknown values
+    pub fn from_u8(value: u8) -> TemperatureUnit {
+        match value {
+            1 => TemperatureUnit::Fahrenheit,
+            _ => TemperatureUnit::Celsius,
+        }
+    }
+}
+
+// Unit of the displayed temperatures, toggled by a long press of the button
+static UNIT: Mutex<RefCell<TemperatureUnit>> = Mutex::new(RefCell::new(TemperatureUnit::Celsius));
+
+pub fn temperature_unit() -> TemperatureUnit {
+    free(|cs| *UNIT.borrow(*cs).borrow())
+}
+
+pub fn set_temperature_unit(unit: TemperatureUnit) {
+    free(|cs| {
+        UNIT.borrow(*cs).replace(unit);
+    });
+}
+
+pub fn to_fahrenheit(c: f32) -> f32 {
+    c * 9.0 / 5.0 + 32.0
+}
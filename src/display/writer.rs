@@ -0,0 +1,61 @@
+use core::fmt;
+use embedded_graphics::{
+    mono_font::MonoTextStyle,
+    pixelcolor::Rgb565,
+    prelude::*,
+    text::{renderer::TextRenderer, Text},
+};
+
+/// Text output on the LCD through `write!`. Each write is drawn at the
+/// cursor, which then moves past the text; a newline moves it to the
+/// start of the next line.
+///
+/// Pad with the format width (e.g. `{:<15}`) to overwrite a longer text
+/// drawn earlier at the same place.
+pub struct LcdWriter<'a, T: DrawTarget<Color = Rgb565>> {
+    lcd: &'a mut T,
+    style: MonoTextStyle<'static, Rgb565>,
+    cursor: Point,
+    // Where a newline returns the cursor to
+    line_start: i32,
+}
+
+impl<'a, T: DrawTarget<Color = Rgb565>> LcdWriter<'a, T> {
+    /// `cursor` is the baseline position of the first character
+    pub fn new(lcd: &'a mut T, style: MonoTextStyle<'static, Rgb565>, cursor: Point) -> Self {
+        LcdWriter {
+            lcd,
+            style,
+            cursor,
+            line_start: cursor.x,
+        }
+    }
+}
+
+impl<'a, T: DrawTarget<Color = Rgb565>> fmt::Write for LcdWriter<'a, T> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut lines = s.split('\n');
+        if let Some(first) = lines.next() {
+            self.draw(first)?;
+        }
+        for line in lines {
+            let line_height = self.style.line_height() as i32;
+            self.cursor = Point::new(self.line_start, self.cursor.y + line_height);
+            self.draw(line)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a, T: DrawTarget<Color = Rgb565>> LcdWriter<'a, T> {
+    // Draws text without newlines and moves the cursor to its end
+    fn draw(&mut self, text: &str) -> fmt::Result {
+        if text.is_empty() {
+            return Ok(());
+        }
+        self.cursor = Text::new(text, self.cursor, self.style)
+            .draw(self.lcd)
+            .map_err(|_| fmt::Error)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,86 @@
+use embedded_graphics::mono_font::MonoFont;
+use embedded_graphics::pixelcolor::Rgb565;
+use embedded_graphics::prelude::*;
+
+#[cfg(any(
+    all(feature = "font-small", feature = "font-medium"),
+    all(feature = "font-small", feature = "font-large"),
+    all(feature = "font-medium", feature = "font-large"),
+))]
+compile_error!(
+    "font-small, font-medium and font-large are mutually exclusive, \
+     use --no-default-features to replace the default font-medium"
+);
+
+/// Font of the readings and status texts, selected with the font-* features
+#[cfg(feature = "font-small")]
+pub const UI_FONT: MonoFont<'static> = embedded_graphics::mono_font::iso_8859_1::FONT_6X10;
+#[cfg(feature = "font-large")]
+pub const UI_FONT: MonoFont<'static> = embedded_graphics::mono_font::iso_8859_1::FONT_9X18_BOLD;
+#[cfg(not(any(feature = "font-small", feature = "font-large")))]
+pub const UI_FONT: MonoFont<'static> = embedded_graphics::mono_font::iso_8859_1::FONT_10X20;
+
+/// Vertical distance between the baselines of two rows of UI_FONT text
+#[cfg(feature = "font-small")]
+pub const LINE_SPACING: i32 = 12;
+#[cfg(feature = "font-large")]
+pub const LINE_SPACING: i32 = 22;
+#[cfg(not(any(feature = "font-small", feature = "font-large")))]
+pub const LINE_SPACING: i32 = 25;
+
+// Bands of the 160x80 landscape screen as (top left, size). Pages clear
+// only the bands they draw on when the page changes.
+
+/// Status marks along the top edge: the power mode and alert indicators
+pub const HEADER_REGION: (Point, Size) = (Point::new(0, 0), Size::new(160, 10));
+
+/// Where every page draws its content
+pub const BODY_REGION: (Point, Size) = (Point::new(0, 10), Size::new(160, 60));
+
+/// Bottom edge, used by the main page's banners
+pub const FOOTER_REGION: (Point, Size) = (Point::new(0, 70), Size::new(160, 10));
+
+// Colors of everything drawn on the LCD. A different theme only needs
+// changes here.
+
+/// Readings and other body text
+#[cfg(not(feature = "high-contrast"))]
+pub const TEXT_COLOR: Rgb565 = Rgb565::new(50, 50, 50);
+#[cfg(feature = "high-contrast")]
+pub const TEXT_COLOR: Rgb565 = Rgb565::WHITE;
+
+/// Screen background, also the text background so redrawn text overwrites the old
+pub const BG_COLOR: Rgb565 = Rgb565::BLACK;
+
+/// Sensor errors, the alert indicator and failed checks
+pub const ALERT_COLOR: Rgb565 = Rgb565::RED;
+
+/// Titles, outlines and the clock date
+pub const HIGHLIGHT_COLOR: Rgb565 = Rgb565::WHITE;
+
+/// Status marks: the clock digits and the power mode icon
+pub const STATUS_COLOR: Rgb565 = Rgb565::CYAN;
+
+/// Passed checks, progress and a full battery
+pub const OK_COLOR: Rgb565 = Rgb565::GREEN;
+
+/// A battery running low
+pub const WARNING_COLOR: Rgb565 = Rgb565::YELLOW;
+
+/// Temperature history line
+pub const GRAPH_LINE_COLOR: Rgb565 = Rgb565::YELLOW;
+
+/// Dim line at 0°C on the graph
+#[cfg(not(feature = "high-contrast"))]
+pub const GRAPH_GRID_COLOR: Rgb565 = Rgb565::new(8, 16, 8);
+#[cfg(feature = "high-contrast")]
+pub const GRAPH_GRID_COLOR: Rgb565 = Rgb565::new(16, 32, 16);
+
+/// Dashed peak temperature line on the graph
+pub const GRAPH_PEAK_COLOR: Rgb565 = Rgb565::MAGENTA;
+
+/// Comfort banner fills at the bottom of the main page
+pub const BANNER_DRY_COLOR: Rgb565 = Rgb565::YELLOW;
+pub const BANNER_COMFY_COLOR: Rgb565 = Rgb565::GREEN;
+pub const BANNER_HUMID_COLOR: Rgb565 = Rgb565::BLUE;
+pub const BANNER_HOT_COLOR: Rgb565 = Rgb565::RED;
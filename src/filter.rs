@@ -0,0 +1,99 @@
+use heapless::HistoryBuffer;
+
+use crate::types::SensorReading;
+use crate::util::fmt::to_tenths;
+
+// Number of samples in the Savitzky-Golay window
+const SG_WINDOW: usize = 5;
+
+// 5-point quadratic smoothing kernel, oldest sample first
+const SG_COEFFS: [f32; SG_WINDOW] = [-3.0, 12.0, 17.0, 12.0, -3.0];
+
+// Normalisation factor of the kernel
+const SG_NORM: f32 = 35.0;
+
+/// 5-point quadratic Savitzky-Golay filter. Smooths noise while keeping
+/// the height of genuine peaks better than an exponential average.
+pub struct SavitzkyGolay5 {
+    samples: HistoryBuffer<f32, SG_WINDOW>,
+}
+
+impl SavitzkyGolay5 {
+    pub const fn new() -> Self {
+        SavitzkyGolay5 {
+            samples: HistoryBuffer::new(),
+        }
+    }
+
+    /// Adds a new sample and returns the smoothed value. Until the window
+    /// has been filled the raw sample is returned as is.
+    pub fn apply(&mut self, sample: f32) -> f32 {
+        self.samples.write(sample);
+
+        if self.samples.len() < SG_WINDOW {
+            return sample;
+        }
+
+        let sum: f32 = self
+            .samples
+            .oldest_ordered()
+            .zip(SG_COEFFS.iter())
+            .map(|(s, c)| s * c)
+            .sum();
+
+        sum / SG_NORM
+    }
+}
+
+impl Default for SavitzkyGolay5 {
+    fn default() -> Self {
+        SavitzkyGolay5::new()
+    }
+}
+
+/// Average of the last `N` readings. Values are summed as integer tenths
+/// so repeated averaging does not accumulate floating-point error.
+pub struct MovingAverage<const N: usize> {
+    readings: [SensorReading; N],
+    // Number of readings pushed so far, the next slot is count % N
+    count: usize,
+}
+
+impl<const N: usize> MovingAverage<N> {
+    pub const fn new() -> Self {
+        MovingAverage {
+            readings: [SensorReading::zero(); N],
+            count: 0,
+        }
+    }
+
+    pub fn push(&mut self, r: SensorReading) {
+        self.readings[self.count % N] = r;
+        self.count = self.count.wrapping_add(1);
+    }
+
+    /// Average of the stored readings, zero before the first push
+    pub fn average(&self) -> SensorReading {
+        let len = self.count.min(N);
+        if len == 0 {
+            return SensorReading::zero();
+        }
+
+        let (temp_sum, hum_sum) = self.readings[..len]
+            .iter()
+            .fold((0i32, 0i32), |(t, h), r| {
+                (t + r.celsius().0 as i32, h + to_tenths(r.humidity))
+            });
+
+        SensorReading::new(
+            (temp_sum / len as i32) as f32 / 10.0,
+            (hum_sum / len as i32) as f32 / 10.0,
+        )
+    }
+}
+
+impl<const N: usize> Default for MovingAverage<N> {
+    fn default() -> Self {
+        MovingAverage::new()
+    }
+}
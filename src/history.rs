@@ -0,0 +1,92 @@
+use core::cell::RefCell;
+use riscv::interrupt::{free, Mutex};
+
+use crate::types::SensorReading;
+
+// Number of readings kept, 3 minutes at the default update interval
+pub const HISTORY_LEN: usize = 60;
+
+// Latest successful readings, pushed by the main loop
+pub static HISTORY: Mutex<RefCell<RingBuffer<SensorReading, HISTORY_LEN>>> =
+    Mutex::new(RefCell::new(RingBuffer::new()));
+
+/// Fixed capacity ring buffer that overwrites the oldest item when full
+pub struct RingBuffer<T: Copy, const CAP: usize> {
+    buf: [Option<T>; CAP],
+    // Slot of the oldest item
+    head: usize,
+    len: usize,
+    // Items pushed in total, wrapping
+    pushed: u32,
+}
+
+impl<T: Copy, const CAP: usize> RingBuffer<T, CAP> {
+    pub const fn new() -> Self {
+        RingBuffer {
+            buf: [None; CAP],
+            head: 0,
+            len: 0,
+            pushed: 0,
+        }
+    }
+
+    pub fn push(&mut self, item: T) {
+        let tail = (self.head + self.len) % CAP;
+        self.buf[tail] = Some(item);
+        self.pushed = self.pushed.wrapping_add(1);
+        if self.is_full() {
+            self.head = (self.head + 1) % CAP;
+        } else {
+            self.len += 1;
+        }
+    }
+
+    /// Item at `index`, 0 being the oldest
+    pub fn get(&self, index: usize) -> Option<T> {
+        if index >= self.len {
+            return None;
+        }
+        self.buf[(self.head + index) % CAP]
+    }
+
+    /// Items from oldest to newest
+    pub fn iter(&self) -> impl Iterator<Item = T> + '_ {
+        (0..self.len).filter_map(move |i| self.get(i))
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of items ever pushed, wraps. Changes whenever the contents do.
+    pub fn pushed(&self) -> u32 {
+        self.pushed
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len == CAP
+    }
+}
+
+impl<T: Copy, const CAP: usize> Default for RingBuffer<T, CAP> {
+    fn default() -> Self {
+        RingBuffer::new()
+    }
+}
+
+/// Oldest reading in HISTORY
+pub fn oldest_reading() -> Option<SensorReading> {
+    free(|cs| HISTORY.borrow(*cs).borrow().get(0))
+}
+
+/// Latest reading in HISTORY
+pub fn newest_reading() -> Option<SensorReading> {
+    free(|cs| {
+        let history = HISTORY.borrow(*cs).borrow();
+        history.len().checked_sub(1).and_then(|i| history.get(i))
+    })
+}
@@ -0,0 +1,117 @@
+use core::cell::RefCell;
+use core::ops::DerefMut;
+use core::sync::atomic::{AtomicU8, Ordering};
+use embedded_hal::digital::v2::InputPin;
+use longan_nano::hal::gpio::gpioa::PA1;
+use longan_nano::hal::gpio::{Input, PullUp};
+use longan_nano::hal::pac::TIMER5;
+use longan_nano::hal::timer::Timer;
+use riscv::interrupt::{free, Mutex};
+
+/// Rate the button is sampled at, in Hz
+pub const SAMPLE_RATE_HZ: u32 = 100;
+
+// Samples that have to agree before a change counts, 40 ms at 100 Hz
+const DEBOUNCE_WINDOW: u8 = 4;
+
+// Active-low page button, None when PA1 is used by the sensor multiplexer
+pub static BUTTON_PIN: Mutex<RefCell<Option<PA1<Input<PullUp>>>>> =
+    Mutex::new(RefCell::new(None));
+
+// Timer sampling the button, also the 100 Hz tick of the buzzer
+pub static SAMPLE_TIMER: Mutex<RefCell<Option<Timer<TIMER5>>>> = Mutex::new(RefCell::new(None));
+
+// Debounced state of the button, updated by the TIMER5 interrupt
+static DEBOUNCER: Mutex<RefCell<Debouncer<DEBOUNCE_WINDOW>>> =
+    Mutex::new(RefCell::new(Debouncer::new()));
+
+// Latest event not yet handled by the main loop, a ButtonEvent as u8
+static BUTTON_EVENT: AtomicU8 = AtomicU8::new(ButtonEvent::None as u8);
+
+/// Change of the debounced button state
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[repr(u8)]
+pub enum ButtonEvent {
+    None,
+    Pressed,
+    Released,
+}
+
+impl ButtonEvent {
+    fn from_u8(value: u8) -> ButtonEvent {
+        match value {
+            1 => ButtonEvent::Pressed,
+            2 => ButtonEvent::Released,
+            _ => ButtonEvent::None,
+        }
+    }
+}
+
+/// Debounces an active-low button sampled at a fixed rate. The latest
+/// samples are kept as bits of `history`, the button changes state only
+/// once the last `WINDOW` of them agree.
+pub struct Debouncer<const WINDOW: u8> {
+    history: u32,
+    pressed: bool,
+}
+
+impl<const WINDOW: u8> Debouncer<WINDOW> {
+    // Bits of history that have to agree
+    const MASK: u32 = if WINDOW >= 32 {
+        u32::MAX
+    } else {
+        (1 << WINDOW) - 1
+    };
+
+    /// Starts released, the line idles high
+    pub const fn new() -> Self {
+        Debouncer {
+            history: u32::MAX,
+            pressed: false,
+        }
+    }
+
+    /// Adds a sample of the pin and returns the change it completes, if any
+    pub fn update(&mut self, pin_high: bool) -> ButtonEvent {
+        self.history = (self.history << 1) | pin_high as u32;
+        let window = self.history & Self::MASK;
+
+        if window == 0 && !self.pressed {
+            self.pressed = true;
+            ButtonEvent::Pressed
+        } else if window == Self::MASK && self.pressed {
+            self.pressed = false;
+            ButtonEvent::Released
+        } else {
+            ButtonEvent::None
+        }
+    }
+}
+
+/// Latest button event, cleared by the call
+pub fn take_button_event() -> ButtonEvent {
+    ButtonEvent::from_u8(BUTTON_EVENT.swap(ButtonEvent::None as u8, Ordering::Relaxed))
+}
+
+//Interrupt handler for sampling the button, timing the buzzer and pacing binary frames
+#[allow(non_snake_case)]
+#[no_mangle]
+fn TIMER5() {
+    free(|cs| {
+        let pin_high = match *BUTTON_PIN.borrow(*cs).borrow() {
+            Some(ref pin) => pin.is_high().unwrap_or(true),
+            None => true,
+        };
+        let event = DEBOUNCER.borrow(*cs).borrow_mut().update(pin_high);
+        if event != ButtonEvent::None {
+            BUTTON_EVENT.store(event as u8, Ordering::Relaxed);
+        }
+
+        crate::buzzer::buzzer_tick();
+        crate::serial::stream_tick();
+
+        if let Some(ref mut timer) = SAMPLE_TIMER.borrow(*cs).borrow_mut().deref_mut() {
+            timer.clear_update_interrupt_flag();
+        }
+    });
+}
@@ -0,0 +1,160 @@
+use core::cell::RefCell;
+use longan_nano::hal::pac;
+use riscv::interrupt::{free, Mutex};
+use riscv::register::mcycle;
+
+use crate::SYSCLK_MHZ;
+
+// Edges in one NEC frame: leader mark and space, 32 bits of mark and space, final mark
+const NEC_EDGES: usize = 68;
+
+// Nominal NEC timings in microseconds
+const LEADER_MARK_US: u32 = 9000;
+const LEADER_SPACE_US: u32 = 4500;
+const BIT_ONE_SPACE_US: u32 = 1687;
+const BIT_ZERO_SPACE_US: u32 = 562;
+
+// A gap longer than this between edges starts a new frame
+const FRAME_GAP_US: u32 = 15000;
+
+// EXTI line of the receiver output, PB10. PA6 is used by the LCD's SPI0.
+const IR_EXTI_LINE: u32 = 10;
+
+// Command codes of the remote in use, adjust for other remotes
+pub const NEC_CMD_VOLUME_UP: u8 = 0x15;
+pub const NEC_CMD_VOLUME_DOWN: u8 = 0x07;
+pub const NEC_CMD_MUTE: u8 = 0x09;
+
+/// Address and command of a received NEC frame
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct NecFrame {
+    pub address: u8,
+    pub command: u8,
+}
+
+/// What a remote button does on the station
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum IrAction {
+    NextPage,
+    PreviousPage,
+    ToggleUnit,
+}
+
+impl NecFrame {
+    pub fn action(&self) -> Option<IrAction> {
+        match self.command {
+            NEC_CMD_VOLUME_UP => Some(IrAction::NextPage),
+            NEC_CMD_VOLUME_DOWN => Some(IrAction::PreviousPage),
+            NEC_CMD_MUTE => Some(IrAction::ToggleUnit),
+            _ => None,
+        }
+    }
+}
+
+// Latest decoded frame, taken by the main loop
+pub static IR_COMMAND: Mutex<RefCell<Option<NecFrame>>> = Mutex::new(RefCell::new(None));
+
+// Receiver state, updated from the EXTI interrupt
+static IR_RECEIVER: Mutex<RefCell<IrReceiver>> = Mutex::new(RefCell::new(IrReceiver::new()));
+
+/// Decodes NEC frames from the edges of an IR receiver (e.g. TSOP38238)
+pub struct IrReceiver {
+    // Time between consecutive edges
+    pulses_us: [u32; NEC_EDGES],
+    edges: usize,
+    last_edge_cycles: u32,
+}
+
+impl IrReceiver {
+    pub const fn new() -> Self {
+        IrReceiver {
+            pulses_us: [0; NEC_EDGES],
+            edges: 0,
+            last_edge_cycles: 0,
+        }
+    }
+
+    /// Records an edge at the given mcycle count. Returns the frame once
+    /// all of its edges have been received.
+    pub fn on_edge(&mut self, now_cycles: u32) -> Option<NecFrame> {
+        let pulse_us = now_cycles.wrapping_sub(self.last_edge_cycles) / SYSCLK_MHZ;
+        self.last_edge_cycles = now_cycles;
+
+        if self.edges == 0 || pulse_us > FRAME_GAP_US {
+            // First edge of a frame, nothing to measure yet
+            self.edges = 1;
+            return None;
+        }
+
+        self.pulses_us[self.edges - 1] = pulse_us;
+        self.edges += 1;
+
+        if self.edges < NEC_EDGES {
+            return None;
+        }
+        self.edges = 0;
+        decode(&self.pulses_us[..NEC_EDGES - 1])
+    }
+}
+
+// Within 25% of the nominal value
+fn is_near(value: u32, nominal: u32) -> bool {
+    value > nominal * 3 / 4 && value < nominal * 5 / 4
+}
+
+// Decodes the pulses of a frame, None if it is not a valid NEC frame
+fn decode(pulses_us: &[u32]) -> Option<NecFrame> {
+    if !is_near(pulses_us[0], LEADER_MARK_US) || !is_near(pulses_us[1], LEADER_SPACE_US) {
+        return None;
+    }
+
+    // Bits are sent LSB first, the length of the space after each mark tells the value
+    let threshold = (BIT_ZERO_SPACE_US + BIT_ONE_SPACE_US) / 2;
+    let mut bits: u32 = 0;
+    for bit in 0..32 {
+        if pulses_us[2 + bit * 2 + 1] > threshold {
+            bits |= 1 << bit;
+        }
+    }
+
+    let [address, address_inv, command, command_inv] = bits.to_le_bytes();
+    if command != !command_inv {
+        return None;
+    }
+    // Extended NEC uses the inverted address byte as more address bits
+    let _ = address_inv;
+
+    Some(NecFrame { address, command })
+}
+
+/// Routes PB10 to EXTI line 10 and enables interrupts on both edges.
+/// The AFIO clock must already be enabled.
+pub fn setup_exti() {
+    // AFIO is owned by the LCD setup, only the EXTI source selection is touched here
+    let afio = unsafe { &*pac::AFIO::ptr() };
+    let exti = unsafe { &*pac::EXTI::ptr() };
+
+    // EXTISS2 holds the port selection of lines 8-11, 4 bits each, port B is 1
+    afio.extiss2.modify(|r, w| unsafe { w.bits(r.bits() & !(0xf << 8) | 0x1 << 8) });
+
+    exti.rten.modify(|r, w| unsafe { w.bits(r.bits() | 1 << IR_EXTI_LINE) });
+    exti.ften.modify(|r, w| unsafe { w.bits(r.bits() | 1 << IR_EXTI_LINE) });
+    exti.inten.modify(|r, w| unsafe { w.bits(r.bits() | 1 << IR_EXTI_LINE) });
+}
+
+//Interrupt handler for EXTI lines 10-15
+#[allow(non_snake_case)]
+#[no_mangle]
+fn EXTI_LINE15_10() {
+    let now_cycles = mcycle::read() as u32;
+
+    free(|cs| {
+        if let Some(frame) = IR_RECEIVER.borrow(*cs).borrow_mut().on_edge(now_cycles) {
+            IR_COMMAND.borrow(*cs).replace(Some(frame));
+        }
+    });
+
+    // Clear pending flag by writing 1
+    let exti = unsafe { &*pac::EXTI::ptr() };
+    exti.pd.write(|w| unsafe { w.bits(1 << IR_EXTI_LINE) });
+}
@@ -0,0 +1,3 @@
+pub mod button;
+#[cfg(feature = "ir_remote")]
+pub mod ir;
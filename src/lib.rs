@@ -0,0 +1,33 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+//! Sensor driver, readings and the values derived from them for the
+//! weather station. Everything here builds without the Longan Nano HAL
+//! when the `hal` feature is off, so it can be tested on the host with
+//! `cargo test --no-default-features --features std`.
+
+pub mod calibration;
+pub mod collections;
+pub mod crc;
+pub mod derived;
+pub mod derived_metrics;
+pub mod dht;
+pub mod display_config;
+pub mod filter;
+pub mod history;
+pub mod protocol;
+pub mod sensor;
+pub mod stats;
+#[cfg(any(test, feature = "std"))]
+pub mod testing;
+pub mod time;
+pub mod types;
+pub mod util;
+
+pub use crate::derived_metrics::{comfort_level, dew_point};
+pub use crate::dht::{Dht as DhtDriver, SensorError};
+pub use crate::filter::MovingAverage;
+pub use crate::history::RingBuffer;
+pub use crate::types::SensorReading;
+
+/// System clock in MHz, used for converting cycle counts to microseconds
+pub const SYSCLK_MHZ: u32 = 80;
@@ -14,164 +14,977 @@
  *          Elias Hagelberg, elias.hagelberg@tuni.fi
  */
 
+#[cfg(feature = "ntc_fallback")]
+mod adc_sensor;
+mod alert;
+#[cfg(feature = "battery")]
+mod battery;
+mod buzzer;
+mod command;
+mod config;
+mod diag;
+mod display;
+mod input;
+mod metrics;
+mod power;
+mod selftest;
+mod serial;
+mod storage;
+mod sync;
+mod task;
+#[cfg(feature = "fault_injection")]
+mod test_utils;
+mod ui;
+
+// Hardware independent parts live in the library, imported here so the
+// rest of the firmware can keep using them through crate:: paths
+use weather_station::{
+    calibration, collections, crc, derived, derived_metrics, dht, display_config, filter, history,
+    protocol, sensor, stats, time, types, util, SYSCLK_MHZ,
+};
+
 use core::cell::RefCell;
+use core::fmt::{self, Write};
 use core::ops::DerefMut;
+use core::sync::atomic::{AtomicBool, AtomicU32, AtomicU8, Ordering};
+use crate::alert::{
+    led_alert, relay_alert, update_alert_pin, ALARM_LOG, ALERT_DISPATCHER, ALERT_LED, ALERT_PIN,
+    RELAY_PIN,
+};
+use crate::calibration::{
+    apply_self_heating_correction, calibration_offset, offsets_text, quick_calibrate,
+    record_calibration, set_calibration_offset, set_self_heating_coeff, step_offset,
+    CalibrationOffset, OffsetKind, CALIBRATION_HISTORY, HUM_OFFSET, TEMP_OFFSET_TENTH,
+};
+use crate::command::{
+    is_line_end, parse_reference, CalibrationKey, Command, LineBuffer, LineCommand, WordCommand,
+    ERROR_END, HELP, RESPONSE_END,
+};
+use crate::config::{monitoring_profile, set_monitoring_profile, MonitoringProfile};
+use crate::dht::identity::SensorIdentity;
+use crate::dht::quality::{compute_read_quality, BitErrorRate};
+use crate::dht::capture::Timer4Capture;
+#[cfg(feature = "second_sensor")]
+use crate::dht::capture::PolledCapture;
+#[cfg(feature = "second_sensor")]
+use crate::dht::{SecondInPin, SecondOutPin};
+use crate::dht::{Dht, Dht11, InPin, OutPin, SensorError};
+use crate::derived::HourlyLog;
+use crate::derived_metrics::dew_point;
+use crate::display_config::BG_COLOR;
+use crate::diag::FailurePatternAnalyzer;
+use crate::filter::MovingAverage;
+use crate::history::HISTORY;
+use crate::metrics::METRICS;
+use crate::power::PowerMode;
+#[cfg(feature = "second_sensor")]
+use crate::sensor::dual::DualReadings;
+use crate::sensor::{SENSOR_WARMUP, WARMUP_MONITOR};
+use crate::sensor::verify::ReadVerifier;
+use crate::serial::UartWriter;
+use crate::stats::{MinMaxTracker, PeakHold};
+use crate::storage::fmc::FlashError;
+use crate::sync::GlobalInterruptGuard;
+use crate::task::WeatherTask;
+use crate::time::TimeOfDay;
+use crate::types::SensorReading;
+use crate::util::fmt::push_tenths;
 use embedded_graphics::{
-    mono_font::{iso_8859_1::FONT_10X20, MonoTextStyleBuilder},
-    pixelcolor::Rgb565,
     prelude::*,
     primitives::{PrimitiveStyle, Rectangle},
-    text::Text,
 };
-use embedded_hal::digital::v2::{InputPin, OutputPin};
+use embedded_hal::watchdog::WatchdogEnable;
 use heapless::String;
 use longan_nano::hal::{
     delay::McycleDelay,
     eclic::{EclicExt, Level, LevelPriorityBits, Priority, TriggerType},
-    gpio::gpioa::PA0,
-    gpio::{Output, PushPull},
+    serial::{Config as UartConfig, Event as UartEvent, Parity, Serial, StopBits},
     timer::{Event, Timer},
+    watchdog::FreeWatchdog,
     {pac, prelude::*, rcu::RcuExt},
 };
+use longan_nano::led::{Led, RED};
 use longan_nano::{lcd, lcd_pins};
 use panic_halt as _;
-use riscv::interrupt::{free, Mutex};
+use riscv::interrupt::{free, CriticalSection, Mutex};
+use riscv::register::mcycle;
 use riscv_rt::entry;
 
 // Interrupt timer
 static TIMER: Mutex<RefCell<Option<Timer<longan_nano::hal::pac::TIMER1>>>> =
     Mutex::new(RefCell::new(None));
 
-// Measured (temperature, humidity) pair
-static DATA: Mutex<RefCell<Option<(f32, f32)>>> = Mutex::new(RefCell::new(Some((0.0, 0.0))));
+// Latest successful measurement, None until the first one
+static DATA: Mutex<RefCell<Option<SensorReading>>> = Mutex::new(RefCell::new(None));
+
+// Whether the latest read succeeded. DATA keeps the previous value when it didn't.
+static LAST_READ_OK: AtomicBool = AtomicBool::new(false);
+
+// Lowest and highest temperature and humidity since power-on
+static MIN_MAX: Mutex<RefCell<MinMaxTracker>> = Mutex::new(RefCell::new(MinMaxTracker::new()));
+
+// Recent peak temperature, drawn on the graph page
+static TEMP_PEAK: Mutex<RefCell<PeakHold>> = Mutex::new(RefCell::new(PeakHold::new()));
+
+// Number of readings averaged into DATA
+const READING_FILTER_LEN: usize = 5;
+
+// Smooths the readings written to DATA
+static READING_FILTER: Mutex<RefCell<MovingAverage<READING_FILTER_LEN>>> =
+    Mutex::new(RefCell::new(MovingAverage::new()));
 
 // Used for creating delays in read_data-function
 static DELAY: Mutex<RefCell<Option<McycleDelay>>> = Mutex::new(RefCell::new(None));
 
-// Pin used for reading data from sensor
-static SIGNAL_PIN: Mutex<RefCell<Option<PA0<Output<PushPull>>>>> = Mutex::new(RefCell::new(None));
+// Sensor driver, owns the pin used for reading data from sensor. The board
+// has a DHT11, change the last parameter to Dht22 for the DHT22.
+static SENSOR: Mutex<RefCell<Option<Dht<InPin, OutPin, Timer4Capture, Dht11>>>> =
+    Mutex::new(RefCell::new(None));
 
-// Counter to only read data on specific interrupts to decrease update inverval from 1 Hz
-static mut TIMER_COUNTER: u32 = 0;
+// Second sensor on PB6, read in turns with SENSOR
+#[cfg(feature = "second_sensor")]
+type SecondSensor = Dht<SecondInPin, SecondOutPin, PolledCapture, Dht11>;
+#[cfg(feature = "second_sensor")]
+static SECOND_SENSOR: Mutex<RefCell<Option<SecondSensor>>> = Mutex::new(RefCell::new(None));
 
-// Update interval in seconds
-static UPDATE_INTERVAL: u32 = 3;
+// Latest reading of each sensor, averaged into DATA
+#[cfg(feature = "second_sensor")]
+static DUAL_READINGS: Mutex<RefCell<DualReadings>> = Mutex::new(RefCell::new(DualReadings::new()));
 
-//Function for reading data from the sensor
-fn read_data() -> Result<(f32, f32), &'static str> {
-    // Used for saving and replacing signal pin after taking it from SIGNAL_PIN
-    let mut signal_pin_saved = None;
+// Sensor read on the next update, 0 for SENSOR and 1 for SECOND_SENSOR
+#[cfg(feature = "second_sensor")]
+static NEXT_SENSOR: AtomicU8 = AtomicU8::new(0);
 
-    // Keeps track of signal pin needing to be replaced in SIGNAL_PIN
-    let mut replaced = false;
+// Set while the two sensors differ by more than the limits in sensor::dual.
+// Stays false with a single sensor.
+static SENSOR_DISAGREEMENT: AtomicBool = AtomicBool::new(false);
 
-    // Initialize variables for temperature and humidity
-    let mut t = 1001.0;
-    let mut h = 1001.0;
+// Success history of sensor reads for failure pattern analysis
+static FAILURE_ANALYZER: Mutex<RefCell<FailurePatternAnalyzer>> =
+    Mutex::new(RefCell::new(FailurePatternAnalyzer::new()));
 
-    free(|cs| {
-        if let Some(mut signal_pin) = SIGNAL_PIN.borrow(*cs).take() {
-            if let Some(ref mut delay) = DELAY.borrow(*cs).borrow_mut().deref_mut() {
-                replaced = true;
+// Quality (%) of the latest complete sensor frame, see compute_read_quality
+static LAST_READ_QUALITY: Mutex<RefCell<u8>> = Mutex::new(RefCell::new(0));
 
-                // same as count_ in c++ library, based on cpu clock speed which in this project is 80 MHz
-                let count_ = 22;
+// Share of marginal bits over all reads
+static BIT_ERROR_RATE: Mutex<RefCell<BitErrorRate>> = Mutex::new(RefCell::new(BitErrorRate::new()));
 
-                // how many timing transitions are needed to keep track of. 2 * number bits + extra
-                let maxtimings_ = 85;
+// Fingerprint of the connected sensor, taken on first successful read
+static SENSOR_ID: Mutex<RefCell<Option<SensorIdentity>>> = Mutex::new(RefCell::new(None));
 
-                let mut laststate: bool = true;
-                let mut counter: i32;
-                let mut i: u8 = 0;
-                let mut j: u8 = 0;
+// Latest successful reading before calibration offsets, used by setref
+static LAST_RAW_READING: Mutex<RefCell<Option<SensorReading>>> = Mutex::new(RefCell::new(None));
 
-                // Storing read data, first byte for humidity, 3rd and 4th for temperature
-                let mut data: [u8; 5] = [0, 0, 0, 0, 0];
+// Hourly averages of the filtered readings for the climate classification
+static HOURLY_LOG: Mutex<RefCell<HourlyLog>> = Mutex::new(RefCell::new(HourlyLog::new()));
 
-                signal_pin.set_high().unwrap();
-                delay.delay_ms(250);
+// Whether the latest reading was outside the alert thresholds
+static ALERT_STATE: Mutex<RefCell<bool>> = Mutex::new(RefCell::new(false));
 
-                signal_pin.set_low().unwrap();
-                delay.delay_ms(20);
+// Counter to only read data on specific interrupts to decrease update inverval from 1 Hz.
+// Seconds of the current day, reset to zero when UPTIME_DAYS is incremented.
+static TIMER_COUNTER: AtomicU32 = AtomicU32::new(0);
 
-                signal_pin.set_high().unwrap();
-                delay.delay_us(40);
+// Whole days since boot
+static UPTIME_DAYS: AtomicU32 = AtomicU32::new(0);
 
-                let signal_pin_input = signal_pin.into_pull_up_input();
+const SECONDS_PER_DAY: u32 = 86_400;
 
-                // read in timings
-                while i < maxtimings_ {
-                    counter = 0;
-                    while signal_pin_input.is_high().unwrap() == laststate {
-                        counter += 1;
-                        delay.delay_us(1);
-                        if counter == 255 {
-                            break;
-                        }
-                    }
-                    laststate = signal_pin_input.is_high().unwrap();
+// Update intervals in seconds to choose from with a long press of the button
+const INTERVAL_OPTIONS: [u32; 4] = [1, 3, 5, 10];
 
-                    if counter == 255 {
-                        break;
-                    }
+// Index into INTERVAL_OPTIONS of the interval used in the normal power mode, 3 s by default
+static CURRENT_INTERVAL_IDX: AtomicU8 = AtomicU8::new(1);
 
-                    // ignore first 3 transitions
-                    if (i >= 4) && (i % 2 == 0) {
-                        // shove each bit into the storage bytes
-                        let index = (j / 8) as usize;
-                        data[index] <<= 1;
-                        if counter > count_ {
-                            data[index] |= 1;
+// Seconds between reads in the current power mode, INTERVAL_OPTIONS[CURRENT_INTERVAL_IDX]
+// or power::LOWERED_INTERVAL_S while the readings are stable
+static UPDATE_INTERVAL_TICKS: AtomicU32 = AtomicU32::new(INTERVAL_OPTIONS[1]);
+
+// Update interval selected for the normal power mode, in seconds
+fn update_interval_s() -> u32 {
+    INTERVAL_OPTIONS[CURRENT_INTERVAL_IDX.load(Ordering::Relaxed) as usize % INTERVAL_OPTIONS.len()]
+}
+
+// Selects the update interval with index idx into INTERVAL_OPTIONS. Takes
+// effect right away unless the lowered power mode has spaced out the reads.
+fn set_update_interval_idx(idx: u8) {
+    CURRENT_INTERVAL_IDX.store(idx % INTERVAL_OPTIONS.len() as u8, Ordering::Relaxed);
+    if power::power_mode() == PowerMode::Normal {
+        UPDATE_INTERVAL_TICKS.store(update_interval_s(), Ordering::Relaxed);
+    }
+}
+
+// Switches to the next of INTERVAL_OPTIONS, wrapping to the first, and returns it
+fn next_update_interval() -> u32 {
+    set_update_interval_idx(CURRENT_INTERVAL_IDX.load(Ordering::Relaxed) + 1);
+    update_interval_s()
+}
+
+// Set by the `r` command, the next TIMER1 interrupt schedules a read regardless of the interval
+static FORCE_READ: AtomicBool = AtomicBool::new(false);
+
+// Set by the TIMER1 interrupt when a read is due, the main loop does the read
+static READ_DUE: AtomicBool = AtomicBool::new(false);
+
+// Set by the `C` command until `W`, calibration keys are taken before the commands
+static CALIBRATING: AtomicBool = AtomicBool::new(false);
+
+// LineCommand whose argument is being received on the UART
+static PENDING_LINE: Mutex<RefCell<Option<LineBuffer>>> = Mutex::new(RefCell::new(None));
+
+// Offset stepped by + and - in the calibration mode
+static CALIBRATION_TARGET: Mutex<RefCell<OffsetKind>> = Mutex::new(RefCell::new(OffsetKind::Temp));
+
+// Unix time at boot, set by the `D` command. None until then, there is no RTC.
+static CLOCK_OFFSET_S: Mutex<RefCell<Option<u32>>> = Mutex::new(RefCell::new(None));
+
+// Wall-clock time as Unix seconds, None while the clock is not set
+fn wall_clock_s() -> Option<u32> {
+    let offset = free(|cs| *CLOCK_OFFSET_S.borrow(*cs).borrow());
+    offset.map(|offset| offset.wrapping_add(uptime_s()))
+}
+
+// Hour of the wall clock (UTC), or of the uptime while the clock is not set
+fn hour_of_day(now_s: u32) -> usize {
+    let seconds = wall_clock_s().unwrap_or(now_s);
+    (seconds % SECONDS_PER_DAY / 3600) as usize
+}
+
+// Seconds since boot. Wraps after about 136 years, UPTIME_DAYS itself keeps counting.
+fn uptime_s() -> u32 {
+    // Both counters are read without the interrupt rolling the day over in between
+    free(|_| {
+        let days = UPTIME_DAYS.load(Ordering::Acquire);
+        let seconds = TIMER_COUNTER.load(Ordering::Acquire);
+        days.wrapping_mul(SECONDS_PER_DAY).wrapping_add(seconds)
+    })
+}
+
+// The main loop feeds the watchdog about once a second, between TIMER1 ticks. A
+// read in the main loop takes up to four attempts, 250 ms idle + 20 ms start +
+// 100 ms retry delay apiece, so about 1.5 s in the worst case. The timeout
+// covers that with room to spare, a hung read still resets the MCU.
+const WATCHDOG_TIMEOUT_MS: u32 = 6000;
+
+// Reloads the watchdog from a command that runs longer than
+// WATCHDOG_TIMEOUT_MS. The FreeWatchdog itself is owned by the main loop.
+#[cfg(feature = "spi_flash")]
+fn feed_watchdog() {
+    const FWDGT_CMD_RELOAD: u16 = 0xAAAA;
+    unsafe { (*pac::FWDGT::ptr()).ctl.write(|w| w.cmd().bits(FWDGT_CMD_RELOAD)) };
+}
+
+// Default timeout for a single pin transition while reading the sensor, in microseconds
+const SENSOR_TIMEOUT_US_DEFAULT: u32 = 500;
+
+// Sensible range for the transition timeout, shorter than a DHT bit or longer than a full frame makes no sense
+const SENSOR_TIMEOUT_US_MIN: u32 = 100;
+const SENSOR_TIMEOUT_US_MAX: u32 = 10000;
+
+// Transition timeout used by read_data, adjustable at runtime with the `T` command
+static SENSOR_TIMEOUT_US: AtomicU32 = AtomicU32::new(SENSOR_TIMEOUT_US_DEFAULT);
+
+// Sets the transition timeout used by read_data
+fn set_sensor_timeout_us(us: u32) -> Result<(), &'static str> {
+    if us <= SENSOR_TIMEOUT_US_MIN || us >= SENSOR_TIMEOUT_US_MAX {
+        return Err("Sensor timeout must be between 100 and 10000 us!");
+    }
+    SENSOR_TIMEOUT_US.store(us, Ordering::Relaxed);
+    Ok(())
+}
+
+// Handles `R<tenths> <percent>`: single-point calibration against the latest raw reading
+fn set_reference(
+    reference_temp: f32,
+    reference_humidity: f32,
+) -> Result<CalibrationOffset, &'static str> {
+    let raw = free(|cs| *LAST_RAW_READING.borrow(*cs).borrow())
+        .ok_or("No sensor reading to calibrate against!")?;
+    let offset = quick_calibrate(reference_temp, reference_humidity, &raw);
+    set_calibration_offset(offset);
+    record_calibration(uptime_s(), false);
+    Ok(offset)
+}
+
+// Runs the commands received on the UART since the last call
+fn process_commands() {
+    while let Some(byte) = free(|cs| serial::RX_QUEUE.borrow(*cs).borrow_mut().dequeue()) {
+        // The argument of a LineCommand is taken up to the end of the line
+        if let Some(mut line) = free(|cs| PENDING_LINE.borrow(*cs).borrow_mut().take()) {
+            if is_line_end(byte) {
+                process_line_command(line.command(), line.arg());
+            } else {
+                line.push(byte);
+                free(|cs| PENDING_LINE.borrow(*cs).replace(Some(line)));
+            }
+            continue;
+        }
+
+        if CALIBRATING.load(Ordering::Relaxed) {
+            if let Some(key) = CalibrationKey::from_byte(byte) {
+                process_calibration_key(key);
+                continue;
+            }
+        }
+
+        if let Some(command) = LineCommand::from_byte(byte) {
+            free(|cs| PENDING_LINE.borrow(*cs).replace(Some(LineBuffer::new(command))));
+            continue;
+        }
+
+        let command = match Command::from_byte(byte) {
+            Some(command) => command,
+            None => continue,
+        };
+
+        // Cycles of format_i32 and write!, measured before interrupts are masked
+        let mut bench_cycles = (0, 0);
+
+        match command {
+            Command::Read => FORCE_READ.store(true, Ordering::Relaxed),
+            Command::ResetMinMax => free(|cs| {
+                // A thermistor reading has no humidity to start from
+                let current = DATA.borrow(*cs).borrow().filter(SensorReading::has_humidity);
+                MIN_MAX.borrow(*cs).borrow_mut().reset(current);
+            }),
+            Command::NextLayout => {
+                display::layout::next_layout();
+            }
+            Command::Calibrate => CALIBRATING.store(true, Ordering::Relaxed),
+            Command::StreamFrames => {
+                serial::STREAMING.fetch_xor(true, Ordering::Relaxed);
+            }
+            Command::Benchmark => bench_cycles = diag::bench_format_i32(),
+            Command::PrintHistory
+            | Command::CalibrationHistory
+            | Command::AlarmLog
+            | Command::FailurePattern
+            | Command::Help => {}
+        }
+
+        free(|cs| {
+            if let Some(ref mut uart) = *serial::UART.borrow(*cs).borrow_mut() {
+                let _ = match command {
+                    Command::Read => serial::write_str(uart, "Reading on next tick"),
+                    Command::ResetMinMax => serial::write_str(uart, "Min/max reset"),
+                    Command::PrintHistory => {
+                        serial::write_history_csv(uart, &HISTORY.borrow(*cs).borrow())
+                    }
+                    Command::NextLayout => {
+                        let layout = display::layout::active_layout();
+                        serial::write_str(uart, "Layout: ")
+                            .and_then(|_| serial::write_str(uart, layout.label()))
+                    }
+                    Command::Calibrate => serial::write_str(uart, "Calibration: ")
+                        .and_then(|_| serial::write_str(uart, &offsets_text())),
+                    Command::StreamFrames => {
+                        if serial::STREAMING.load(Ordering::Relaxed) {
+                            serial::write_str(uart, "Binary frames on")
+                        } else {
+                            serial::write_str(uart, "Binary frames off")
                         }
-                        j += 1;
                     }
-                    i += 1;
-                }
-                // Saving signal pin to replace value in SIGNAL_PIN
-                signal_pin_saved = Some(signal_pin_input);
+                    Command::CalibrationHistory => {
+                        let _ = CALIBRATION_HISTORY
+                            .borrow(*cs)
+                            .borrow()
+                            .print_history(&mut UartWriter(uart));
+                        Ok(())
+                    }
+                    Command::AlarmLog => {
+                        let _ = ALARM_LOG
+                            .borrow(*cs)
+                            .borrow()
+                            .print_log(&mut UartWriter(uart));
+                        Ok(())
+                    }
+                    Command::FailurePattern => {
+                        let pattern = FAILURE_ANALYZER.borrow(*cs).borrow().analyze();
+                        let mut text: String<40> = String::new();
+                        let _ = write!(text, "Failures: {}", pattern);
+                        serial::write_str(uart, &text)
+                    }
+                    Command::Benchmark => {
+                        let (fast, slow) = bench_cycles;
+                        let mut text: String<64> = String::new();
+                        let _ = write!(text, "format_i32: {} cycles, write!: {} cycles", fast, slow);
+                        serial::write_str(uart, &text)
+                    }
+                    Command::Help => serial::write_str(uart, HELP),
+                };
+                let _ = serial::write_str(uart, RESPONSE_END);
+            }
+        });
+    }
+}
+
+// Stores the offsets in use in the boot config, they are loaded from there at boot
+fn save_calibration_offsets() -> Result<(), FlashError> {
+    let mut config = config::load_boot_config();
+    config.temp_offset_tenth_deg = TEMP_OFFSET_TENTH.load(Ordering::Relaxed);
+    config.hum_offset_percent = HUM_OFFSET.load(Ordering::Relaxed);
+    config::save_boot_config(&config)
+}
+
+// Runs a LineCommand once its line has ended. `arg` is None when it was too long.
+fn process_line_command(command: LineCommand, arg: Option<&str>) {
+    let mut text: String<32> = String::new();
+    let result = match command {
+        LineCommand::SensorTimeout => arg
+            .and_then(|arg| arg.parse::<u32>().ok())
+            .ok_or("Sensor timeout must be a number of us!")
+            .and_then(|us| {
+                set_sensor_timeout_us(us)?;
+                let _ = write!(text, "Sensor timeout: {} us", us);
+                Ok(())
+            }),
+        LineCommand::SetReference => arg
+            .and_then(parse_reference)
+            .ok_or("Reference must be R<tenths> <percent>!")
+            .and_then(|(temp, humidity)| {
+                set_reference(temp, humidity)?;
+                save_calibration_offsets().map_err(|_| "Saving the offsets failed!")?;
+                let _ = write!(text, "Calibration: {} saved", offsets_text());
+                Ok(())
+            }),
+        LineCommand::SelfHeating => arg
+            .and_then(|arg| arg.parse::<u8>().ok())
+            .ok_or("Self-heating must be S<tenths of C>!")
+            .map(|tenths| {
+                set_self_heating_coeff(tenths as f32 / 10.0);
+                let _ = text.push_str("Self-heating: ");
+                let _ = push_tenths(&mut text, tenths as i32);
+                let _ = text.push_str(" C");
+            }),
+        LineCommand::SetClock => arg
+            .and_then(|arg| arg.parse::<u32>().ok())
+            .ok_or("Clock must be D<unix seconds>!")
+            .map(|unix_s| {
+                free(|cs| {
+                    CLOCK_OFFSET_S
+                        .borrow(*cs)
+                        .replace(Some(unix_s.wrapping_sub(uptime_s())))
+                });
+                let _ = text.push_str("Clock: ");
+                let _ = text.push_str(&TimeOfDay::from_unix_s(unix_s).format());
+            }),
+        LineCommand::SetProfile => arg
+            .and_then(MonitoringProfile::from_name)
+            .ok_or("Profile must be indoor, outdoor, greenhouse or serverroom!")
+            .map(|profile| {
+                set_monitoring_profile(profile);
+                let _ = text.push_str("Profile: ");
+                let _ = text.push_str(profile.name());
+            }),
+        LineCommand::Word => return process_word_command(arg),
+    };
 
-                // check we read 40 bits and that the checksum matches
-                if (j >= 40) && (data[4] == (data[0] + data[1] + data[2] + data[3])) {
-                    // converting read temperature to float
-                    t = data[2] as f32;
+    free(|cs| {
+        if let Some(ref mut uart) = *serial::UART.borrow(*cs).borrow_mut() {
+            let _ = match result {
+                Ok(()) => serial::write_str(uart, &text)
+                    .and_then(|_| serial::write_str(uart, RESPONSE_END)),
+                Err(message) => serial::write_str(uart, message)
+                    .and_then(|_| serial::write_str(uart, ERROR_END)),
+            };
+        }
+    });
+}
 
-                    let value = data[3] % 128;
-                    match value {
-                        0..=9 => t += (data[3] % 128 / 10) as f32,
+// Alarms in the event log section of :fullreport
+const FULL_REPORT_ALARMS: usize = 20;
 
-                        10..=100 => t += (data[3] % 128 / 100) as f32,
+// Writes the :fullreport of a field service visit, the outputs of the
+// other commands under `=== SECTION ===` headers. About 2.5 KB at most,
+// the alarms take 1.5 KB of it, within the 4 KB of a terminal buffer.
+fn write_full_report(uart: &mut serial::Uart, cs: CriticalSection) -> fmt::Result {
+    let mut out = UartWriter(uart);
+    out.write_str("=== DEVICE ===\r\n")?;
+    write!(out, "Firmware: {}\r\nUptime: {} s\r\n", env!("CARGO_PKG_VERSION"), uptime_s())?;
+    if let Some(id) = *SENSOR_ID.borrow(cs).borrow() {
+        write!(out, "Sensor ID: {}\r\n", id)?;
+    }
 
-                        _ => t += ((data[3] % 128) as i32 / 1000) as f32,
-                    }
+    out.write_str("=== READINGS ===\r\n")?;
+    match *DATA.borrow(cs).borrow() {
+        Some(reading) => {
+            let dp = dew_point(reading.temperature, reading.humidity);
+            serial::write_reading(out.0, &reading, dp).map_err(|_| fmt::Error)?;
+        }
+        None => out.write_str("No reading yet\r\n")?,
+    }
 
-                    // The left-most digit indicate the negative sign.
-                    if data[3] >= 128 {
-                        t = -t;
-                    }
+    out.write_str("=== 24 HOURS ===\r\n")?;
+    derived::write_climate_report(&HOURLY_LOG.borrow(cs).borrow(), &mut out)?;
 
-                    // Humidity
-                    h = data[0] as f32;
+    out.write_str("\r\n=== SENSOR HEALTH ===\r\n")?;
+    let pattern = FAILURE_ANALYZER.borrow(cs).borrow().analyze();
+    write!(out, "Failures: {}\r\n", pattern)?;
+    write!(out, "{}\r\n", *BIT_ERROR_RATE.borrow(cs).borrow())?;
+
+    out.write_str("=== CONFIGURATION ===\r\n")?;
+    write!(
+        out,
+        "Interval: {} s\r\nCalibration: {}\r\n",
+        update_interval_s(),
+        offsets_text()
+    )?;
+    CALIBRATION_HISTORY.borrow(cs).borrow().print_history(&mut out)?;
+
+    out.write_str("=== EVENTS ===\r\n")?;
+    ALARM_LOG
+        .borrow(cs)
+        .borrow()
+        .print_latest(&mut out, FULL_REPORT_ALARMS)
+}
+
+// Runs a `:<name> <args>` command. The responses are longer than the other
+// LineCommands', they are written straight to the UART.
+fn process_word_command(line: Option<&str>) {
+    let (command, args) = match line.and_then(WordCommand::parse) {
+        Some(parsed) => parsed,
+        None => {
+            free(|cs| {
+                if let Some(ref mut uart) = *serial::UART.borrow(*cs).borrow_mut() {
+                    let _ = serial::write_str(uart, "Unknown command!")
+                        .and_then(|_| serial::write_str(uart, ERROR_END));
+                }
+            });
+            return;
+        }
+    };
+
+    // Reads the sensor with interrupts enabled, the edges are timed by them
+    let validation_read = match command {
+        WordCommand::ValidateProtocol => Some(read_data_once()),
+        _ => None,
+    };
+
+    free(|cs| {
+        if let Some(ref mut uart) = *serial::UART.borrow(*cs).borrow_mut() {
+            let result: Result<(), &'static str> = match command {
+                WordCommand::DumpProtocol => {
+                    let _ = dht::diag::PROTOCOL_CAPTURE
+                        .borrow(*cs)
+                        .borrow()
+                        .write_csv(&mut UartWriter(uart));
+                    Ok(())
+                }
+                WordCommand::BitErrorRate => {
+                    let ber = *BIT_ERROR_RATE.borrow(*cs).borrow();
+                    let _ = write!(UartWriter(uart), "{}", ber);
+                    Ok(())
+                }
+                WordCommand::ValidateProtocol => match validation_read {
+                    Some(Err(_)) => Err("Sensor read failed!"),
+                    _ => dht::diag::PROTOCOL_CAPTURE
+                        .borrow(*cs)
+                        .borrow()
+                        .trace()
+                        .ok_or("No edges captured, needs protocol_capture!")
+                        .map(|trace| {
+                            let _ = dht::validate::write_report(&trace, &mut UartWriter(uart));
+                        }),
+                },
+                WordCommand::ClimateZone => {
+                    let _ = derived::write_climate_report(
+                        &HOURLY_LOG.borrow(*cs).borrow(),
+                        &mut UartWriter(uart),
+                    );
+                    Ok(())
+                }
+                WordCommand::InjectFault => {
+                    #[cfg(feature = "fault_injection")]
+                    let result = test_utils::process_inject(args, &mut UartWriter(uart));
+                    #[cfg(not(feature = "fault_injection"))]
+                    let result = {
+                        let _ = args;
+                        Err("Fault injection is not built in!")
+                    };
+                    result
+                }
+                WordCommand::SelectMuxChannel => {
+                    // The following reads come from the selected sensor.
+                    // Without a channel prints the selected one.
+                    #[cfg(feature = "sensor_mux")]
+                    let result = match sensor::SENSOR_MUX.borrow(*cs).borrow_mut().as_mut() {
+                        Some(mux) if args.is_empty() => Ok(mux.channel()),
+                        Some(mux) => args
+                            .parse::<u8>()
+                            .ok()
+                            .filter(|&channel| mux.select(channel).is_ok())
+                            .ok_or("Mux must be :mux <0-7>!"),
+                        None => Err("No multiplexer!"),
+                    }
+                    .map(|channel| {
+                        let _ = write!(UartWriter(uart), "Mux: channel {}", channel);
+                    });
+                    #[cfg(not(feature = "sensor_mux"))]
+                    let result = Err("Sensor multiplexer is not built in!");
+                    result
+                }
+                WordCommand::FullReport => {
+                    let _ = write_full_report(uart, *cs);
+                    Ok(())
+                }
+                WordCommand::DumpCsv => {
+                    #[cfg(feature = "spi_flash")]
+                    let result = match storage::CSV_LOGGER.borrow(*cs).borrow_mut().as_mut() {
+                        Some(logger) => {
+                            let _ = logger.dump(&mut UartWriter(uart), feed_watchdog);
+                            Ok(())
+                        }
+                        None => Err("No flash log!"),
+                    };
+                    #[cfg(not(feature = "spi_flash"))]
+                    let result = Err("SPI flash log is not built in!");
+                    result
                 }
+                WordCommand::EraseLog => {
+                    #[cfg(feature = "spi_flash")]
+                    let result = match storage::CSV_LOGGER.borrow(*cs).borrow_mut().as_mut() {
+                        Some(logger) => {
+                            logger.erase(feed_watchdog);
+                            let _ = serial::write_str(uart, "Flash log erased");
+                            Ok(())
+                        }
+                        None => Err("No flash log!"),
+                    };
+                    #[cfg(not(feature = "spi_flash"))]
+                    let result = Err("SPI flash log is not built in!");
+                    result
+                }
+            };
+            let _ = match result {
+                Ok(()) => serial::write_str(uart, RESPONSE_END),
+                Err(message) => serial::write_str(uart, message)
+                    .and_then(|_| serial::write_str(uart, ERROR_END)),
+            };
+        }
+    });
+}
+
+// Handles a key of the calibration mode. The offsets apply from the next
+// reading, so they can be stepped while watching a reference thermometer.
+fn process_calibration_key(key: CalibrationKey) {
+    match key {
+        CalibrationKey::Select(kind) => free(|cs| {
+            CALIBRATION_TARGET.borrow(*cs).replace(kind);
+        }),
+        CalibrationKey::Step(delta) => {
+            let kind = free(|cs| *CALIBRATION_TARGET.borrow(*cs).borrow());
+            step_offset(kind, delta);
+        }
+        CalibrationKey::Write => {
+            record_calibration(uptime_s(), true);
+            if let Err(e) = save_calibration_offsets() {
+                serial::report_error_chain(&["calibration", "flash"], &e);
             }
+            CALIBRATING.store(false, Ordering::Relaxed);
+        }
+    }
+
+    free(|cs| {
+        if let Some(ref mut uart) = *serial::UART.borrow(*cs).borrow_mut() {
+            let _ = serial::write_str(uart, &offsets_text());
+            if key == CalibrationKey::Write {
+                let _ = serial::write_str(uart, " saved");
+            }
+            let _ = serial::write_str(uart, RESPONSE_END);
         }
     });
+}
 
-    // Put signal pin back to SIGNAL_PIN for next call
-    if replaced {
-        free(|cs| {
-            SIGNAL_PIN
-                .borrow(*cs)
-                .replace(Some(signal_pin_saved.unwrap().into_push_pull_output()));
-        });
+// Microseconds since boot from the cycle counter, wraps after about 71 minutes
+fn now_us() -> u32 {
+    (mcycle::read64() / SYSCLK_MHZ as u64) as u32
+}
+
+// Extra attempts after a failed read before giving up
+const MAX_RETRIES: u32 = 3;
+
+// Pause between attempts, lets the sensor return to idle
+const RETRY_DELAY_MS: u32 = 100;
+
+// Reads that failed after all retries
+static READ_ERRORS: AtomicU32 = AtomicU32::new(0);
+
+// Data bytes of the latest frame of the sensor, stored before the checksum is
+// known, for the raw data page
+static RAW_BYTES: Mutex<RefCell<[u8; 5]>> = Mutex::new(RefCell::new([0; 5]));
+
+// Bits collected in the latest frame, 40 when complete
+static RAW_BIT_COUNT: AtomicU8 = AtomicU8::new(0);
+
+// Whether the checksum of the latest frame matched, false for an incomplete frame
+static RAW_CHECKSUM_OK: AtomicBool = AtomicBool::new(false);
+
+//Function for reading data from the sensor, retried up to MAX_RETRIES times.
+//read_once does a single read of one of the sensors.
+fn read_data(
+    read_once: fn() -> Result<SensorReading, SensorError>,
+) -> Result<SensorReading, SensorError> {
+    let mut retries = 0;
+    loop {
+        match read_once() {
+            Ok(reading) => return Ok(reading),
+            Err(e) if retries == MAX_RETRIES => {
+                READ_ERRORS.fetch_add(1, Ordering::Relaxed);
+                return Err(e);
+            }
+            Err(_) => {
+                retries += 1;
+                // Waited with interrupts enabled, the delay is taken out of its cell
+                if let Some(mut delay) = sync::take(&DELAY) {
+                    delay.delay_ms(RETRY_DELAY_MS);
+                    sync::put_back(&DELAY, delay);
+                }
+            }
+        }
     }
+}
+
+// Runs read with the sensor and the delay moved out of their cells, so that
+// interrupts stay enabled during the read. None when either isn't set up.
+fn with_sensor<S, R>(
+    cell: &Mutex<RefCell<Option<S>>>,
+    read: impl FnOnce(&mut S, &mut McycleDelay) -> R,
+) -> Option<R> {
+    let mut sensor = sync::take(cell)?;
+    let mut delay = match sync::take(&DELAY) {
+        Some(delay) => delay,
+        None => {
+            sync::put_back(cell, sensor);
+            return None;
+        }
+    };
+    let result = read(&mut sensor, &mut delay);
+    sync::put_back(cell, sensor);
+    sync::put_back(&DELAY, delay);
+    Some(result)
+}
 
-    //Return temp and humidity values
-    if t < 1000.0 && h < 1000.0 {
-        return Ok((t, h));
+// Single read of the sensor. The edges are timestamped by the TIMER4 capture
+// hardware, so interrupts during the read don't disturb the decoding.
+fn read_data_once() -> Result<SensorReading, SensorError> {
+    #[cfg(feature = "fault_injection")]
+    if let Some(result) = test_utils::injected_result() {
+        return result;
     }
 
-    // return this when something failed
-    Err("Could not read values!")
+    with_sensor(&SENSOR, |sensor, delay| {
+        // Give the multiplexer time to settle on the selected sensor
+        #[cfg(feature = "sensor_mux")]
+        if free(|cs| sensor::SENSOR_MUX.borrow(*cs).borrow().is_some()) {
+            delay.delay_us(sensor::mux::MUX_SETTLE_US);
+        }
+
+        sensor.set_timeout_us(SENSOR_TIMEOUT_US.load(Ordering::Relaxed));
+        let result = sensor.read(delay);
+
+        if let Some(frame) = sensor.last_frame() {
+            RAW_BIT_COUNT.store(frame.bit, Ordering::Relaxed);
+            let checksum_mismatch = matches!(result, Err(SensorError::ChecksumMismatch { .. }));
+            RAW_CHECKSUM_OK.store(frame.bit >= 40 && !checksum_mismatch, Ordering::Relaxed);
+
+            free(|cs| {
+                RAW_BYTES.borrow(*cs).replace(frame.data);
+
+                if frame.bit >= 40 {
+                    LAST_READ_QUALITY
+                        .borrow(*cs)
+                        .replace(compute_read_quality(&frame.pulse_widths_us));
+                    BIT_ERROR_RATE
+                        .borrow(*cs)
+                        .borrow_mut()
+                        .update(&frame.pulse_widths_us);
+                }
+
+                if result.is_ok() {
+                    let mut sensor_id = SENSOR_ID.borrow(*cs).borrow_mut();
+                    if sensor_id.is_none() {
+                        *sensor_id =
+                            Some(SensorIdentity::from_pulse_widths(&frame.pulse_widths_us));
+                    }
+                }
+            });
+        }
+
+        result
+    })
+    .unwrap_or(Err(SensorError::PinError))
+}
+
+// Each sensor's readings cross-checked against its previous accepted reading
+static READ_VERIFIERS: Mutex<RefCell<[ReadVerifier; 2]>> =
+    Mutex::new(RefCell::new([ReadVerifier::new(), ReadVerifier::new()]));
+
+// Reads sensor `index`, 0 or 1, once and cross-checks the reading against the
+// previous accepted one. Not read twice, the sensor needs 1 s between reads.
+fn read_data_verified(
+    index: usize,
+    read_once: fn() -> Result<SensorReading, SensorError>,
+) -> Result<SensorReading, SensorError> {
+    let reading = read_data(read_once)?;
+    Ok(free(|cs| READ_VERIFIERS.borrow(*cs).borrow_mut()[index].verify(reading)))
+}
+
+// Single read of the second sensor. Its edges are timestamped by polling,
+// so this one read, about 25 ms, runs with interrupts disabled. The retry
+// delays between reads don't.
+#[cfg(feature = "second_sensor")]
+fn read_second_once() -> Result<SensorReading, SensorError> {
+    with_sensor(&SECOND_SENSOR, |sensor, delay| {
+        sensor.set_timeout_us(SENSOR_TIMEOUT_US.load(Ordering::Relaxed));
+        free(|_| sensor.read(delay))
+    })
+    .unwrap_or(Err(SensorError::PinError))
+}
+
+// Reads one of the two sensors, taking turns between updates, and combines
+// it with the latest reading of the other. Fails only when neither has a
+// valid reading.
+#[cfg(feature = "second_sensor")]
+fn read_dual() -> Result<SensorReading, SensorError> {
+    let index = NEXT_SENSOR.fetch_xor(1, Ordering::Relaxed) as usize;
+    let read_once = if index == 0 {
+        read_data_once
+    } else {
+        read_second_once
+    };
+    let result = read_data_verified(index, read_once);
+
+    free(|cs| {
+        let mut dual = DUAL_READINGS.borrow(*cs).borrow_mut();
+        dual.record(index, result.ok());
+        SENSOR_DISAGREEMENT.store(dual.disagree(), Ordering::Relaxed);
+        // Nothing to combine only when this read failed as well
+        match dual.combined() {
+            Some(reading) => Ok(reading),
+            None => result,
+        }
+    })
+}
+
+// Reads the sensor and passes the reading on to the display, history, alerts
+// and logs. Called from the main loop when TIMER1 has set READ_DUE.
+fn update_reading() {
+    let now_s = uptime_s();
+    #[cfg(not(feature = "second_sensor"))]
+    let data = read_data_verified(0, read_data_once);
+    #[cfg(feature = "second_sensor")]
+    let data = read_dual();
+
+    free(|cs| {
+        FAILURE_ANALYZER
+            .borrow(*cs)
+            .borrow_mut()
+            .record_result(data.is_ok(), now_s);
+    });
+    diag::record_read_status(data.is_ok());
+
+    match data {
+        Ok(raw) => {
+            // Heat of the MCU in the enclosure is removed after the calibration offsets
+            let v = calibration_offset().apply(&raw);
+            let duty = free(|cs| power::DUTY_CYCLE.borrow(*cs).borrow_mut().take_percent());
+            let v = SensorReading {
+                temperature: apply_self_heating_correction(v.temperature, duty),
+                ..v
+            };
+            #[cfg(feature = "defmt")]
+            defmt::info!("T={} H={} ok", v.temperature, v.humidity);
+            free(|cs| {
+                METRICS.borrow(*cs).borrow_mut().reads_ok += 1;
+                LAST_RAW_READING.borrow(*cs).replace(Some(raw));
+
+                // Raw reading for calibration analysis on a connected PC
+                if let Some(ref mut uart) = *serial::UART.borrow(*cs).borrow_mut() {
+                    let dp = dew_point(raw.temperature, raw.humidity);
+                    let _ = serial::write_reading(uart, &raw, dp);
+                }
+
+                let mut filter = READING_FILTER.borrow(*cs).borrow_mut();
+                filter.push(v);
+                let filtered = filter.average();
+                let previous = DATA.borrow(*cs).replace(Some(filtered));
+                // A noticeable change wakes the backlight like a button press
+                if previous.map_or(false, |p| task::exceeds_hysteresis(&p, &filtered)) {
+                    display::backlight::reset_idle();
+                }
+                LAST_READ_OK.store(true, Ordering::Relaxed);
+                update_alert_pin(filtered.temperature, filtered.humidity);
+                buzzer::check_thresholds(filtered.temperature, filtered.humidity);
+                MIN_MAX.borrow(*cs).borrow_mut().update(&filtered);
+                TEMP_PEAK.borrow(*cs).borrow_mut().update(filtered.temperature);
+                let mut history = HISTORY.borrow(*cs).borrow_mut();
+                history.push(filtered);
+                HOURLY_LOG.borrow(*cs).borrow_mut().push(
+                    hour_of_day(now_s),
+                    filtered.temperature,
+                    filtered.humidity,
+                );
+
+                // Space the reads out while nothing changes, back to normal on the first change
+                let mode = PowerMode::from_history(&history);
+                power::set_power_mode(mode);
+                UPDATE_INTERVAL_TICKS.store(
+                    match mode {
+                        PowerMode::Normal => update_interval_s(),
+                        PowerMode::Lowered => power::LOWERED_INTERVAL_S,
+                    },
+                    Ordering::Relaxed,
+                );
+
+                #[cfg(feature = "spi_flash")]
+                if let Some(ref mut logger) = *storage::CSV_LOGGER.borrow(*cs).borrow_mut() {
+                    logger.log(now_s, v.temperature, v.humidity);
+                }
+
+                let mut warmup = WARMUP_MONITOR.borrow(*cs).borrow_mut();
+                warmup.update(now_s, v.temperature);
+
+                // Notify alert outputs only when the alert state changes. No
+                // alerts until the sensor has settled after power-on. Every
+                // alert output and the alarm log go by the filtered reading,
+                // like the alert pin and the buzzer above.
+                let thresholds = monitoring_profile().defaults();
+                let alert = warmup.is_settled()
+                    && thresholds.is_exceeded(filtered.temperature, filtered.humidity);
+                if ALERT_STATE.borrow(*cs).replace(alert) != alert {
+                    ALERT_DISPATCHER.borrow(*cs).borrow().dispatch(alert);
+                }
+
+                let mut alarm_log = ALARM_LOG.borrow(*cs).borrow_mut();
+                match thresholds.violation(filtered.temperature, filtered.humidity) {
+                    Some((kind, threshold, reading)) if !alarm_log.is_active() => {
+                        alarm_log.alarm_started(now_s, reading, kind, threshold)
+                    }
+                    Some((_, _, reading)) => alarm_log.update(reading),
+                    None => alarm_log.alarm_ended(now_s),
+                }
+            });
+        }
+        // DATA, min/max and history keep the last good values, the display shows the error
+        Err(e) => {
+            #[cfg(feature = "defmt")]
+            defmt::warn!("read failed: {:?}", e);
+            free(|cs| METRICS.borrow(*cs).borrow_mut().record_error(e));
+            serial::report_error("sensor", &e);
+            LAST_READ_OK.store(false, Ordering::Relaxed);
+
+            // After MAX_RETRIES failed updates in a row the main page shows the
+            // thermistor's temperature. Filter, min/max, history and alerts
+            // are left to the DHT, the thermistor measures no humidity.
+            #[cfg(feature = "ntc_fallback")]
+            if diag::CONSECUTIVE_FAILURES.load(Ordering::Relaxed) as u32 >= MAX_RETRIES {
+                if let Some(reading) = adc_sensor::read_fallback() {
+                    free(|cs| DATA.borrow(*cs).replace(Some(reading)));
+                    LAST_READ_OK.store(true, Ordering::Relaxed);
+                }
+            }
+        }
+    }
 }
 
 //Interrupt handler function
@@ -179,47 +992,68 @@ fn read_data() -> Result<(f32, f32), &'static str> {
 #[no_mangle]
 fn TIMER1() {
     // Only update on specific intervals, didn't find way to setup interrupt timer freq below 1 Hz
-    let mut do_update = false;
-    unsafe {
-        if TIMER_COUNTER % UPDATE_INTERVAL == 0 {
-            do_update = true;
-        }
-        TIMER_COUNTER += 1;
+    // The interrupt is the only writer of the counters, so a relaxed read is enough
+    let now_s = uptime_s();
+    let interval = UPDATE_INTERVAL_TICKS.load(Ordering::Relaxed);
+    let mut do_update = now_s % interval == 0 || FORCE_READ.swap(false, Ordering::Relaxed);
+    let seconds = TIMER_COUNTER.load(Ordering::Relaxed) + 1;
+    if seconds >= SECONDS_PER_DAY {
+        UPTIME_DAYS.fetch_add(1, Ordering::Release);
+        TIMER_COUNTER.store(0, Ordering::Release);
+    } else {
+        TIMER_COUNTER.store(seconds, Ordering::Release);
+    }
+
+    diag::blink_status_led();
+    display::backlight::idle_tick();
+    free(|cs| TEMP_PEAK.borrow(*cs).borrow_mut().tick());
+
+    // Sensor must not be queried right after power-on
+    if !SENSOR_WARMUP.is_ready(now_s) {
+        do_update = false;
     }
 
+    // The read itself takes up to seconds with the retries, the main loop does it
     if do_update {
-        let data = read_data();
-        match data {
-            Ok(v) => {
-                free(|cs| {
-                    if let Some(ref mut data_stored) = DATA.borrow(*cs).borrow_mut().deref_mut() {
-                        *data_stored = v;
-                    }
-                });
-            }
-            // Value t = 112 h = 112 used to show error in reading
-            Err(_e) => {
-                free(|cs| {
-                    if let Some(ref mut data_stored) = DATA.borrow(*cs).borrow_mut().deref_mut() {
-                        *data_stored = (112.0, 112.0);
-                    }
-                });
-            }
-        }
+        READ_DUE.store(true, Ordering::Relaxed);
     }
 
-    free(|cs| {
-        if let Some(ref mut timer) = TIMER.borrow(*cs).borrow_mut().deref_mut() {
-            timer.clear_update_interrupt_flag();
-        }
-    });
+    let guard = GlobalInterruptGuard::new();
+    let mut timer = TIMER.borrow(guard.cs()).borrow_mut();
+    if let Some(timer) = timer.deref_mut() {
+        timer.clear_update_interrupt_flag();
+    }
 }
 
 #[entry]
 fn main() -> ! {
+    // defmt output over RTT, read by probe-rs through the JTAG probe
+    #[cfg(feature = "defmt")]
+    rtt_target::rtt_init_defmt!();
+
+    // Settings kept over resets, read before any peripheral is set up. The
+    // offsets are applied to every reading in the main loop.
+    let boot_config = config::load_boot_config();
+    TEMP_OFFSET_TENTH.store(boot_config.temp_offset_tenth_deg, Ordering::Relaxed);
+    HUM_OFFSET.store(boot_config.hum_offset_percent, Ordering::Relaxed);
+    let unit = display::TemperatureUnit::from_u8(boot_config.temperature_unit);
+    display::unit::set_temperature_unit(unit);
+    // The interval is saved in seconds, one that isn't an option keeps the default
+    if let Some(idx) = INTERVAL_OPTIONS
+        .iter()
+        .position(|&s| s == boot_config.update_interval_s as u32)
+    {
+        set_update_interval_idx(idx as u8);
+    }
+
     let dp = pac::Peripherals::take().unwrap();
 
-    // Configure clocks, sysclk set to 80 MHz to work properly with count_ in read_data
+    let reset_cause = power::read_reset_cause(&dp.RCU);
+    free(|cs| {
+        power::RESET_CAUSE.borrow(*cs).replace(Some(reset_cause));
+    });
+
+    // Configure clocks, sysclk set to 80 MHz as assumed by SYSCLK_MHZ and the capture prescaler
     let mut rcu = dp
         .RCU
         .configure()
@@ -230,19 +1064,176 @@ fn main() -> ! {
 
     let gpioa = dp.GPIOA.split(&mut rcu);
     let gpiob = dp.GPIOB.split(&mut rcu);
+    let gpioc = dp.GPIOC.split(&mut rcu);
 
     let out_pin = gpioa.pa0.into_push_pull_output();
+    // Hardware timestamps of the sensor's edges, channel 0 of TIMER4 is on PA0
+    let capture = Timer4Capture::new(dp.TIMER4);
 
     let delay = McycleDelay::new(&rcu.clocks);
+    let mut delay2 = McycleDelay::new(&rcu.clocks);
+
+    // Thermistor on PA4 for when the DHT fails
+    #[cfg(feature = "ntc_fallback")]
+    {
+        let ntc = adc_sensor::NtcSensor::new(dp.ADC0, gpioa.pa4.into_analog(), &mut delay2);
+        free(|cs| {
+            adc_sensor::NTC_SENSOR.borrow(*cs).replace(Some(ntc));
+        });
+    }
 
     free(|cs| {
-        SIGNAL_PIN.borrow(*cs).replace(Some(out_pin));
+        SENSOR.borrow(*cs).replace(Some(Dht::new(out_pin, capture)));
         DELAY.borrow(*cs).replace(Some(delay));
     });
 
+    // Second sensor on PB6, open-drain like a shared one-wire bus
+    #[cfg(feature = "second_sensor")]
+    {
+        let second_out_pin = gpiob.pb6.into_open_drain_output();
+        let second = Dht::new(second_out_pin, PolledCapture::new(6));
+        free(|cs| {
+            SECOND_SENSOR.borrow(*cs).replace(Some(second));
+        });
+    }
+
+    // USART0 on the debug connector for the readings output
+    let uart_tx = gpioa.pa9.into_alternate_push_pull();
+    let uart_rx = gpioa.pa10.into_floating_input();
+    let uart_config = UartConfig {
+        baudrate: serial::UART_BAUD.bps(),
+        parity: Parity::ParityNone,
+        stopbits: StopBits::STOP1,
+    };
+    let mut uart = Serial::new(dp.USART0, (uart_tx, uart_rx), uart_config, &mut afio, &mut rcu);
+    uart.listen(UartEvent::Rxne);
+    let (uart_tx, uart_rx) = uart.split();
+    free(|cs| {
+        serial::UART.borrow(*cs).replace(Some(uart_tx));
+        serial::UART_RX.borrow(*cs).replace(Some(uart_rx));
+    });
+
+    // TIMER5 samples the button for debouncing and times the buzzer's beeps
+    let mut sample_timer = Timer::timer5(dp.TIMER5, input::button::SAMPLE_RATE_HZ.hz(), &mut rcu);
+    sample_timer.listen(Event::Update);
+    free(|cs| {
+        input::button::SAMPLE_TIMER.borrow(*cs).replace(Some(sample_timer));
+    });
+
+    // Page button and status LED, PA1 and PA2 are address pins of the
+    // multiplexer when there is one
+    #[cfg(not(feature = "sensor_mux"))]
+    {
+        let button = gpioa.pa1.into_pull_up_input();
+        let status_led = gpioa.pa2.into_push_pull_output();
+        free(|cs| {
+            input::button::BUTTON_PIN.borrow(*cs).replace(Some(button));
+            diag::STATUS_LED.borrow(*cs).replace(Some(status_led));
+        });
+    }
+
+    // Multiplexer address pins, selects channel 0 at start
+    #[cfg(feature = "sensor_mux")]
+    {
+        let mux = sensor::mux::Mux4051::new(
+            gpioa.pa1.into_push_pull_output(),
+            gpioa.pa2.into_push_pull_output(),
+            gpioa.pa4.into_push_pull_output(),
+        );
+        free(|cs| {
+            sensor::SENSOR_MUX.borrow(*cs).replace(Some(mux));
+        });
+    }
+
+    // CSV log on external SPI flash
+    #[cfg(feature = "spi_flash")]
+    {
+        let spi = longan_nano::hal::spi::Spi::spi1(
+            dp.SPI1,
+            (
+                gpiob.pb13.into_alternate_push_pull(),
+                gpiob.pb14.into_floating_input(),
+                gpiob.pb15.into_alternate_push_pull(),
+            ),
+            embedded_hal::spi::MODE_0,
+            8.mhz(),
+            &mut rcu,
+        );
+        let mut flash =
+            storage::spiflash::W25q32Driver::new(spi, gpiob.pb12.into_push_pull_output());
+        // Without the chip MISO floats and the pointer page would be garbage
+        let id = flash.read_id();
+        if id == storage::spiflash::W25Q32_JEDEC_ID {
+            let logger = storage::spiflash::CsvLogger::new(flash);
+            free(|cs| {
+                storage::CSV_LOGGER.borrow(*cs).replace(Some(logger));
+            });
+        } else {
+            serial::report_error("flash", &format_args!("unknown JEDEC ID {:06X}", id));
+        }
+    }
+
+    // IR remote receiver on PB10
+    #[cfg(feature = "ir_remote")]
+    {
+        let _ir_pin = gpiob.pb10.into_floating_input();
+        input::ir::setup_exti();
+    }
+
+    // Alert outputs
+    let mut alert_led = RED::new(gpioc.pc13);
+    alert_led.off();
+    let relay_pin = gpiob.pb9.into_push_pull_output();
+    let alert_pin = gpioa.pa8.into_push_pull_output();
+    free(|cs| {
+        ALERT_LED.borrow(*cs).replace(Some(alert_led));
+        RELAY_PIN.borrow(*cs).replace(Some(relay_pin));
+        ALERT_PIN.borrow(*cs).replace(Some(alert_pin));
+
+        let mut dispatcher = ALERT_DISPATCHER.borrow(*cs).borrow_mut();
+        dispatcher.register(led_alert).ok();
+        dispatcher.register(relay_alert).ok();
+    });
+
+    // Passive piezo buzzer, 2 kHz PWM on PB8 from TIMER3 channel 2
+    let _buzzer_pin = gpiob.pb8.into_alternate_push_pull();
+    let piezo = buzzer::PwmBuzzer::new(dp.TIMER3);
+    free(|cs| {
+        buzzer::BUZZER.borrow(*cs).replace(Some(piezo));
+    });
+
+    // Ambient light for the backlight brightness, LDR on PA3
+    #[cfg(feature = "ambient_light")]
+    {
+        let ldr = display::ldr::Ldr::new(dp.ADC1, gpioa.pa3.into_analog(), &mut delay2);
+        free(|cs| {
+            display::ldr::LDR.borrow(*cs).replace(Some(ldr));
+        });
+        display::ldr::sample();
+    }
+
+    // Battery level for the icon in the top right corner, cell on PA3 and charger on PB7
+    #[cfg(feature = "battery")]
+    {
+        let monitor = battery::VoltageMonitor::new(
+            dp.ADC1,
+            gpioa.pa3.into_analog(),
+            gpiob.pb7.into_pull_up_input(),
+            &mut delay2,
+        );
+        free(|cs| {
+            battery::VOLTAGE_MONITOR.borrow(*cs).replace(Some(monitor));
+        });
+        battery::sample();
+    }
+
+    // Backlight PWM on PA11, TIMER0 channel 3. PA11 is USB D-, unused by the firmware.
+    let _backlight_pin = gpioa.pa11.into_alternate_push_pull();
+    let backlight = display::backlight::Backlight::new(dp.TIMER0);
+
     let lcd_pins = lcd_pins!(gpioa, gpiob);
     let mut lcd = lcd::configure(dp.SPI0, lcd_pins, &mut afio, &mut rcu);
-    let (width, height) = (lcd.size().width as i32, lcd.size().height as i32);
+    display::set_orientation(&mut lcd, display::DISPLAY_ORIENTATION);
 
     //Set timer
     let mut timer = Timer::timer1(dp.TIMER1, 1.hz(), &mut rcu);
@@ -263,48 +1254,80 @@ fn main() -> ! {
     );
     unsafe { pac::ECLIC::unmask(pac::Interrupt::TIMER1) };
 
+    pac::ECLIC::setup(
+        pac::Interrupt::USART0,
+        TriggerType::Level,
+        Level::L1,
+        Priority::P2,
+    );
+    unsafe { pac::ECLIC::unmask(pac::Interrupt::USART0) };
+
+    #[cfg(feature = "ir_remote")]
+    {
+        pac::ECLIC::setup(
+            pac::Interrupt::EXTI_LINE15_10,
+            TriggerType::Level,
+            Level::L1,
+            Priority::P2,
+        );
+        unsafe { pac::ECLIC::unmask(pac::Interrupt::EXTI_LINE15_10) };
+    }
+
+    pac::ECLIC::setup(
+        pac::Interrupt::TIMER5,
+        TriggerType::Level,
+        Level::L1,
+        Priority::P1,
+    );
+    unsafe { pac::ECLIC::unmask(pac::Interrupt::TIMER5) };
+
+    // End of a frame transfer to the LCD
+    #[cfg(feature = "lcd_dma")]
+    {
+        pac::ECLIC::setup(
+            pac::Interrupt::DMA0_CHANNEL2,
+            TriggerType::Level,
+            Level::L1,
+            Priority::P1,
+        );
+        unsafe { pac::ECLIC::unmask(pac::Interrupt::DMA0_CHANNEL2) };
+    }
+
     //Enable interrupts
     unsafe { riscv::interrupt::enable() };
 
+    free(|cs| {
+        if let Some(ref mut uart) = *serial::UART.borrow(*cs).borrow_mut() {
+            let _ = serial::write_str(uart, "Reset: ");
+            let _ = serial::write_str(uart, reset_cause.label());
+            let _ = serial::write_str(uart, "\r\n");
+        }
+    });
+
+    // Splash screen, skipped after a watchdog reset to get back to reading
+    // the sensor at once. Interrupts are already enabled, so the timer keeps
+    // ticking during the busy wait.
+    if !reset_cause.is_watchdog() {
+        ui::splash::draw_splash(&mut lcd);
+        delay2.delay_ms(2000);
+    }
+
+    // The splash screen covers the sensor's warmup, so it can be read here
+    selftest::self_test(&mut delay2, &mut lcd);
+
     // Clear screen
-    Rectangle::new(Point::new(0, 0), Size::new(width as u32, height as u32))
-        .into_styled(PrimitiveStyle::with_fill(Rgb565::BLACK))
+    Rectangle::new(Point::new(0, 0), display::screen_size())
+        .into_styled(PrimitiveStyle::with_fill(BG_COLOR))
         .draw(&mut lcd)
         .unwrap();
 
-    let style = MonoTextStyleBuilder::new()
-        .font(&FONT_10X20)
-        .text_color(Rgb565::new(50, 50, 50))
-        .background_color(Rgb565::BLACK)
-        .build();
+    // Watchdog fed by the main loop, started after the splash screen's busy wait
+    let mut watchdog = FreeWatchdog::new(dp.FWDGT);
+    watchdog.start(WATCHDOG_TIMEOUT_MS.ms());
 
-    loop {
-        // Write temperature and humidity values on screen
-        free(|cs| {
-            if let Some(ref mut data) = DATA.borrow(*cs).borrow_mut().deref_mut() {
-                let mut t_as_text: String<10> = String::from(data.0 as i32);
-                t_as_text.push('°').unwrap();
-                t_as_text.push('C').unwrap();
-                t_as_text.push(' ').unwrap(); // Push extra spaces to overwrite last print if string gets shorter (e.g. 12°C -> 9°C )
-                t_as_text.push(' ').unwrap();
-
-                Text::new(t_as_text.as_str(), Point::new(40, 35), style)
-                    .draw(&mut lcd)
-                    .unwrap();
-
-                let mut h_as_text: String<10> = String::from(data.1 as i32);
-                h_as_text.push('%').unwrap();
-                h_as_text.push(' ').unwrap(); // Push extra spaces to overwrite last print if string gets shorter (e.g. 15% -> 9%)
-                t_as_text.push(' ').unwrap();
-                Text::new(h_as_text.as_str(), Point::new(40, 60), style)
-                    .draw(&mut lcd)
-                    .unwrap();
-            }
-        });
+    #[cfg(feature = "lcd_dma")]
+    let lcd = display::dma::DmaLcd::new(lcd);
 
-        //set chip to sleep
-        unsafe {
-            riscv::asm::wfi();
-        }
-    }
+    let mut task = WeatherTask::new(lcd, watchdog, backlight);
+    task.run()
 }
@@ -0,0 +1,42 @@
+use core::cell::RefCell;
+use riscv::interrupt::Mutex;
+
+use crate::dht::SensorError;
+
+// Read counters since boot, updated after every read
+pub static METRICS: Mutex<RefCell<Metrics>> = Mutex::new(RefCell::new(Metrics::new()));
+
+/// Outcome counts of the sensor reads
+#[derive(Clone, Copy, Debug)]
+pub struct Metrics {
+    pub reads_ok: u32,
+    pub reads_failed: u32,
+    pub checksum_errors: u32,
+    pub timeout_errors: u32,
+    pub insufficient_bits_errors: u32,
+    pub pin_errors: u32,
+}
+
+impl Metrics {
+    pub const fn new() -> Self {
+        Metrics {
+            reads_ok: 0,
+            reads_failed: 0,
+            checksum_errors: 0,
+            timeout_errors: 0,
+            insufficient_bits_errors: 0,
+            pin_errors: 0,
+        }
+    }
+
+    /// Counts a failed read under its error kind
+    pub fn record_error(&mut self, error: SensorError) {
+        self.reads_failed += 1;
+        match error {
+            SensorError::Timeout { .. } => self.timeout_errors += 1,
+            SensorError::ChecksumMismatch { .. } => self.checksum_errors += 1,
+            SensorError::InsufficientBits { .. } => self.insufficient_bits_errors += 1,
+            SensorError::PinError => self.pin_errors += 1,
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use core::cell::RefCell;
+use core::sync::atomic::{AtomicBool, Ordering};
+use longan_nano::hal::pac::RCU;
+use riscv::interrupt::Mutex;
+use riscv::register::mcycle;
+
+use crate::history::RingBuffer;
+use crate::types::SensorReading;
+
+/// Seconds between sensor reads while the readings are stable
+pub const LOWERED_INTERVAL_S: u32 = 30;
+
+// Latest readings that have to stay within the limits below for the lowered mode
+const STABLE_READINGS: usize = 10;
+const STABLE_MAX_TEMP_DELTA: f32 = 0.5;
+const STABLE_MAX_HUMIDITY_DELTA: f32 = 2.0;
+
+// Whether the sensor is read at the lowered rate, written after every reading
+static LOWERED: AtomicBool = AtomicBool::new(false);
+
+/// Why the MCU was last reset, from the RCU reset source flags.
+///
+/// The GD32VF103 has no separate brown-out flag: a supply drop below the
+/// power-down threshold sets the same flag as power-on, so brown-outs are
+/// reported as `PowerOn`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ResetCause {
+    PowerOn,
+    // Free watchdog timer (FWDGT), the GD32 name for the independent watchdog
+    FreeWatchdog,
+    // Window watchdog timer (WWDGT)
+    WindowWatchdog,
+    SoftwareReset,
+    // NRST pin pulled low
+    PinReset,
+    // Reset when entering standby or deep-sleep while not allowed
+    LowPower,
+}
+
+impl ResetCause {
+    /// Either watchdog, the firmware hung and the sensor is still warm
+    pub fn is_watchdog(&self) -> bool {
+        matches!(self, ResetCause::FreeWatchdog | ResetCause::WindowWatchdog)
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ResetCause::PowerOn => "power-on",
+            ResetCause::FreeWatchdog => "free watchdog",
+            ResetCause::WindowWatchdog => "window watchdog",
+            ResetCause::SoftwareReset => "software",
+            ResetCause::PinReset => "NRST pin",
+            ResetCause::LowPower => "low power",
+        }
+    }
+}
+
+// Reset cause of the current boot, set once in main
+pub static RESET_CAUSE: Mutex<RefCell<Option<ResetCause>>> = Mutex::new(RefCell::new(None));
+
+/// Reads the reset flags and clears them so the next boot starts fresh.
+/// Must be called before the RCU is configured.
+pub fn read_reset_cause(rcu: &RCU) -> ResetCause {
+    let flags = rcu.rstsck.read();
+
+    // Power-on also sets the pin flag, so check the more specific causes first
+    let cause = if flags.lprstf().bit_is_set() {
+        ResetCause::LowPower
+    } else if flags.wwdgtrstf().bit_is_set() {
+        ResetCause::WindowWatchdog
+    } else if flags.fwdgtrstf().bit_is_set() {
+        ResetCause::FreeWatchdog
+    } else if flags.swrstf().bit_is_set() {
+        ResetCause::SoftwareReset
+    } else if flags.porrstf().bit_is_set() {
+        ResetCause::PowerOn
+    } else {
+        ResetCause::PinReset
+    };
+
+    rcu.rstsck.modify(|_, w| w.rstfc().set_bit());
+
+    cause
+}
+
+/// How often the sensor is read. While the readings are stable the reads
+/// are spaced out to LOWERED_INTERVAL_S and the main loop sleeps longer.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PowerMode {
+    Normal,
+    Lowered,
+}
+
+impl PowerMode {
+    /// Lowered when the last STABLE_READINGS readings all stay within the
+    /// stability limits, Normal as soon as one doesn't
+    pub fn from_history<const CAP: usize>(history: &RingBuffer<SensorReading, CAP>) -> PowerMode {
+        if history.len() < STABLE_READINGS {
+            return PowerMode::Normal;
+        }
+
+        let mut readings = (history.len() - STABLE_READINGS..history.len())
+            .filter_map(|i| history.get(i));
+        let first = match readings.next() {
+            Some(first) => first,
+            None => return PowerMode::Normal,
+        };
+        let (mut min, mut max) = (first, first);
+        for reading in readings {
+            min.temperature = min.temperature.min(reading.temperature);
+            min.humidity = min.humidity.min(reading.humidity);
+            max.temperature = max.temperature.max(reading.temperature);
+            max.humidity = max.humidity.max(reading.humidity);
+        }
+
+        if max.temperature - min.temperature < STABLE_MAX_TEMP_DELTA
+            && max.humidity - min.humidity < STABLE_MAX_HUMIDITY_DELTA
+        {
+            PowerMode::Lowered
+        } else {
+            PowerMode::Normal
+        }
+    }
+}
+
+/// Mode selected after the latest reading
+pub fn power_mode() -> PowerMode {
+    if LOWERED.load(Ordering::Relaxed) {
+        PowerMode::Lowered
+    } else {
+        PowerMode::Normal
+    }
+}
+
+pub fn set_power_mode(mode: PowerMode) {
+    LOWERED.store(mode == PowerMode::Lowered, Ordering::Relaxed);
+}
+
+/// Share of time the MCU is awake rather than asleep in `wfi`, from the
+/// cycle counter around the main loop's sleeps. Interrupt handlers that
+/// run while the loop sleeps count as asleep.
+pub struct DutyCycle {
+    // Cycle count when the current measurement started
+    window_start: u64,
+    // Cycles slept since then
+    slept: u64,
+}
+
+impl DutyCycle {
+    pub const fn new() -> Self {
+        DutyCycle {
+            window_start: 0,
+            slept: 0,
+        }
+    }
+
+    /// Adds a sleep that started at cycle count `start` and ended now
+    pub fn record_sleep(&mut self, start: u64) {
+        self.slept += mcycle::read64().wrapping_sub(start);
+    }
+
+    /// Percent of the time awake since the previous call, 0-100
+    pub fn take_percent(&mut self) -> u8 {
+        let now = mcycle::read64();
+        let elapsed = now.wrapping_sub(self.window_start);
+        let asleep = if elapsed == 0 {
+            0
+        } else {
+            self.slept.min(elapsed) * 100 / elapsed
+        };
+        self.window_start = now;
+        self.slept = 0;
+        100 - asleep as u8
+    }
+}
+
+/// Duty cycle of the MCU, for the self-heating correction of the readings
+pub static DUTY_CYCLE: Mutex<RefCell<DutyCycle>> = Mutex::new(RefCell::new(DutyCycle::new()));
@@ -0,0 +1,138 @@
+use crate::crc::crc8;
+use crate::types::SensorReading;
+use crate::util::fmt::{round_i32, to_tenths};
+
+/// Length of a serialized SensorFrame
+pub const FRAME_LEN: usize = 11;
+
+/// First two bytes of every frame, `WS` on the wire. ASCII responses to
+/// commands never start with them.
+pub const FRAME_MAGIC: u16 = 0x5357;
+
+/// Humidity of a reading that has none, a thermistor reading
+pub const NO_HUMIDITY: u8 = 0xFF;
+
+/// One reading in the binary UART protocol. Serialized little-endian in
+/// field order, the CRC-8 covers the ten bytes before it.
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SensorFrame {
+    pub magic: u16,
+    pub sequence: u32,
+    pub temperature_tenth_deg: i16,
+    pub humidity_percent: u8,
+    // ReadingSource of the reading, 0 for the DHT and 1 for the thermistor
+    pub source: u8,
+    pub crc8: u8,
+}
+
+/// Ways a received frame can be invalid
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FrameError {
+    // The first two bytes were not FRAME_MAGIC, e.g. the parser is out of sync
+    BadMagic { got: u16 },
+    // The CRC byte did not match the CRC-8 of the rest of the frame
+    CrcMismatch { expected: u8, got: u8 },
+}
+
+impl SensorFrame {
+    /// Frame of a reading with its CRC filled in
+    pub fn new(sequence: u32, reading: &SensorReading) -> Self {
+        let humidity_percent = if reading.has_humidity() {
+            round_i32(reading.humidity).clamp(0, 100) as u8
+        } else {
+            NO_HUMIDITY
+        };
+        let mut frame = SensorFrame {
+            magic: FRAME_MAGIC,
+            sequence,
+            temperature_tenth_deg: to_tenths(reading.temperature) as i16,
+            humidity_percent,
+            source: reading.source as u8,
+            crc8: 0,
+        };
+        let mut buf = [0u8; FRAME_LEN];
+        serialize(&frame, &mut buf);
+        frame.crc8 = crc8(&buf[..FRAME_LEN - 1]);
+        frame
+    }
+}
+
+/// Writes the frame as it is, the CRC included
+pub fn serialize(frame: &SensorFrame, buf: &mut [u8; FRAME_LEN]) {
+    buf[0..2].copy_from_slice(&frame.magic.to_le_bytes());
+    buf[2..6].copy_from_slice(&frame.sequence.to_le_bytes());
+    buf[6..8].copy_from_slice(&frame.temperature_tenth_deg.to_le_bytes());
+    buf[8] = frame.humidity_percent;
+    buf[9] = frame.source;
+    buf[10] = frame.crc8;
+}
+
+/// Parses a frame, checking the magic word and the CRC
+pub fn deserialize(buf: &[u8; FRAME_LEN]) -> Result<SensorFrame, FrameError> {
+    let magic = u16::from_le_bytes([buf[0], buf[1]]);
+    if magic != FRAME_MAGIC {
+        return Err(FrameError::BadMagic { got: magic });
+    }
+
+    let expected = crc8(&buf[..FRAME_LEN - 1]);
+    if buf[10] != expected {
+        return Err(FrameError::CrcMismatch {
+            expected,
+            got: buf[10],
+        });
+    }
+
+    Ok(SensorFrame {
+        magic,
+        sequence: u32::from_le_bytes([buf[2], buf[3], buf[4], buf[5]]),
+        temperature_tenth_deg: i16::from_le_bytes([buf[6], buf[7]]),
+        humidity_percent: buf[8],
+        source: buf[9],
+        crc8: buf[10],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_round_trip() {
+        let frame = SensorFrame::new(1234, &SensorReading::new(-5.25, 47.6));
+        assert_eq!(frame.temperature_tenth_deg, -53);
+        assert_eq!(frame.humidity_percent, 48);
+
+        let mut buf = [0u8; FRAME_LEN];
+        serialize(&frame, &mut buf);
+        assert_eq!(&buf[..2], b"WS");
+        assert_eq!(deserialize(&buf), Ok(frame));
+    }
+
+    #[test]
+    fn thermistor_frame_has_no_humidity() {
+        let frame = SensorFrame::new(0, &SensorReading::from_ntc(21.0));
+        assert_eq!(frame.humidity_percent, NO_HUMIDITY);
+        assert_eq!(frame.source, 1);
+    }
+
+    #[test]
+    fn corrupted_frame_is_rejected() {
+        let mut buf = [0u8; FRAME_LEN];
+        serialize(&SensorFrame::new(7, &SensorReading::new(21.0, 40.0)), &mut buf);
+
+        let mut flipped = buf;
+        flipped[6] ^= 0x01;
+        assert!(matches!(
+            deserialize(&flipped),
+            Err(FrameError::CrcMismatch { .. })
+        ));
+
+        let mut shifted = [0u8; FRAME_LEN];
+        shifted[1..].copy_from_slice(&buf[..FRAME_LEN - 1]);
+        assert_eq!(
+            deserialize(&shifted),
+            Err(FrameError::BadMagic { got: 0x5700 })
+        );
+    }
+}
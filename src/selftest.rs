@@ -0,0 +1,127 @@
+use embedded_graphics::{
+    mono_font::{ascii::FONT_6X10, MonoTextStyle},
+    pixelcolor::Rgb565,
+    prelude::*,
+    primitives::{PrimitiveStyle, Rectangle},
+    text::{Alignment, Baseline, Text, TextStyleBuilder},
+};
+use heapless::String;
+use longan_nano::hal::delay::McycleDelay;
+use longan_nano::hal::pac;
+use longan_nano::hal::prelude::*;
+use riscv::interrupt::free;
+
+use crate::buzzer;
+use crate::display::screen_size;
+use crate::display_config::{ALERT_COLOR, BG_COLOR, OK_COLOR};
+use crate::serial;
+
+// How long the result stays on the screen
+const RESULT_SHOW_MS: u32 = 1500;
+
+// GPIOA_ISTAT bit of PA0, the sensor's data line
+const ISTAT_PA0: u32 = 1 << 0;
+
+/// Outcome of the boot self test
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SelfTestResult {
+    pub sensor_ok: bool,
+    pub lcd_ok: bool,
+}
+
+impl SelfTestResult {
+    pub fn passed(&self) -> bool {
+        self.sensor_ok && self.lcd_ok
+    }
+
+    // "SELF TEST OK", or FAIL with the failed parts, S for the sensor and L for the LCD
+    fn text(&self) -> String<20> {
+        let mut text = String::new();
+        if self.passed() {
+            text.push_str("SELF TEST OK").ok();
+            return text;
+        }
+        text.push_str("SELF TEST FAIL (").ok();
+        if !self.sensor_ok {
+            text.push('S').ok();
+        }
+        if !self.sensor_ok && !self.lcd_ok {
+            text.push('/').ok();
+        }
+        if !self.lcd_ok {
+            text.push('L').ok();
+        }
+        text.push(')').ok();
+        text
+    }
+}
+
+/// Checks the LCD and the sensor before the main loop starts, then shows
+/// the result for 1.5 s and writes it to the UART.
+///
+/// The sensor passes if its line idles high and one read gets through the
+/// checksum. Must run after the sensor's power-on warmup.
+pub fn self_test<D>(delay: &mut McycleDelay, lcd: &mut D) -> SelfTestResult
+where
+    D: DrawTarget<Color = Rgb565>,
+{
+    // A read during the test must not beep
+    buzzer::set_muted(true);
+
+    let lcd_ok = Rectangle::new(Point::new(0, 0), screen_size())
+        .into_styled(PrimitiveStyle::with_fill(ALERT_COLOR))
+        .draw(lcd)
+        .is_ok();
+
+    // The pull-up keeps the line high between reads, low means a short or a
+    // missing pull-up. PA0 is owned by the sensor driver, so the input
+    // register is read directly.
+    let gpioa = unsafe { &*pac::GPIOA::ptr() };
+    let line_idle_high = gpioa.istat.read().bits() & ISTAT_PA0 != 0;
+    let sensor_ok = line_idle_high && crate::read_data_once().is_ok();
+
+    let result = SelfTestResult { sensor_ok, lcd_ok };
+    draw_result(lcd, &result);
+
+    free(|cs| {
+        if let Some(ref mut uart) = *serial::UART.borrow(*cs).borrow_mut() {
+            let _ = serial::write_str(uart, &result.text());
+            let _ = serial::write_str(uart, "\r\n");
+        }
+    });
+
+    delay.delay_ms(RESULT_SHOW_MS);
+    buzzer::set_muted(false);
+    result
+}
+
+// Result centered on a black screen, green when passed and red when not
+fn draw_result<D>(lcd: &mut D, result: &SelfTestResult)
+where
+    D: DrawTarget<Color = Rgb565>,
+{
+    let size = screen_size();
+    Rectangle::new(Point::new(0, 0), size)
+        .into_styled(PrimitiveStyle::with_fill(BG_COLOR))
+        .draw(lcd)
+        .ok();
+
+    let color = if result.passed() {
+        OK_COLOR
+    } else {
+        ALERT_COLOR
+    };
+    let centered = TextStyleBuilder::new()
+        .alignment(Alignment::Center)
+        .baseline(Baseline::Middle)
+        .build();
+    let center = Point::new(size.width as i32 / 2, size.height as i32 / 2);
+    Text::with_text_style(
+        &result.text(),
+        center,
+        MonoTextStyle::new(&FONT_6X10, color),
+        centered,
+    )
+    .draw(lcd)
+    .ok();
+}
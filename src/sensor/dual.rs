@@ -0,0 +1,56 @@
+use crate::types::SensorReading;
+
+/// Largest differences between the two sensors that still count as agreeing
+pub const MAX_TEMP_DIFF: f32 = 2.0;
+pub const MAX_HUMIDITY_DIFF: f32 = 5.0;
+
+/// Latest reading of each of two redundant sensors, combined into one
+pub struct DualReadings {
+    // None after a failed read
+    latest: [Option<SensorReading>; 2],
+}
+
+impl DualReadings {
+    pub const fn new() -> Self {
+        DualReadings {
+            latest: [None, None],
+        }
+    }
+
+    /// Stores the result of sensor `index`, 0 or 1, None for a failed read
+    pub fn record(&mut self, index: usize, reading: Option<SensorReading>) {
+        self.latest[index % 2] = reading;
+    }
+
+    /// Average of the two readings, or the one that is valid
+    pub fn combined(&self) -> Option<SensorReading> {
+        match self.latest {
+            [Some(a), Some(b)] => Some(SensorReading::new(
+                (a.temperature + b.temperature) / 2.0,
+                (a.humidity + b.humidity) / 2.0,
+            )),
+            [Some(a), None] => Some(a),
+            [None, b] => b,
+        }
+    }
+
+    /// True when both readings are valid and differ by more than
+    /// MAX_TEMP_DIFF or MAX_HUMIDITY_DIFF
+    pub fn disagree(&self) -> bool {
+        match self.latest {
+            [Some(a), Some(b)] => {
+                let dt = a.temperature - b.temperature;
+                let dh = a.humidity - b.humidity;
+                !(-MAX_TEMP_DIFF..=MAX_TEMP_DIFF).contains(&dt)
+                    || !(-MAX_HUMIDITY_DIFF..=MAX_HUMIDITY_DIFF).contains(&dh)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Default for DualReadings {
+    fn default() -> Self {
+        DualReadings::new()
+    }
+}
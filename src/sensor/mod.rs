@@ -0,0 +1,124 @@
+use core::cell::RefCell;
+use embedded_hal::blocking::delay::DelayUs;
+use riscv::interrupt::Mutex;
+
+use crate::dht::SensorError;
+use crate::types::SensorReading;
+
+pub mod dual;
+#[cfg(feature = "sensor_mux")]
+pub mod mux;
+pub mod verify;
+
+/// Source of temperature and humidity readings
+pub trait WeatherSensor {
+    /// Takes one reading. `delay` is for sensors that time their own protocol.
+    fn read(&mut self, delay: &mut impl DelayUs<u32>) -> Result<SensorReading, SensorError>;
+}
+
+/// Minimum time after power-on before the sensor may be queried. The
+/// datasheet requires 1 s, 2 s leaves some margin.
+pub const SENSOR_WARMUP_MS: u32 = 2000;
+
+/// Warm-up delay of the sensor after power-on
+pub static SENSOR_WARMUP: PowerOnDelay = PowerOnDelay::new(SENSOR_WARMUP_MS);
+
+/// Keeps the sensor from being read until it has been powered long enough
+pub struct PowerOnDelay {
+    warmup_ms: u32,
+}
+
+impl PowerOnDelay {
+    pub const fn new(warmup_ms: u32) -> Self {
+        PowerOnDelay { warmup_ms }
+    }
+
+    /// Takes the uptime in seconds, in milliseconds it would wrap after 49 days
+    pub fn is_ready(&self, uptime_s: u32) -> bool {
+        uptime_s.saturating_mul(1000) >= self.warmup_ms
+    }
+}
+
+/// Typical time for the sensor to reach thermal equilibrium after power-on
+pub const TYPICAL_WARMUP_S: u32 = 300;
+
+// Rate of change below which the temperature is considered stable
+const SETTLED_RATE_C_PER_MIN: f32 = 0.2;
+
+// Consecutive stable minutes needed before the sensor counts as settled
+const SETTLED_MINUTES: u8 = 2;
+
+// Thermal settling of the sensor, updated after every reading
+pub static WARMUP_MONITOR: Mutex<RefCell<WarmupMonitor>> =
+    Mutex::new(RefCell::new(WarmupMonitor::new()));
+
+/// Follows the temperature after power-on until it stops drifting
+pub struct WarmupMonitor {
+    // Uptime and temperature at the start of the current minute
+    minute_start: Option<(u32, f32)>,
+    stable_minutes: u8,
+    settled: bool,
+}
+
+impl WarmupMonitor {
+    pub const fn new() -> Self {
+        WarmupMonitor {
+            minute_start: None,
+            stable_minutes: 0,
+            settled: false,
+        }
+    }
+
+    /// Feeds a reading. The rate of change is checked once a minute.
+    pub fn update(&mut self, uptime_s: u32, temperature: f32) {
+        if self.settled {
+            return;
+        }
+
+        let (start_s, start_temp) = match self.minute_start {
+            Some(start) => start,
+            None => {
+                self.minute_start = Some((uptime_s, temperature));
+                return;
+            }
+        };
+        if uptime_s - start_s < 60 {
+            return;
+        }
+
+        let minutes = (uptime_s - start_s) as f32 / 60.0;
+        let rate = (temperature - start_temp) / minutes;
+        if rate > -SETTLED_RATE_C_PER_MIN && rate < SETTLED_RATE_C_PER_MIN {
+            self.stable_minutes += 1;
+        } else {
+            self.stable_minutes = 0;
+        }
+        self.settled = self.stable_minutes >= SETTLED_MINUTES;
+        self.minute_start = Some((uptime_s, temperature));
+    }
+
+    /// True once the temperature has changed less than 0.2°C/minute for
+    /// two consecutive minutes. Stays true after that.
+    pub fn is_settled(&self) -> bool {
+        self.settled
+    }
+
+    /// Estimated warm-up progress (%) based on the typical warm-up time,
+    /// held below 100 until the sensor has actually settled
+    pub fn progress_percent(&self, uptime_s: u32) -> u8 {
+        if self.settled {
+            return 100;
+        }
+        (uptime_s * 100 / TYPICAL_WARMUP_S).min(99) as u8
+    }
+}
+
+impl Default for WarmupMonitor {
+    fn default() -> Self {
+        WarmupMonitor::new()
+    }
+}
+
+// Multiplexer in front of the sensors, None when the sensor is wired directly
+#[cfg(feature = "sensor_mux")]
+pub static SENSOR_MUX: Mutex<RefCell<Option<mux::Mux4051>>> = Mutex::new(RefCell::new(None));
@@ -0,0 +1,53 @@
+use embedded_hal::digital::v2::OutputPin;
+use longan_nano::hal::gpio::gpioa::{PA1, PA2, PA4};
+use longan_nano::hal::gpio::{Output, PushPull};
+
+/// Time for the multiplexer to settle after changing the address, before
+/// the DHT start pulse
+pub const MUX_SETTLE_US: u32 = 1;
+
+/// CD4051 8-channel analog multiplexer connecting several DHT sensors to
+/// the single signal pin. Address bits A, B and C are on PA1, PA2 and PA4.
+pub struct Mux4051 {
+    a: PA1<Output<PushPull>>,
+    b: PA2<Output<PushPull>>,
+    c: PA4<Output<PushPull>>,
+    channel: u8,
+}
+
+impl Mux4051 {
+    pub fn new(
+        a: PA1<Output<PushPull>>,
+        b: PA2<Output<PushPull>>,
+        c: PA4<Output<PushPull>>,
+    ) -> Self {
+        let mut mux = Mux4051 { a, b, c, channel: 0 };
+        mux.select(0).unwrap();
+        mux
+    }
+
+    /// Routes the given channel (0-7) to the signal pin
+    pub fn select(&mut self, channel: u8) -> Result<(), ()> {
+        if channel > 7 {
+            return Err(());
+        }
+
+        set_level(&mut self.a, channel & 0b001 != 0);
+        set_level(&mut self.b, channel & 0b010 != 0);
+        set_level(&mut self.c, channel & 0b100 != 0);
+        self.channel = channel;
+        Ok(())
+    }
+
+    pub fn channel(&self) -> u8 {
+        self.channel
+    }
+}
+
+fn set_level(pin: &mut impl OutputPin, high: bool) {
+    if high {
+        pin.set_high().ok();
+    } else {
+        pin.set_low().ok();
+    }
+}
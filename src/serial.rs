@@ -0,0 +1,265 @@
+use core::cell::RefCell;
+use core::fmt::{self, Display, Write};
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use embedded_hal::serial;
+use heapless::spsc::Queue;
+use heapless::{FnvIndexMap, String};
+use longan_nano::hal::pac::{self, USART0};
+use longan_nano::hal::serial::{Rx, Tx};
+use nb::block;
+use riscv::interrupt::{free, Mutex};
+
+use crate::history::{RingBuffer, HISTORY_LEN};
+use crate::input::button::SAMPLE_RATE_HZ;
+use crate::protocol::{serialize, SensorFrame, FRAME_LEN};
+use crate::types::SensorReading;
+use crate::util::fmt::{push_tenths, to_tenths};
+
+/// Baud rate of USART0 on the debug connector
+pub const UART_BAUD: u32 = 115_200;
+
+// Transmit half of USART0 on the debug connector, TX PA9. The 0.5 HAL
+// implements the serial traits on the split halves only, not on Serial.
+pub type Uart = Tx<USART0>;
+
+// Serial port for the readings output, None until configured in main
+pub static UART: Mutex<RefCell<Option<Uart>>> = Mutex::new(RefCell::new(None));
+
+// Receive half of USART0, RX PA10, read by the USART0 interrupt
+pub static UART_RX: Mutex<RefCell<Option<Rx<USART0>>>> = Mutex::new(RefCell::new(None));
+
+// Bytes received on USART0, filled by the receive interrupt and drained by the main loop
+pub static RX_QUEUE: Mutex<RefCell<Queue<u8, 16>>> = Mutex::new(RefCell::new(Queue::new()));
+
+// Framing and parity errors seen on the command UART since boot. Kept in
+// RAM only, saving it to flash from the receive interrupt would wear the
+// flash on a noisy line, so there is no warning at boot about errors of a
+// previous session. The metrics page shows the count once it is non-zero.
+pub static UART_FRAME_ERRORS: Mutex<RefCell<u32>> = Mutex::new(RefCell::new(0));
+
+/// Rate of the binary frames sent after the `B` command
+pub const STREAM_RATE_HZ: u32 = 10;
+
+// TIMER5 ticks between two binary frames
+const STREAM_TICKS: u32 = SAMPLE_RATE_HZ / STREAM_RATE_HZ;
+
+// Toggled by the `B` command, while set the TIMER5 interrupt sends binary frames
+pub static STREAMING: AtomicBool = AtomicBool::new(false);
+
+// TIMER5 ticks since the latest binary frame
+static STREAM_TICK: AtomicU32 = AtomicU32::new(0);
+
+// Minimum seconds between two reports with the same context
+const REPORT_INTERVAL_S: u32 = 1;
+
+/// Formats errors as `ERROR [context]: error` lines to a serial writer
+/// without heap allocation. Reports are rate limited to one per second
+/// per context.
+pub struct ErrorReporter<W: Write> {
+    writer: W,
+    // Context hash -> time of the last report in seconds
+    last_report: FnvIndexMap<u32, u32, 16>,
+}
+
+impl<W: Write> ErrorReporter<W> {
+    pub const fn new(writer: W) -> Self {
+        ErrorReporter {
+            writer,
+            last_report: FnvIndexMap::new(),
+        }
+    }
+
+    pub fn report(&mut self, context: &'static str, error: &impl Display) {
+        self.report_chain(&[context], error);
+    }
+
+    /// Reports an error that passed through several layers, written as
+    /// `ERROR [ctx1 > ctx2 > ctx3]: error`
+    pub fn report_chain(&mut self, contexts: &[&'static str], error: &impl Display) {
+        let now_s = crate::uptime_s();
+        let key = contexts.iter().fold(0x811c_9dc5, |h, c| fnv1a(h, c));
+
+        if let Some(last) = self.last_report.get(&key) {
+            if now_s.wrapping_sub(*last) < REPORT_INTERVAL_S {
+                return;
+            }
+        }
+        // If all slots are taken the error is still reported, just not rate limited
+        let _ = self.last_report.insert(key, now_s);
+
+        let _ = self.writer.write_str("ERROR [");
+        for (i, context) in contexts.iter().enumerate() {
+            if i > 0 {
+                let _ = self.writer.write_str(" > ");
+            }
+            let _ = self.writer.write_str(context);
+        }
+        let _ = write!(self.writer, "]: {}\r\n", error);
+    }
+}
+
+/// `core::fmt::Write` on UART, borrowed for each string. Writes nothing
+/// before the UART is configured. Must not be used while UART is borrowed.
+pub struct SharedUart;
+
+impl Write for SharedUart {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        free(|cs| match *UART.borrow(*cs).borrow_mut() {
+            Some(ref mut uart) => write_str(uart, s).map_err(|_| fmt::Error),
+            None => Ok(()),
+        })
+    }
+}
+
+// Reports the errors of the sensor, the flash and the UART itself
+static ERROR_REPORTER: Mutex<RefCell<ErrorReporter<SharedUart>>> =
+    Mutex::new(RefCell::new(ErrorReporter::new(SharedUart)));
+
+/// Reports an error on the UART as `ERROR [context]: error`, at most once
+/// a second for the same context
+pub fn report_error(context: &'static str, error: &impl Display) {
+    free(|cs| ERROR_REPORTER.borrow(*cs).borrow_mut().report(context, error));
+}
+
+/// Reports an error that passed through several layers on the UART
+pub fn report_error_chain(contexts: &[&'static str], error: &impl Display) {
+    free(|cs| {
+        ERROR_REPORTER
+            .borrow(*cs)
+            .borrow_mut()
+            .report_chain(contexts, error)
+    });
+}
+
+// FNV-1a hash of a string, continued from the given hash
+fn fnv1a(hash: u32, s: &str) -> u32 {
+    s.bytes()
+        .fold(hash, |h, b| (h ^ b as u32).wrapping_mul(0x0100_0193))
+}
+
+/// Reads and clears the framing (FERR) and parity (PERR) error flags of
+/// USART0, adds them to UART_FRAME_ERRORS and returns how many were set.
+/// Framing errors usually mean a baud rate mismatch with the other end.
+pub fn check_uart_frame_errors(uart: &pac::usart0::RegisterBlock) -> u32 {
+    // The GD32VF103 has a single STAT register, the flags are cleared by
+    // reading STAT followed by DATA
+    let stat = uart.stat.read();
+    let errors = stat.ferr().bit_is_set() as u32 + stat.perr().bit_is_set() as u32;
+    if errors > 0 {
+        let _ = uart.data.read();
+        free(|cs| {
+            *UART_FRAME_ERRORS.borrow(*cs).borrow_mut() += errors;
+        });
+    }
+    errors
+}
+
+/// Writes a reading as a `T=23.4,H=45.0,Dp=10.6\r\n` line. Numbers are
+/// formatted by hand to keep `core::fmt` float code out of the binary.
+pub fn write_reading<S: serial::Write<u8>>(
+    uart: &mut S,
+    reading: &SensorReading,
+    dew_point: f32,
+) -> Result<(), S::Error> {
+    let mut line: String<32> = String::new();
+    // 32 bytes fit the longest possible line
+    let _ = write!(line, "T={},H=", reading.celsius());
+    let _ = push_tenths(&mut line, to_tenths(reading.humidity));
+    let _ = line.push_str(",Dp=");
+    let _ = push_tenths(&mut line, to_tenths(dew_point));
+    let _ = line.push_str("\r\n");
+
+    write_str(uart, &line)
+}
+
+/// Writes the history oldest first as `temperature,humidity` CSV rows
+pub fn write_history_csv<S: serial::Write<u8>>(
+    uart: &mut S,
+    history: &RingBuffer<SensorReading, HISTORY_LEN>,
+) -> Result<(), S::Error> {
+    write_str(uart, "temperature,humidity")?;
+    for reading in history.iter() {
+        let mut row: String<16> = String::new();
+        // 16 bytes fit the longest possible row
+        let _ = write!(row, "\r\n{},", reading.celsius());
+        let _ = push_tenths(&mut row, to_tenths(reading.humidity));
+        write_str(uart, &row)?;
+    }
+    Ok(())
+}
+
+/// Writes a frame of the binary protocol, about 1 ms at 115200 baud
+pub fn write_frame<S: serial::Write<u8>>(
+    uart: &mut S,
+    frame: &SensorFrame,
+) -> Result<(), S::Error> {
+    let mut buf = [0u8; FRAME_LEN];
+    serialize(frame, &mut buf);
+    for &byte in buf.iter() {
+        block!(uart.write(byte))?;
+    }
+    Ok(())
+}
+
+/// Sends the latest reading as a SensorFrame every STREAM_TICKS ticks while
+/// streaming, called from the TIMER5 interrupt. The sequence number is
+/// TIMER_COUNTER, so the frames of one second share it.
+pub fn stream_tick() {
+    if !STREAMING.load(Ordering::Relaxed) {
+        return;
+    }
+    if STREAM_TICK.fetch_add(1, Ordering::Relaxed) + 1 < STREAM_TICKS {
+        return;
+    }
+    STREAM_TICK.store(0, Ordering::Relaxed);
+
+    free(|cs| {
+        // Nothing to send before the first reading
+        let reading = match *crate::DATA.borrow(*cs).borrow() {
+            Some(reading) => reading,
+            None => return,
+        };
+        let frame = SensorFrame::new(crate::TIMER_COUNTER.load(Ordering::Relaxed), &reading);
+        if let Some(ref mut uart) = *UART.borrow(*cs).borrow_mut() {
+            let _ = write_frame(uart, &frame);
+        }
+    });
+}
+
+/// `core::fmt::Write` on a serial port, for printing formatted text such
+/// as the calibration history
+pub struct UartWriter<'a, S>(pub &'a mut S);
+
+impl<'a, S: serial::Write<u8>> Write for UartWriter<'a, S> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        write_str(self.0, s).map_err(|_| fmt::Error)
+    }
+}
+
+/// Writes a string byte by byte, blocking until each is sent
+pub fn write_str<S: serial::Write<u8>>(uart: &mut S, s: &str) -> Result<(), S::Error> {
+    for &byte in s.as_bytes() {
+        block!(uart.write(byte))?;
+    }
+    Ok(())
+}
+
+//Interrupt handler for USART0, queues each received byte
+#[allow(non_snake_case)]
+#[no_mangle]
+fn USART0() {
+    // A byte with a framing or parity error is counted, reported and dropped
+    if check_uart_frame_errors(unsafe { &*pac::USART0::ptr() }) > 0 {
+        report_error("uart", &"framing or parity error");
+        return;
+    }
+
+    free(|cs| {
+        if let Some(ref mut rx) = *UART_RX.borrow(*cs).borrow_mut() {
+            // Reading the byte clears the interrupt, a full queue drops it
+            if let Ok(byte) = serial::Read::read(rx) {
+                let _ = RX_QUEUE.borrow(*cs).borrow_mut().enqueue(byte);
+            }
+        }
+    });
+}
@@ -0,0 +1,243 @@
+use crate::types::SensorReading;
+
+// Number of latest samples used for the correlation
+const CORRELATION_WINDOW: usize = 20;
+
+// Correlation beyond this magnitude is considered meaningful
+const CORRELATION_THRESHOLD: f32 = 0.7;
+
+/// What the temperature/humidity correlation says about the room
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CorrelationKind {
+    // Both rise together, closed space heating up
+    Sealed,
+    // Humidity rises while temperature falls, outside air leaking in
+    Infiltration,
+    Mixed,
+}
+
+impl CorrelationKind {
+    pub fn from_coefficient(r: f32) -> CorrelationKind {
+        if r > CORRELATION_THRESHOLD {
+            CorrelationKind::Sealed
+        } else if r < -CORRELATION_THRESHOLD {
+            CorrelationKind::Infiltration
+        } else {
+            CorrelationKind::Mixed
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            CorrelationKind::Sealed => "Sealed",
+            CorrelationKind::Infiltration => "Infiltration",
+            CorrelationKind::Mixed => "Mixed",
+        }
+    }
+}
+
+/// Pearson correlation coefficient between the latest (at most 20)
+/// temperature and humidity samples. Returns 0.0 when either series is
+/// constant or there are fewer than two samples.
+pub fn pearson_correlation(temp_history: &[f32], humidity_history: &[f32]) -> f32 {
+    let n = temp_history
+        .len()
+        .min(humidity_history.len())
+        .min(CORRELATION_WINDOW);
+    if n < 2 {
+        return 0.0;
+    }
+
+    let temps = &temp_history[temp_history.len() - n..];
+    let hums = &humidity_history[humidity_history.len() - n..];
+
+    let mean_t = temps.iter().sum::<f32>() / n as f32;
+    let mean_h = hums.iter().sum::<f32>() / n as f32;
+
+    let mut cov = 0.0;
+    let mut var_t = 0.0;
+    let mut var_h = 0.0;
+    for (t, h) in temps.iter().zip(hums.iter()) {
+        let dt = t - mean_t;
+        let dh = h - mean_h;
+        cov += dt * dh;
+        var_t += dt * dt;
+        var_h += dh * dh;
+    }
+
+    if var_t == 0.0 || var_h == 0.0 {
+        return 0.0;
+    }
+
+    cov / sqrt(var_t * var_h)
+}
+
+// Square root with Newton's method, core has no f32::sqrt without std
+fn sqrt(x: f32) -> f32 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    let mut guess = if x > 1.0 { x / 2.0 } else { 1.0 };
+    for _ in 0..32 {
+        guess = 0.5 * (guess + x / guess);
+    }
+    guess
+}
+
+/// Lowest and highest temperature and humidity seen, tracked separately
+/// so the extremes can come from different readings
+#[derive(Clone, Copy)]
+pub struct MinMaxTracker {
+    min: Option<SensorReading>,
+    max: Option<SensorReading>,
+}
+
+impl MinMaxTracker {
+    pub const fn new() -> Self {
+        MinMaxTracker {
+            min: None,
+            max: None,
+        }
+    }
+
+    /// Widens the extremes to include the reading
+    pub fn update(&mut self, reading: &SensorReading) {
+        if let (Some(min), Some(max)) = (self.min.as_mut(), self.max.as_mut()) {
+            min.temperature = min.temperature.min(reading.temperature);
+            min.humidity = min.humidity.min(reading.humidity);
+            max.temperature = max.temperature.max(reading.temperature);
+            max.humidity = max.humidity.max(reading.humidity);
+            return;
+        }
+        self.reset(Some(*reading));
+    }
+
+    /// Starts over from the given reading, or from nothing
+    pub fn reset(&mut self, reading: Option<SensorReading>) {
+        self.min = reading;
+        self.max = reading;
+    }
+
+    /// Lowest and highest values, None before the first reading
+    pub fn extremes(&self) -> Option<(SensorReading, SensorReading)> {
+        self.min.zip(self.max)
+    }
+}
+
+impl Default for MinMaxTracker {
+    fn default() -> Self {
+        MinMaxTracker::new()
+    }
+}
+
+/// TIMER1 ticks the peak is held before it starts to decay, 5 minutes
+pub const PEAK_HOLD_TICKS: u32 = 300;
+
+// Decay of the peak per tick once the hold time is over, °C
+const PEAK_DECAY_STEP: f32 = 0.1;
+
+/// Highest temperature, held for PEAK_HOLD_TICKS after it was reached and
+/// then lowered by 0.1°C per tick until a reading catches up with it
+pub struct PeakHold {
+    value: f32,
+    decay_ticks_remaining: u32,
+}
+
+impl PeakHold {
+    pub const fn new() -> Self {
+        PeakHold {
+            value: f32::NEG_INFINITY,
+            decay_ticks_remaining: 0,
+        }
+    }
+
+    /// Raises the peak to the temperature if it is higher, restarting the hold
+    pub fn update(&mut self, temperature: f32) {
+        if temperature > self.value {
+            self.value = temperature;
+            self.decay_ticks_remaining = PEAK_HOLD_TICKS;
+        }
+    }
+
+    /// Counts down the hold time, then decays the peak
+    pub fn tick(&mut self) {
+        if self.decay_ticks_remaining > 0 {
+            self.decay_ticks_remaining -= 1;
+        } else {
+            self.value -= PEAK_DECAY_STEP;
+        }
+    }
+
+    /// Peak temperature, None before the first reading
+    pub fn value(&self) -> Option<f32> {
+        if self.value.is_finite() {
+            Some(self.value)
+        } else {
+            None
+        }
+    }
+}
+
+impl Default for PeakHold {
+    fn default() -> Self {
+        PeakHold::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn correlated_series() {
+        let temps = [20.0, 20.5, 21.0, 21.5, 22.0, 22.5];
+        let hums = [40.0, 41.0, 42.0, 43.0, 44.0, 45.0];
+        let r = pearson_correlation(&temps, &hums);
+        assert!((r - 1.0).abs() < 1e-4, "{}", r);
+        assert_eq!(
+            CorrelationKind::from_coefficient(r),
+            CorrelationKind::Sealed
+        );
+    }
+
+    #[test]
+    fn anti_correlated_series() {
+        let temps = [22.0, 21.0, 20.0, 19.0, 18.0];
+        let hums = [40.0, 44.0, 47.0, 52.0, 55.0];
+        let r = pearson_correlation(&temps, &hums);
+        assert!(r < -0.99, "{}", r);
+        assert_eq!(
+            CorrelationKind::from_coefficient(r),
+            CorrelationKind::Infiltration
+        );
+    }
+
+    #[test]
+    fn known_coefficient() {
+        // Covariance 8 over sqrt(10 * 10)
+        let temps = [1.0, 2.0, 3.0, 4.0, 5.0];
+        let hums = [2.0, 1.0, 4.0, 3.0, 5.0];
+        assert!((pearson_correlation(&temps, &hums) - 0.8).abs() < 1e-4);
+    }
+
+    #[test]
+    fn only_latest_window_is_used() {
+        let mut temps = [0.0f32; 30];
+        let mut hums = [0.0f32; 30];
+        for (i, (t, h)) in temps.iter_mut().zip(hums.iter_mut()).enumerate() {
+            *t = i as f32;
+            // Anti-correlated for the first 10, correlated in the window
+            *h = if i < 10 { 100.0 - i as f32 } else { i as f32 };
+        }
+        assert!((pearson_correlation(&temps, &hums) - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn constant_series_has_no_correlation() {
+        assert_eq!(
+            pearson_correlation(&[21.0; 5], &[40.0, 41.0, 42.0, 43.0, 44.0]),
+            0.0
+        );
+        assert_eq!(pearson_correlation(&[21.0], &[40.0]), 0.0);
+    }
+}
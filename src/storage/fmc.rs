@@ -0,0 +1,115 @@
+use core::fmt;
+use longan_nano::hal::pac;
+
+/// Size of an erase page of the internal flash
+pub const PAGE_SIZE: u32 = 1024;
+
+extern "C" {
+    // Start of the last page of the linked flash, defined by boot_config.x
+    static _boot_config_page: u8;
+}
+
+/// Start of the last page of the internal flash. The linker script places
+/// it from the flash size of the memory map and fails the link if the
+/// program reaches into it.
+pub fn last_page_addr() -> u32 {
+    unsafe { &_boot_config_page as *const u8 as u32 }
+}
+
+// Unlock sequence written to FMC_KEY0
+const KEY_1: u32 = 0x4567_0123;
+const KEY_2: u32 = 0xCDEF_89AB;
+
+// FMC_CTL0 bits
+const CTL0_PG: u32 = 1 << 0;
+const CTL0_PER: u32 = 1 << 1;
+const CTL0_START: u32 = 1 << 6;
+const CTL0_LK: u32 = 1 << 7;
+
+// FMC_STAT0 bits
+const STAT0_BUSY: u32 = 1 << 0;
+const STAT0_PGERR: u32 = 1 << 2;
+const STAT0_WPERR: u32 = 1 << 4;
+const STAT0_ENDF: u32 = 1 << 5;
+
+/// Program or erase failure reported by the FMC
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FlashError {
+    // Programming a half-word that was not erased
+    Program,
+    // Page is write protected
+    WriteProtected,
+}
+
+// For the error reports on the UART
+impl fmt::Display for FlashError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            FlashError::Program => "programming failed",
+            FlashError::WriteProtected => "page is write protected",
+        })
+    }
+}
+
+/// Reads bytes of the memory mapped flash starting at `addr`
+pub fn read(addr: u32, buf: &mut [u8]) {
+    for (i, byte) in buf.iter_mut().enumerate() {
+        *byte = unsafe { core::ptr::read_volatile((addr as usize + i) as *const u8) };
+    }
+}
+
+/// Erases the page containing `addr` to 0xFF
+pub fn erase_page(addr: u32) -> Result<(), FlashError> {
+    let fmc = unsafe { &*pac::FMC::ptr() };
+    unlock(fmc);
+    fmc.ctl0.modify(|r, w| unsafe { w.bits(r.bits() | CTL0_PER) });
+    fmc.addr0.write(|w| unsafe { w.bits(addr) });
+    fmc.ctl0.modify(|r, w| unsafe { w.bits(r.bits() | CTL0_START) });
+    let result = wait_done(fmc);
+    fmc.ctl0.modify(|r, w| unsafe { w.bits(r.bits() & !CTL0_PER | CTL0_LK) });
+    result
+}
+
+/// Programs `data` starting at the half-word aligned `addr`. The area must
+/// have been erased. An odd trailing byte is padded with 0xFF.
+pub fn program(addr: u32, data: &[u8]) -> Result<(), FlashError> {
+    let fmc = unsafe { &*pac::FMC::ptr() };
+    unlock(fmc);
+    fmc.ctl0.modify(|r, w| unsafe { w.bits(r.bits() | CTL0_PG) });
+    let mut result = Ok(());
+    for (i, pair) in data.chunks(2).enumerate() {
+        let half_word = u16::from_le_bytes([pair[0], *pair.get(1).unwrap_or(&0xff)]);
+        let target = (addr as usize + 2 * i) as *mut u16;
+        unsafe { core::ptr::write_volatile(target, half_word) };
+        result = wait_done(fmc);
+        if result.is_err() {
+            break;
+        }
+    }
+    fmc.ctl0.modify(|r, w| unsafe { w.bits(r.bits() & !CTL0_PG | CTL0_LK) });
+    result
+}
+
+// Unlocks FMC_CTL0 if it is locked
+fn unlock(fmc: &pac::fmc::RegisterBlock) {
+    if fmc.ctl0.read().bits() & CTL0_LK != 0 {
+        fmc.key0.write(|w| unsafe { w.bits(KEY_1) });
+        fmc.key0.write(|w| unsafe { w.bits(KEY_2) });
+    }
+}
+
+// Waits for the running operation and clears its status flags
+fn wait_done(fmc: &pac::fmc::RegisterBlock) -> Result<(), FlashError> {
+    while fmc.stat0.read().bits() & STAT0_BUSY != 0 {}
+
+    let stat = fmc.stat0.read().bits();
+    // Flags are cleared by writing 1
+    fmc.stat0.write(|w| unsafe { w.bits(STAT0_ENDF | STAT0_PGERR | STAT0_WPERR) });
+    if stat & STAT0_PGERR != 0 {
+        Err(FlashError::Program)
+    } else if stat & STAT0_WPERR != 0 {
+        Err(FlashError::WriteProtected)
+    } else {
+        Ok(())
+    }
+}
@@ -0,0 +1,34 @@
+pub mod fmc;
+
+#[cfg(feature = "spi_flash")]
+pub mod spiflash;
+
+#[cfg(feature = "spi_flash")]
+pub use self::flash_log::*;
+
+#[cfg(feature = "spi_flash")]
+mod flash_log {
+    use core::cell::RefCell;
+    use longan_nano::hal::gpio::gpiob::{PB12, PB13, PB14, PB15};
+    use longan_nano::hal::gpio::{Alternate, Floating, Input, Output, PushPull};
+    use longan_nano::hal::pac::SPI1;
+    use longan_nano::hal::spi::Spi;
+    use riscv::interrupt::Mutex;
+
+    use super::spiflash::CsvLogger;
+
+    // External W25Q32 on SPI1: SCK PB13, MISO PB14, MOSI PB15, CS PB12
+    pub type FlashSpi = Spi<
+        SPI1,
+        (
+            PB13<Alternate<PushPull>>,
+            PB14<Input<Floating>>,
+            PB15<Alternate<PushPull>>,
+        ),
+    >;
+    pub type FlashCs = PB12<Output<PushPull>>;
+
+    // Standalone CSV log of the readings
+    pub static CSV_LOGGER: Mutex<RefCell<Option<CsvLogger<FlashSpi, FlashCs>>>> =
+        Mutex::new(RefCell::new(None));
+}
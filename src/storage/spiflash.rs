@@ -0,0 +1,248 @@
+use core::fmt::Write as _;
+use embedded_hal::blocking::spi::{Transfer, Write};
+use embedded_hal::digital::v2::OutputPin;
+use heapless::String;
+
+pub const PAGE_SIZE: usize = 256;
+pub const SECTOR_SIZE: u32 = 4096;
+const PAGES_PER_SECTOR: u32 = SECTOR_SIZE / PAGE_SIZE as u32;
+
+// W25Q32: 32 Mbit = 16384 pages
+pub const PAGE_COUNT: u32 = 16384;
+
+/// read_id of a W25Q32: Winbond, SPI NOR, 32 Mbit
+pub const W25Q32_JEDEC_ID: u32 = 0xEF4016;
+
+// W25Q32 instructions
+const CMD_WRITE_ENABLE: u8 = 0x06;
+const CMD_READ_STATUS1: u8 = 0x05;
+const CMD_READ_DATA: u8 = 0x03;
+const CMD_PAGE_PROGRAM: u8 = 0x02;
+const CMD_SECTOR_ERASE: u8 = 0x20;
+const CMD_JEDEC_ID: u8 = 0x9F;
+
+// Status register 1 bit set while a program or erase is in progress
+const STATUS_BUSY: u8 = 0x01;
+
+/// Driver for a W25Q32 SPI NOR flash
+pub struct W25q32Driver<SPI, CS> {
+    spi: SPI,
+    cs: CS,
+}
+
+impl<SPI, CS, E> W25q32Driver<SPI, CS>
+where
+    SPI: Transfer<u8, Error = E> + Write<u8, Error = E>,
+    CS: OutputPin,
+{
+    pub fn new(spi: SPI, mut cs: CS) -> Self {
+        cs.set_high().ok();
+        W25q32Driver { spi, cs }
+    }
+
+    /// JEDEC ID as 0x00MMTTCC (manufacturer, memory type, capacity)
+    pub fn read_id(&mut self) -> u32 {
+        let mut buf = [CMD_JEDEC_ID, 0, 0, 0];
+        self.cs.set_low().ok();
+        self.spi.transfer(&mut buf).ok();
+        self.cs.set_high().ok();
+        (buf[1] as u32) << 16 | (buf[2] as u32) << 8 | buf[3] as u32
+    }
+
+    pub fn read_page(&mut self, page: u32, buf: &mut [u8; PAGE_SIZE]) {
+        self.cs.set_low().ok();
+        self.spi.write(&command(CMD_READ_DATA, page * PAGE_SIZE as u32)).ok();
+        self.spi.transfer(buf).ok();
+        self.cs.set_high().ok();
+    }
+
+    /// Programs a page. The page must have been erased before.
+    pub fn write_page(&mut self, page: u32, buf: &[u8; PAGE_SIZE]) {
+        self.write_enable();
+        self.cs.set_low().ok();
+        self.spi.write(&command(CMD_PAGE_PROGRAM, page * PAGE_SIZE as u32)).ok();
+        self.spi.write(buf).ok();
+        self.cs.set_high().ok();
+        self.wait_ready();
+    }
+
+    /// Erases a 4 KB sector (16 pages) to 0xFF
+    pub fn erase_sector(&mut self, sector: u32) {
+        self.write_enable();
+        self.cs.set_low().ok();
+        self.spi.write(&command(CMD_SECTOR_ERASE, sector * SECTOR_SIZE)).ok();
+        self.cs.set_high().ok();
+        self.wait_ready();
+    }
+
+    fn write_enable(&mut self) {
+        self.cs.set_low().ok();
+        self.spi.write(&[CMD_WRITE_ENABLE]).ok();
+        self.cs.set_high().ok();
+    }
+
+    fn wait_ready(&mut self) {
+        loop {
+            let mut buf = [CMD_READ_STATUS1, 0];
+            self.cs.set_low().ok();
+            self.spi.transfer(&mut buf).ok();
+            self.cs.set_high().ok();
+            if buf[1] & STATUS_BUSY == 0 {
+                break;
+            }
+        }
+    }
+}
+
+// Instruction followed by a 24-bit address
+fn command(cmd: u8, address: u32) -> [u8; 4] {
+    [cmd, (address >> 16) as u8, (address >> 8) as u8, address as u8]
+}
+
+// The first page holds the write pointer as a list of u32 entries, the
+// last written entry is the current one. Appending entries instead of
+// rewriting one value means sector 0 is erased only once every 64 pages.
+const POINTER_PAGE: u32 = 0;
+const POINTER_SLOTS: usize = PAGE_SIZE / 4;
+
+// CSV data starts from the second sector
+const DATA_START_PAGE: u32 = PAGES_PER_SECTOR;
+
+const CSV_HEADER: &str = "timestamp,temperature,humidity\r\n";
+
+/// Appends CSV rows of readings to the SPI flash. Rows are collected to a
+/// page buffer in RAM and written a page at a time.
+pub struct CsvLogger<SPI, CS> {
+    flash: W25q32Driver<SPI, CS>,
+    // Next page to program
+    next_page: u32,
+    // Used pointer slots in the pointer page
+    pointer_slot: usize,
+    page_buf: [u8; PAGE_SIZE],
+    page_len: usize,
+}
+
+impl<SPI, CS, E> CsvLogger<SPI, CS>
+where
+    SPI: Transfer<u8, Error = E> + Write<u8, Error = E>,
+    CS: OutputPin,
+{
+    /// Continues logging after the data already in the flash
+    pub fn new(mut flash: W25q32Driver<SPI, CS>) -> Self {
+        let mut page = [0u8; PAGE_SIZE];
+        flash.read_page(POINTER_PAGE, &mut page);
+
+        let mut next_page = DATA_START_PAGE;
+        let mut pointer_slot = 0;
+        for slot in page.chunks(4) {
+            let value = u32::from_le_bytes([slot[0], slot[1], slot[2], slot[3]]);
+            if value == 0xFFFF_FFFF {
+                break;
+            }
+            next_page = value;
+            pointer_slot += 1;
+        }
+
+        let mut logger = CsvLogger {
+            flash,
+            next_page,
+            pointer_slot,
+            page_buf: [0xFF; PAGE_SIZE],
+            page_len: 0,
+        };
+        if next_page == DATA_START_PAGE {
+            logger.append(CSV_HEADER.as_bytes());
+        }
+        logger
+    }
+
+    /// Adds a `timestamp,temperature,humidity` row
+    pub fn log(&mut self, timestamp_s: u32, temperature: f32, humidity: f32) {
+        let mut row: String<32> = String::new();
+        let _ = write!(row, "{},{:.1},{:.0}\r\n", timestamp_s, temperature, humidity);
+        self.append(row.as_bytes());
+    }
+
+    /// Writes the partially filled page buffer to flash
+    pub fn flush(&mut self) {
+        if self.page_len == 0 || self.next_page >= PAGE_COUNT {
+            return;
+        }
+
+        // Start of a new sector has to be erased before programming
+        if self.next_page % PAGES_PER_SECTOR == 0 {
+            self.flash.erase_sector(self.next_page / PAGES_PER_SECTOR);
+        }
+        self.flash.write_page(self.next_page, &self.page_buf);
+
+        self.next_page += 1;
+        self.page_buf = [0xFF; PAGE_SIZE];
+        self.page_len = 0;
+        self.store_pointer();
+    }
+
+    /// Streams all logged CSV data. A full log takes minutes over the UART,
+    /// `on_page` is called before each page, e.g. to feed the watchdog.
+    pub fn dump(
+        &mut self,
+        out: &mut impl core::fmt::Write,
+        mut on_page: impl FnMut(),
+    ) -> core::fmt::Result {
+        let mut page = [0u8; PAGE_SIZE];
+        for p in DATA_START_PAGE..self.next_page {
+            on_page();
+            self.flash.read_page(p, &mut page);
+            write_text(out, &page)?;
+        }
+        write_text(out, &self.page_buf[..self.page_len])
+    }
+
+    /// Erases all logged data and starts over. A sector erase takes up to
+    /// 400 ms, `on_sector` is called before each one.
+    pub fn erase(&mut self, mut on_sector: impl FnMut()) {
+        let last_sector = (self.next_page.max(DATA_START_PAGE + 1) - 1) / PAGES_PER_SECTOR;
+        for sector in 0..=last_sector {
+            on_sector();
+            self.flash.erase_sector(sector);
+        }
+        self.next_page = DATA_START_PAGE;
+        self.pointer_slot = 0;
+        self.page_buf = [0xFF; PAGE_SIZE];
+        self.page_len = 0;
+        self.append(CSV_HEADER.as_bytes());
+    }
+
+    fn append(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.page_buf[self.page_len] = b;
+            self.page_len += 1;
+            if self.page_len == PAGE_SIZE {
+                self.flush();
+            }
+        }
+    }
+
+    fn store_pointer(&mut self) {
+        let mut page = [0xFFu8; PAGE_SIZE];
+        if self.pointer_slot == POINTER_SLOTS {
+            self.flash.erase_sector(POINTER_PAGE / PAGES_PER_SECTOR);
+            self.pointer_slot = 0;
+        } else {
+            self.flash.read_page(POINTER_PAGE, &mut page);
+        }
+
+        let offset = self.pointer_slot * 4;
+        page[offset..offset + 4].copy_from_slice(&self.next_page.to_le_bytes());
+        self.flash.write_page(POINTER_PAGE, &page);
+        self.pointer_slot += 1;
+    }
+}
+
+// Writes the ASCII bytes of a page, stopping at erased (0xFF) bytes
+fn write_text(out: &mut impl core::fmt::Write, bytes: &[u8]) -> core::fmt::Result {
+    let end = bytes.iter().position(|&b| b == 0xFF).unwrap_or(bytes.len());
+    match core::str::from_utf8(&bytes[..end]) {
+        Ok(text) => out.write_str(text),
+        Err(_) => Ok(()),
+    }
+}
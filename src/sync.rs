@@ -0,0 +1,80 @@
+use core::cell::RefCell;
+use riscv::interrupt::{free, CriticalSection, Mutex};
+use riscv::register::mstatus;
+
+/// Moves a peripheral out of its shared cell, so that it can be used for a
+/// long time, e.g. for a sensor read, without keeping interrupts disabled.
+/// None when it isn't set up or has already been taken. Give it back with
+/// `put_back`.
+pub fn take<T>(cell: &Mutex<RefCell<Option<T>>>) -> Option<T> {
+    free(|cs| cell.borrow(*cs).borrow_mut().take())
+}
+
+pub fn put_back<T>(cell: &Mutex<RefCell<Option<T>>>, value: T) {
+    free(|cs| {
+        cell.borrow(*cs).replace(Some(value));
+    });
+}
+
+/// Critical section that lasts until the guard is dropped. Unlike
+/// `riscv::interrupt::free` this allows early returns (e.g. with `?`)
+/// from inside the critical section. The existing `free` closures have no
+/// early returns and are left as they are, a call site moves over to the
+/// guard when it needs one.
+///
+/// Interrupts are restored on drop only if they were enabled when the
+/// guard was created, so nesting guards or using one in an interrupt
+/// handler does not enable interrupts too early.
+///
+/// This is only sound on a single-hart system such as the GD32VF103:
+/// clearing `mstatus.MIE` masks interrupts on the current hart only and
+/// does nothing to stop code running on another hart. Do not use this
+/// type on multi-hart chips.
+pub struct GlobalInterruptGuard {
+    was_enabled: bool,
+}
+
+impl GlobalInterruptGuard {
+    pub fn new() -> Self {
+        let was_enabled = mstatus::read().mie();
+        unsafe { riscv::interrupt::disable() };
+        GlobalInterruptGuard { was_enabled }
+    }
+
+    /// Token for borrowing `Mutex` globals while the guard is alive
+    pub fn cs(&self) -> CriticalSection<'_> {
+        // Safe since interrupts stay disabled for the lifetime of the borrow
+        unsafe { CriticalSection::new() }
+    }
+}
+
+impl Drop for GlobalInterruptGuard {
+    fn drop(&mut self) {
+        if self.was_enabled {
+            unsafe { riscv::interrupt::enable() };
+        }
+    }
+}
+
+// rtt-target takes its lock through the critical-section crate, which
+// riscv 0.7 does not implement. Same single-hart caveat as above.
+#[cfg(feature = "defmt")]
+struct SingleHartCriticalSection;
+
+#[cfg(feature = "defmt")]
+critical_section::set_impl!(SingleHartCriticalSection);
+
+#[cfg(feature = "defmt")]
+unsafe impl critical_section::Impl for SingleHartCriticalSection {
+    unsafe fn acquire() -> critical_section::RawRestoreState {
+        let was_enabled = mstatus::read().mie();
+        riscv::interrupt::disable();
+        was_enabled
+    }
+
+    unsafe fn release(was_enabled: critical_section::RawRestoreState) {
+        if was_enabled {
+            riscv::interrupt::enable();
+        }
+    }
+}
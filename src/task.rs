@@ -0,0 +1,583 @@
+use core::cell::RefCell;
+use core::sync::atomic::Ordering;
+use embedded_graphics::{
+    mono_font::{ascii::FONT_6X10, MonoTextStyle, MonoTextStyleBuilder},
+    pixelcolor::Rgb565,
+    prelude::*,
+    primitives::{PrimitiveStyle, Rectangle},
+    text::{Alignment, Baseline, Text, TextStyleBuilder},
+};
+use embedded_hal::watchdog::Watchdog;
+use longan_nano::hal::watchdog::FreeWatchdog;
+use riscv::interrupt::{free, Mutex};
+use riscv::register::mcycle;
+
+use crate::alert::ALERT_ACTIVE;
+use crate::calibration::offsets_text;
+use crate::config::{load_boot_config, save_boot_config};
+use crate::derived_metrics::DerivedMetrics;
+use crate::display::backlight::{self, Backlight, DIM_PERCENT};
+use crate::display::layout::{active_layout, draw_discrepancy_banner};
+use crate::display::unit::set_temperature_unit;
+use crate::display::{
+    clear_region, layout_point, screen_size, temperature_unit, DisplayLayout, LayoutKind, Screen,
+    TemperatureUnit,
+};
+use crate::display_config::{
+    ALERT_COLOR, BG_COLOR, HIGHLIGHT_COLOR, OK_COLOR, STATUS_COLOR, TEXT_COLOR, UI_FONT,
+};
+use crate::history::HISTORY;
+use crate::input::button::{take_button_event, ButtonEvent};
+#[cfg(feature = "ir_remote")]
+use crate::input::ir::{IrAction, IR_COMMAND};
+use crate::metrics::METRICS;
+use crate::power::{power_mode, PowerMode, DUTY_CYCLE};
+use crate::sensor::{SENSOR_WARMUP, WARMUP_MONITOR};
+use crate::serial::{report_error_chain, UART_FRAME_ERRORS};
+use crate::types::SensorReading;
+#[cfg(feature = "battery")]
+use crate::ui::battery::{draw_battery_icon, BATTERY_ICON_AREA};
+use crate::ui::pages::clock::ClockPage;
+use crate::ui::pages::graph::draw_temperature_graph;
+use crate::ui::pages::metrics::draw_metrics;
+use crate::ui::pages::raw_data::draw_raw_data;
+use crate::ui::pages::minmax::draw_min_max;
+use crate::ui::pages::uptime::draw_uptime;
+use crate::ui::pages::vpd::draw_vpd;
+use crate::ui::pages::{Page, PAGE_CYCLE_S};
+use crate::{
+    BIT_ERROR_RATE, DATA, LAST_READ_OK, LAST_READ_QUALITY, MIN_MAX, RAW_BIT_COUNT, RAW_BYTES,
+    RAW_CHECKSUM_OK, SENSOR_DISAGREEMENT, TEMP_PEAK,
+};
+
+// Presses longer than this toggle the temperature unit instead of switching pages
+const LONG_PRESS_US: u32 = 1_000_000;
+
+// Holding the button this long selects the next update interval instead
+const INTERVAL_PRESS_US: u32 = 2_000_000;
+
+// Reading last drawn on the main page, None forces the next draw
+static LAST_DISPLAYED: Mutex<RefCell<Option<SensorReading>>> = Mutex::new(RefCell::new(None));
+
+// Changes up to these are not redrawn, to avoid flicker from redundant SPI writes
+const REDRAW_HYSTERESIS_TEMP: f32 = 0.4;
+const REDRAW_HYSTERESIS_HUMIDITY: f32 = 1.0;
+
+// Interrupts slept through per loop in the lowered power mode, 50 ms of
+// button sampling at 100 Hz
+const LOWERED_SLEEP_WAKEUPS: u32 = 5;
+
+// Outline of the warm-up progress bar
+const STABILIZING_BAR_SIZE: Size = Size::new(150, 10);
+
+/// Main loop of the weather station as a sequence of steps: read the
+/// sensor when the TIMER1 interrupt says a read is due, render the latest
+/// data, then sleep until the next interrupt. Every step is its own method so the loop
+/// maps directly onto an async task with one `.await` per step later on.
+pub struct WeatherTask {
+    lcd: Screen,
+    // Resets the MCU if the loop stops running, e.g. on a hung sensor read
+    watchdog: FreeWatchdog,
+    // Dimmed after a while without activity
+    backlight: Backlight,
+    style: MonoTextStyle<'static, Rgb565>,
+    // Sensor was still warming up or settling on the previous display update
+    warming_up: bool,
+    // The "Stabilizing..." screen has replaced "Initializing..."
+    stabilizing_shown: bool,
+    // Page drawn on the previous update
+    page: Page,
+    // Layout the main page was last drawn in
+    layout: LayoutKind,
+    // Whether the main page showed a reading rather than the sensor error
+    read_ok: bool,
+    // Whether that reading had humidity, thermistor fallback readings don't
+    has_humidity: bool,
+    // Whether the discrepancy warning replaced the comfort banner
+    disagreement: bool,
+    // HISTORY push count when the graph was last drawn, None after a page switch
+    graph_drawn_at: Option<u32>,
+    // Uptime of the last page switch, for the automatic cycle
+    page_switched_s: u32,
+    // Debounced button state and when it last changed, in microseconds
+    button_down: bool,
+    button_changed_us: u32,
+    // The current press has already toggled the unit
+    long_press_handled: bool,
+    // Temperature unit the main page was last drawn in
+    unit: TemperatureUnit,
+    // Uptime of the last display update. The button sampling wakes the loop
+    // 100 times a second, the display is only updated once a second.
+    updated_at_s: Option<u32>,
+}
+
+impl WeatherTask {
+    pub fn new(lcd: Screen, watchdog: FreeWatchdog, backlight: Backlight) -> Self {
+        let style = MonoTextStyleBuilder::new()
+            .font(&UI_FONT)
+            .text_color(TEXT_COLOR)
+            .background_color(BG_COLOR)
+            .build();
+
+        WeatherTask {
+            lcd,
+            watchdog,
+            backlight,
+            style,
+            warming_up: true,
+            stabilizing_shown: false,
+            page: Page::Current,
+            layout: LayoutKind::Simple,
+            read_ok: true,
+            has_humidity: true,
+            disagreement: false,
+            graph_drawn_at: None,
+            page_switched_s: 0,
+            button_down: false,
+            button_changed_us: 0,
+            long_press_handled: false,
+            unit: temperature_unit(),
+            updated_at_s: None,
+        }
+    }
+
+    pub fn run(&mut self) -> ! {
+        loop {
+            self.watchdog.feed();
+            if crate::READ_DUE.swap(false, Ordering::Relaxed) {
+                crate::update_reading();
+            }
+            let now_s = crate::uptime_s();
+            if self.updated_at_s != Some(now_s) {
+                self.updated_at_s = Some(now_s);
+                self.update_display();
+                self.flush();
+                self.update_backlight();
+            }
+            crate::process_commands();
+            self.sleep();
+            self.poll_button();
+            #[cfg(feature = "ir_remote")]
+            self.poll_ir();
+        }
+    }
+
+    // Draw the current page, or the warm-up screens until the sensor is ready
+    fn update_display(&mut self) {
+        if !SENSOR_WARMUP.is_ready(crate::uptime_s()) {
+            Text::new("Initializing...", layout_point(5, 45), self.style)
+                .draw(&mut self.lcd)
+                .unwrap();
+            return;
+        }
+
+        let (settled, progress) = free(|cs| {
+            let warmup = WARMUP_MONITOR.borrow(*cs).borrow();
+            (warmup.is_settled(), warmup.progress_percent(crate::uptime_s()))
+        });
+        if !settled {
+            self.draw_stabilizing(progress);
+            return;
+        }
+
+        // Cycle automatically unless the button has switched pages recently
+        let now_s = crate::uptime_s();
+        if now_s.wrapping_sub(self.page_switched_s) >= PAGE_CYCLE_S {
+            self.page_switched_s = now_s;
+            advance_page();
+        }
+
+        // Clear the warm-up message, or the part of the screen the previous page used
+        let page = Page::current();
+        if self.warming_up || page != self.page {
+            if self.warming_up {
+                clear_region(&mut self.lcd, Point::zero(), screen_size());
+            } else {
+                self.page.clear(&mut self.lcd);
+            }
+            self.warming_up = false;
+            self.page = page;
+            self.graph_drawn_at = None;
+            free(|cs| LAST_DISPLAYED.borrow(*cs).replace(None));
+        }
+
+        self.render_page(page);
+        self.draw_alert_indicator();
+        self.draw_power_mode_indicator();
+        #[cfg(feature = "battery")]
+        self.draw_battery_indicator();
+    }
+
+    // Draw the given page
+    fn render_page(&mut self, page: Page) {
+        match page {
+            Page::Current => self.draw_readings(),
+            Page::MinMax => self.draw_min_max(),
+            Page::Graph => self.draw_graph(),
+            Page::Metrics => {
+                let (metrics, health, quality, frame_errors) = free(|cs| {
+                    let metrics = *METRICS.borrow(*cs).borrow();
+                    let health = BIT_ERROR_RATE.borrow(*cs).borrow().health();
+                    let quality = *LAST_READ_QUALITY.borrow(*cs).borrow();
+                    (metrics, health, quality, *UART_FRAME_ERRORS.borrow(*cs).borrow())
+                });
+                draw_metrics(
+                    &mut self.lcd,
+                    &metrics,
+                    health,
+                    quality,
+                    frame_errors,
+                    &offsets_text(),
+                );
+            }
+            Page::Uptime => draw_uptime(
+                &mut self.lcd,
+                crate::uptime_s(),
+                crate::update_interval_s(),
+                self.style,
+            ),
+            Page::Vpd => {
+                let reading = free(|cs| *DATA.borrow(*cs).borrow());
+                if let Some(reading) = reading.filter(SensorReading::has_humidity) {
+                    draw_vpd(&mut self.lcd, &reading);
+                }
+            }
+            Page::Clock => {
+                let reading = free(|cs| *DATA.borrow(*cs).borrow());
+                if let Some(unix_s) = crate::wall_clock_s() {
+                    ClockPage::new().draw(&mut self.lcd, unix_s, crate::uptime_s(), reading);
+                }
+            }
+            Page::RawData => {
+                let bytes = free(|cs| *RAW_BYTES.borrow(*cs).borrow());
+                draw_raw_data(
+                    &mut self.lcd,
+                    &bytes,
+                    RAW_BIT_COUNT.load(Ordering::Relaxed),
+                    RAW_CHECKSUM_OK.load(Ordering::Relaxed),
+                );
+            }
+        }
+    }
+
+    // Temperature and humidity in the selected layout, or the error when the
+    // latest read failed. Cleared first when the layout or read state changed,
+    // otherwise skipped while the reading stays within the redraw hysteresis.
+    fn draw_readings(&mut self) {
+        let (reading, read_ok) = free(|cs| {
+            let reading = *DATA.borrow(*cs).borrow();
+            (reading, LAST_READ_OK.load(Ordering::Relaxed))
+        });
+        // No data yet
+        let reading = match reading {
+            Some(reading) => reading,
+            None => return,
+        };
+
+        let layout = active_layout();
+        let unit = temperature_unit();
+        let has_humidity = reading.has_humidity();
+        if layout != self.layout
+            || read_ok != self.read_ok
+            || unit != self.unit
+            || has_humidity != self.has_humidity
+        {
+            self.layout = layout;
+            self.read_ok = read_ok;
+            self.unit = unit;
+            self.has_humidity = has_humidity;
+            free(|cs| LAST_DISPLAYED.borrow(*cs).replace(None));
+            Page::Current.clear(&mut self.lcd);
+        }
+
+        if !read_ok {
+            layout.render_error(&mut self.lcd);
+            return;
+        }
+
+        // A full redraw brings the comfort banner back once the sensors agree again
+        let disagreement = SENSOR_DISAGREEMENT.load(Ordering::Relaxed);
+        if disagreement != self.disagreement {
+            self.disagreement = disagreement;
+            free(|cs| LAST_DISPLAYED.borrow(*cs).replace(None));
+        }
+
+        let last = free(|cs| *LAST_DISPLAYED.borrow(*cs).borrow());
+        if let Some(last) = last {
+            if !exceeds_hysteresis(&last, &reading) {
+                return;
+            }
+        }
+
+        layout.render(&reading, &DerivedMetrics::from_reading(&reading), &mut self.lcd);
+        if disagreement {
+            draw_discrepancy_banner(&mut self.lcd);
+        }
+        free(|cs| LAST_DISPLAYED.borrow(*cs).replace(Some(reading)));
+    }
+
+    // Temperature history, redrawn only when a new reading has arrived
+    fn draw_graph(&mut self) {
+        let lcd = &mut self.lcd;
+        let drawn_at = &mut self.graph_drawn_at;
+        free(|cs| {
+            let history = HISTORY.borrow(*cs).borrow();
+            if *drawn_at != Some(history.pushed()) {
+                *drawn_at = Some(history.pushed());
+                let peak = TEMP_PEAK.borrow(*cs).borrow().value();
+                draw_temperature_graph(lcd, &history, peak);
+            }
+        });
+    }
+
+    // Lowest and highest values since power-on
+    fn draw_min_max(&mut self) {
+        let extremes = free(|cs| MIN_MAX.borrow(*cs).borrow().extremes());
+        if let Some((min, max)) = extremes {
+            draw_min_max(&mut self.lcd, &min, &max, self.style);
+        }
+    }
+
+    // "!" in the top right corner blinking at 0.5 Hz while the alert pin is high
+    fn draw_alert_indicator(&mut self) {
+        let visible = ALERT_ACTIVE.load(Ordering::Relaxed) && crate::uptime_s() % 2 == 0;
+        let style = MonoTextStyleBuilder::new()
+            .font(&FONT_6X10)
+            .text_color(ALERT_COLOR)
+            .background_color(BG_COLOR)
+            .build();
+        // Left of the battery icon when there is one
+        #[cfg(feature = "battery")]
+        let right = screen_size().width - BATTERY_ICON_AREA.width;
+        #[cfg(not(feature = "battery"))]
+        let right = screen_size().width;
+        let top_right = Point::new(right as i32 - 1, 0);
+        let text_style = TextStyleBuilder::new()
+            .alignment(Alignment::Right)
+            .baseline(Baseline::Top)
+            .build();
+        Text::with_text_style(if visible { "!" } else { " " }, top_right, style, text_style)
+            .draw(&mut self.lcd)
+            .unwrap();
+    }
+
+    // Charge level in the top right corner, redrawn with the other indicators
+    #[cfg(feature = "battery")]
+    fn draw_battery_indicator(&mut self) {
+        if let Some((percent, charging)) = crate::battery::battery_level() {
+            let top_right = Point::new(screen_size().width as i32 - 1, 0);
+            draw_battery_icon(&mut self.lcd, percent, charging, top_right);
+        }
+    }
+
+    // "Z" in the top left corner while the sensor is read at the lowered rate
+    fn draw_power_mode_indicator(&mut self) {
+        let lowered = power_mode() == PowerMode::Lowered;
+        let style = MonoTextStyleBuilder::new()
+            .font(&FONT_6X10)
+            .text_color(STATUS_COLOR)
+            .background_color(BG_COLOR)
+            .build();
+        let text_style = TextStyleBuilder::new()
+            .alignment(Alignment::Left)
+            .baseline(Baseline::Top)
+            .build();
+        Text::with_text_style(if lowered { "Z" } else { " " }, Point::zero(), style, text_style)
+            .draw(&mut self.lcd)
+            .unwrap();
+    }
+
+    // Warm-up message with a bar showing the estimated progress
+    fn draw_stabilizing(&mut self, progress_percent: u8) {
+        if !self.stabilizing_shown {
+            self.stabilizing_shown = true;
+            clear_region(&mut self.lcd, Point::zero(), screen_size());
+        }
+
+        Text::new("Stabilizing...", layout_point(5, 30), self.style)
+            .draw(&mut self.lcd)
+            .unwrap();
+
+        let bar_top_left = layout_point(5, 45);
+        Rectangle::new(bar_top_left, STABILIZING_BAR_SIZE)
+            .into_styled(PrimitiveStyle::with_stroke(HIGHLIGHT_COLOR, 1))
+            .draw(&mut self.lcd)
+            .unwrap();
+
+        let fill_width = (STABILIZING_BAR_SIZE.width - 4) * progress_percent as u32 / 100;
+        if fill_width > 0 {
+            Rectangle::new(
+                bar_top_left + Point::new(2, 2),
+                Size::new(fill_width, STABILIZING_BAR_SIZE.height - 4),
+            )
+            .into_styled(PrimitiveStyle::with_fill(OK_COLOR))
+            .draw(&mut self.lcd)
+            .unwrap();
+        }
+    }
+
+    // A short press of the active-low button switches to the next page when
+    // released, a press longer than LONG_PRESS_US toggles the temperature
+    // unit when released. Holding it past INTERVAL_PRESS_US selects the next
+    // update interval right away. The events come debounced from the TIMER5
+    // interrupt.
+    fn poll_button(&mut self) {
+        let now_us = crate::now_us();
+
+        let event = take_button_event();
+        if event != ButtonEvent::None {
+            backlight::reset_idle();
+            self.update_backlight();
+        }
+
+        match event {
+            ButtonEvent::Pressed => {
+                self.button_down = true;
+                self.button_changed_us = now_us;
+                self.long_press_handled = false;
+            }
+            ButtonEvent::Released => {
+                self.button_down = false;
+                // Nothing more to do if the interval was changed while held
+                if !self.long_press_handled {
+                    let held_us = now_us.wrapping_sub(self.button_changed_us);
+                    if held_us > LONG_PRESS_US {
+                        toggle_temperature_unit();
+                    } else {
+                        advance_page();
+                        self.page_switched_s = crate::uptime_s();
+                    }
+                    self.update_display();
+                    self.flush();
+                }
+            }
+            ButtonEvent::None => {}
+        }
+
+        let held_us = now_us.wrapping_sub(self.button_changed_us);
+        if self.button_down && !self.long_press_handled && held_us > INTERVAL_PRESS_US {
+            self.long_press_handled = true;
+            select_next_update_interval();
+            self.update_display();
+            self.flush();
+        }
+    }
+
+    // Handles the latest frame of the IR remote, the volume buttons page
+    // like the page button and mute toggles the unit like a long press
+    #[cfg(feature = "ir_remote")]
+    fn poll_ir(&mut self) {
+        let frame = free(|cs| IR_COMMAND.borrow(*cs).borrow_mut().take());
+        let action = match frame.and_then(|frame| frame.action()) {
+            Some(action) => action,
+            None => return,
+        };
+
+        backlight::reset_idle();
+        self.update_backlight();
+        match action {
+            IrAction::NextPage => {
+                advance_page();
+                self.page_switched_s = crate::uptime_s();
+            }
+            IrAction::PreviousPage => {
+                retreat_page();
+                self.page_switched_s = crate::uptime_s();
+            }
+            IrAction::ToggleUnit => toggle_temperature_unit(),
+        }
+        self.update_display();
+        self.flush();
+    }
+
+    // Dim the backlight while idle, otherwise as bright as the ambient light calls for
+    fn update_backlight(&mut self) {
+        #[cfg(feature = "ambient_light")]
+        if crate::uptime_s() % crate::display::LDR_SAMPLE_INTERVAL == 0 {
+            crate::display::ldr::sample();
+        }
+        #[cfg(feature = "battery")]
+        if crate::uptime_s() % crate::battery::BATTERY_SAMPLE_INTERVAL == 0 {
+            crate::battery::sample();
+        }
+        let percent = if backlight::is_idle() {
+            DIM_PERCENT
+        } else {
+            crate::display::active_brightness()
+        };
+        self.backlight.set_percent(percent);
+    }
+
+    // Send the frame buffer to the LCD. Without lcd_dma drawing goes straight
+    // to the LCD and there is nothing to do.
+    fn flush(&mut self) {
+        #[cfg(feature = "lcd_dma")]
+        self.lcd.flush();
+    }
+
+    //set chip to sleep. In the lowered power mode the loop sleeps through
+    //several button samples at a time, the button event waits meanwhile.
+    fn sleep(&mut self) {
+        let wakeups = match power_mode() {
+            PowerMode::Normal => 1,
+            PowerMode::Lowered => LOWERED_SLEEP_WAKEUPS,
+        };
+        let start = mcycle::read64();
+        for _ in 0..wakeups {
+            unsafe {
+                riscv::asm::wfi();
+            }
+        }
+        free(|cs| DUTY_CYCLE.borrow(*cs).borrow_mut().record_sleep(start));
+    }
+}
+
+// Switches to the next page, skipping the clock while it is not set
+fn advance_page() {
+    Page::advance();
+    if Page::current() == Page::Clock && crate::wall_clock_s().is_none() {
+        Page::advance();
+    }
+}
+
+// Switches to the previous page, skipping the clock while it is not set
+#[cfg(feature = "ir_remote")]
+fn retreat_page() {
+    Page::retreat();
+    if Page::current() == Page::Clock && crate::wall_clock_s().is_none() {
+        Page::retreat();
+    }
+}
+
+// Switches between Celsius and Fahrenheit and saves the choice to flash
+fn toggle_temperature_unit() {
+    let unit = temperature_unit().toggled();
+    set_temperature_unit(unit);
+
+    let mut config = load_boot_config();
+    config.temperature_unit = unit.to_u8();
+    if let Err(e) = save_boot_config(&config) {
+        report_error_chain(&["unit", "flash"], &e);
+    }
+}
+
+// Cycles to the next update interval and saves it to flash
+fn select_next_update_interval() {
+    let interval_s = crate::next_update_interval();
+
+    let mut config = load_boot_config();
+    config.update_interval_s = interval_s as u8;
+    if let Err(e) = save_boot_config(&config) {
+        report_error_chain(&["interval", "flash"], &e);
+    }
+}
+
+/// True when the reading has moved more than the redraw hysteresis from the one on screen
+pub fn exceeds_hysteresis(shown: &SensorReading, reading: &SensorReading) -> bool {
+    let dt = reading.temperature - shown.temperature;
+    let dh = reading.humidity - shown.humidity;
+    dt > REDRAW_HYSTERESIS_TEMP
+        || dt < -REDRAW_HYSTERESIS_TEMP
+        || dh > REDRAW_HYSTERESIS_HUMIDITY
+        || dh < -REDRAW_HYSTERESIS_HUMIDITY
+}
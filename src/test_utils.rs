@@ -0,0 +1,113 @@
+use core::cell::RefCell;
+use core::fmt::Write;
+use riscv::interrupt::{free, Mutex};
+
+use crate::dht::SensorError;
+use crate::types::SensorReading;
+
+/// Fault to simulate in place of a real sensor read
+#[derive(Clone, Copy, Debug)]
+pub enum InjectedFault {
+    ChecksumError,
+    // Sensor stops responding in the middle of the frame
+    Timeout,
+    // Sensor keeps returning the same temperature
+    StuckReading(f32),
+    // Sensor does not respond at all
+    PowerLoss,
+}
+
+impl InjectedFault {
+    /// Fault by its name in `:inject`. `stuck` holds the temperature of
+    /// the latest reading.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "checksum" => InjectedFault::ChecksumError,
+            "timeout" => InjectedFault::Timeout,
+            "stuck" => InjectedFault::StuckReading(free(|cs| {
+                crate::DATA.borrow(*cs).borrow().map_or(0.0, |d| d.temperature)
+            })),
+            "powerloss" => InjectedFault::PowerLoss,
+            _ => return None,
+        })
+    }
+}
+
+/// Replaces real sensor reads with simulated faults for a number of reads
+/// so that retry and recovery paths can be exercised on real hardware
+pub struct FaultInjector {
+    fault: Option<InjectedFault>,
+    remaining_reads: u8,
+}
+
+pub static FAULT_INJECTOR: Mutex<RefCell<FaultInjector>> =
+    Mutex::new(RefCell::new(FaultInjector::new()));
+
+impl FaultInjector {
+    pub const fn new() -> Self {
+        FaultInjector {
+            fault: None,
+            remaining_reads: 0,
+        }
+    }
+
+    pub fn inject(&mut self, fault: InjectedFault, duration_reads: u8) {
+        self.fault = Some(fault);
+        self.remaining_reads = duration_reads;
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.fault.is_some()
+    }
+
+    // Fault for the current read, clears itself after duration_reads reads
+    fn next_fault(&mut self) -> Option<InjectedFault> {
+        let fault = self.fault?;
+        self.remaining_reads = self.remaining_reads.saturating_sub(1);
+        if self.remaining_reads == 0 {
+            self.fault = None;
+        }
+        Some(fault)
+    }
+}
+
+/// Runs `:inject <fault> <reads>`, e.g. `:inject timeout 5`. Without
+/// arguments tells whether a fault is being injected.
+pub fn process_inject(args: &str, out: &mut impl Write) -> Result<(), &'static str> {
+    if args.is_empty() {
+        let active = free(|cs| FAULT_INJECTOR.borrow(*cs).borrow().is_active());
+        let _ = write!(out, "Fault injection: {}", if active { "active" } else { "off" });
+        return Ok(());
+    }
+
+    let mut words = args.split_whitespace();
+    let fault = words.next().and_then(InjectedFault::from_name);
+    let reads = words.next().and_then(|n| n.parse::<u8>().ok()).filter(|&n| n > 0);
+    match (fault, reads, words.next()) {
+        (Some(fault), Some(reads), None) => {
+            free(|cs| FAULT_INJECTOR.borrow(*cs).borrow_mut().inject(fault, reads));
+            let _ = write!(out, "Injecting {:?} for {} reads", fault, reads);
+            Ok(())
+        }
+        _ => Err("Inject must be :inject <checksum|timeout|stuck|powerloss> <1-255>!"),
+    }
+}
+
+/// Result to return from read_data instead of reading the sensor, if a
+/// fault is being injected
+pub fn injected_result() -> Option<Result<SensorReading, SensorError>> {
+    let fault = free(|cs| FAULT_INJECTOR.borrow(*cs).borrow_mut().next_fault())?;
+
+    Some(match fault {
+        InjectedFault::ChecksumError => Err(SensorError::ChecksumMismatch {
+            expected: 0x5a,
+            got: 0x5b,
+        }),
+        InjectedFault::Timeout => Err(SensorError::Timeout { at_bit: 20 }),
+        InjectedFault::PowerLoss => Err(SensorError::Timeout { at_bit: 0 }),
+        InjectedFault::StuckReading(t) => {
+            let h = free(|cs| crate::DATA.borrow(*cs).borrow().map_or(0.0, |d| d.humidity));
+            Ok(SensorReading::new(t, h))
+        }
+    })
+}
@@ -0,0 +1,182 @@
+extern crate std;
+
+use core::convert::Infallible;
+use embedded_hal::blocking::delay::DelayUs;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+use std::vec::Vec;
+
+use crate::dht::capture::EdgeCapture;
+use crate::dht::protocol::FRAME_BITS;
+use crate::dht::{IntoInputPin, IntoOutputPin, SensorError};
+use crate::sensor::WeatherSensor;
+use crate::types::SensorReading;
+
+/// Sensor returning canned responses in order, for running the reading
+/// pipeline on the host
+pub struct SimulatedSensor {
+    responses: VecDeque<Result<SensorReading, SensorError>>,
+}
+
+impl SimulatedSensor {
+    pub fn new(responses: VecDeque<Result<SensorReading, SensorError>>) -> Self {
+        SimulatedSensor { responses }
+    }
+
+    /// Responses not yet read
+    pub fn remaining(&self) -> usize {
+        self.responses.len()
+    }
+}
+
+impl WeatherSensor for SimulatedSensor {
+    // Once the responses run out the sensor behaves like it was unplugged
+    fn read(&mut self, _delay: &mut impl DelayUs<u32>) -> Result<SensorReading, SensorError> {
+        self.responses
+            .pop_front()
+            .unwrap_or(Err(SensorError::Timeout { at_bit: 0 }))
+    }
+}
+
+/// Sensor data pin for the driver on the host. The same type serves as
+/// both the output and the input side, the levels driven on it are kept
+/// in a log shared with the test.
+pub struct MockPin {
+    high: bool,
+    driven: Rc<RefCell<Vec<bool>>>,
+}
+
+impl MockPin {
+    pub fn new() -> Self {
+        MockPin {
+            high: true,
+            driven: Rc::new(RefCell::new(Vec::new())),
+        }
+    }
+
+    /// Levels driven on the pin in order, still readable after the pin has
+    /// been moved into the driver
+    pub fn driven(&self) -> Rc<RefCell<Vec<bool>>> {
+        Rc::clone(&self.driven)
+    }
+}
+
+impl Default for MockPin {
+    fn default() -> Self {
+        MockPin::new()
+    }
+}
+
+impl OutputPin for MockPin {
+    type Error = Infallible;
+
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        self.high = false;
+        self.driven.borrow_mut().push(false);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        self.high = true;
+        self.driven.borrow_mut().push(true);
+        Ok(())
+    }
+}
+
+impl InputPin for MockPin {
+    type Error = Infallible;
+
+    fn is_high(&self) -> Result<bool, Infallible> {
+        Ok(self.high)
+    }
+
+    fn is_low(&self) -> Result<bool, Infallible> {
+        Ok(!self.high)
+    }
+}
+
+impl IntoInputPin for MockPin {
+    type Input = MockPin;
+
+    fn into_input_pin(self) -> MockPin {
+        self
+    }
+}
+
+impl IntoOutputPin for MockPin {
+    type Output = MockPin;
+
+    fn into_output_pin(self) -> MockPin {
+        self
+    }
+}
+
+/// Delay that returns at once and adds up the time it was asked for
+#[derive(Default)]
+pub struct MockDelay {
+    pub total_us: u64,
+}
+
+impl MockDelay {
+    pub fn new() -> Self {
+        MockDelay { total_us: 0 }
+    }
+}
+
+impl DelayUs<u32> for MockDelay {
+    fn delay_us(&mut self, us: u32) {
+        self.total_us += us as u64;
+    }
+}
+
+/// Edge capture replaying recorded timestamps. Each `wait_edge` takes the
+/// next one, the direction is trusted to alternate. Once they run out the
+/// line stays where it is and every wait times out.
+pub struct ScriptedCapture {
+    edges: VecDeque<u16>,
+}
+
+impl ScriptedCapture {
+    pub fn new(edges: Vec<u16>) -> Self {
+        ScriptedCapture {
+            edges: edges.into(),
+        }
+    }
+}
+
+impl EdgeCapture for ScriptedCapture {
+    fn wait_edge(&mut self, _rising: bool, _timeout_us: u32) -> Option<u16> {
+        self.edges.pop_front()
+    }
+}
+
+// Widths (µs) of the high pulse of a 0 and a 1 bit and of the low pulse before each bit
+const ZERO_HIGH_US: u16 = 26;
+const ONE_HIGH_US: u16 = 70;
+const BIT_LOW_US: u16 = 50;
+
+// Sensor response before the first bit: its 80 µs low and 80 µs high
+const ACK_US: u16 = 80;
+
+/// Edge timestamps of a sensor sending `data`, starting from the falling
+/// edge of its response at time 1000. Only the first `bits` bits are sent,
+/// after that the sensor lets the line go high.
+pub fn response_edges(data: [u8; 5], bits: usize) -> Vec<u16> {
+    let mut time: u16 = 1000;
+    let mut edges = Vec::new();
+    edges.push(time);
+    for &width in [ACK_US, ACK_US, BIT_LOW_US].iter() {
+        time = time.wrapping_add(width);
+        edges.push(time);
+    }
+    for index in 0..bits.min(FRAME_BITS) {
+        let one = data[index / 8] & (0x80 >> (index % 8)) != 0;
+        time = time.wrapping_add(if one { ONE_HIGH_US } else { ZERO_HIGH_US });
+        edges.push(time);
+        time = time.wrapping_add(BIT_LOW_US);
+        edges.push(time);
+    }
+    edges
+}
@@ -0,0 +1,78 @@
+use core::fmt::Write;
+use heapless::String;
+
+/// Wall-clock time of day, kept apart from the `u32` uptime seconds used
+/// elsewhere so the two can't be mixed up
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct TimeOfDay {
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl TimeOfDay {
+    pub const fn new(hour: u8, minute: u8, second: u8) -> Self {
+        TimeOfDay {
+            hour,
+            minute,
+            second,
+        }
+    }
+
+    /// Time of day (UTC) of a Unix timestamp
+    pub fn from_unix_s(unix_s: u32) -> Self {
+        let seconds_of_day = unix_s % 86_400;
+        TimeOfDay {
+            hour: (seconds_of_day / 3600) as u8,
+            minute: (seconds_of_day % 3600 / 60) as u8,
+            second: (seconds_of_day % 60) as u8,
+        }
+    }
+
+    /// Minutes since midnight, 0-1439
+    pub fn to_minute_of_day(&self) -> u16 {
+        self.hour as u16 * 60 + self.minute as u16
+    }
+
+    /// True when the time is in [start, end). The range wraps over
+    /// midnight when end is before start, e.g. 22:00-06:00.
+    pub fn is_between(&self, start: &TimeOfDay, end: &TimeOfDay) -> bool {
+        if start <= end {
+            start <= self && self < end
+        } else {
+            self >= start || self < end
+        }
+    }
+
+    /// `HH:MM:SS`
+    pub fn format(&self) -> String<9> {
+        let mut text = String::new();
+        // Can't fail, 8 characters always fit
+        let _ = write!(text, "{:02}:{:02}:{:02}", self.hour, self.minute, self.second);
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn window_over_midnight() {
+        let start = TimeOfDay::new(22, 0, 0);
+        let end = TimeOfDay::new(6, 0, 0);
+        assert!(TimeOfDay::new(23, 30, 0).is_between(&start, &end));
+        assert!(TimeOfDay::new(5, 59, 59).is_between(&start, &end));
+        assert!(!TimeOfDay::new(6, 0, 0).is_between(&start, &end));
+        assert!(!TimeOfDay::new(12, 0, 0).is_between(&start, &end));
+    }
+
+    #[test]
+    fn unix_time_of_day() {
+        // 2024-01-15 14:30:05 UTC
+        let time = TimeOfDay::from_unix_s(1_705_329_005);
+        assert_eq!(time, TimeOfDay::new(14, 30, 5));
+        assert_eq!(time.to_minute_of_day(), 870);
+        assert_eq!(time.format().as_str(), "14:30:05");
+    }
+}
@@ -0,0 +1,99 @@
+use core::fmt;
+
+use crate::util::fmt::to_tenths;
+
+/// Sensor a reading came from
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ReadingSource {
+    Dht,
+    // Thermistor used while the DHT fails, measures no humidity
+    Ntc,
+}
+
+/// One measurement of the sensor. Still in f32: the frame decoder works
+/// in Celsius and RelativeHumidity and converts with `from_fixed`, the
+/// filters, alerts, pages and derived metrics after it use the floats.
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SensorReading {
+    // °C
+    pub temperature: f32,
+    // Relative humidity, %. NaN for thermistor readings.
+    pub humidity: f32,
+    pub source: ReadingSource,
+}
+
+impl SensorReading {
+    pub const fn new(temperature: f32, humidity: f32) -> Self {
+        SensorReading {
+            temperature,
+            humidity,
+            source: ReadingSource::Dht,
+        }
+    }
+
+    /// Temperature from the thermistor fallback, the humidity is NaN
+    pub const fn from_ntc(temperature: f32) -> Self {
+        SensorReading {
+            temperature,
+            humidity: f32::NAN,
+            source: ReadingSource::Ntc,
+        }
+    }
+
+    /// False for thermistor readings, whose humidity is NaN
+    pub fn has_humidity(&self) -> bool {
+        self.source == ReadingSource::Dht
+    }
+
+    /// Placeholder before the first reading has been taken
+    pub const fn zero() -> Self {
+        SensorReading::new(0.0, 0.0)
+    }
+
+    pub fn from_fixed(temperature: Celsius, humidity: RelativeHumidity) -> Self {
+        SensorReading::new(temperature.to_f32(), humidity.to_f32())
+    }
+
+    pub fn celsius(&self) -> Celsius {
+        Celsius::from_f32(self.temperature)
+    }
+}
+
+/// Temperature in tenths of a degree, 23.4°C is `Celsius(234)`. The core
+/// has no FPU, so integer math keeps the sensor path free of float calls.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Celsius(pub i16);
+
+impl Celsius {
+    /// Rounded to the nearest tenth
+    pub fn from_f32(degrees: f32) -> Self {
+        Celsius(to_tenths(degrees) as i16)
+    }
+
+    pub fn to_f32(self) -> f32 {
+        self.0 as f32 / 10.0
+    }
+}
+
+// Written as `-12.3` without going through float formatting
+impl fmt::Display for Celsius {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let abs = self.0.unsigned_abs();
+        write!(f, "{}{}.{}", sign, abs / 10, abs % 10)
+    }
+}
+
+/// Relative humidity in whole percent
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RelativeHumidity(pub u8);
+
+impl RelativeHumidity {
+    pub fn to_f32(self) -> f32 {
+        self.0 as f32
+    }
+}
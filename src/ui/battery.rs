@@ -0,0 +1,92 @@
+use embedded_graphics::{
+    pixelcolor::Rgb565,
+    prelude::*,
+    primitives::{PrimitiveStyle, Rectangle},
+};
+
+use crate::display_config::{ALERT_COLOR, BG_COLOR, HIGHLIGHT_COLOR, OK_COLOR, WARNING_COLOR};
+
+// Battery outline and the nub on its right side
+const BATTERY_SIZE: Size = Size::new(20, 10);
+const NUB_SIZE: Size = Size::new(2, 4);
+
+// Space reserved for the icon at the top right corner of the display
+pub const BATTERY_ICON_AREA: Size = Size::new(25, 12);
+
+// Li-Ion cell voltage at 0% and 100% charge
+const BATTERY_EMPTY_V: f32 = 3.0;
+const BATTERY_FULL_V: f32 = 4.2;
+
+// Lightning bolt shown while charging, 5x8 pixels, MSB is the leftmost pixel
+static BOLT_BITMAP: [u8; 8] = [
+    0b00010, 0b00100, 0b01100, 0b11111, 0b00110, 0b00100, 0b01000, 0b10000,
+];
+
+/// Charge level (%) of a Li-Ion cell from its voltage, linear between
+/// 3.0 V and 4.2 V
+pub fn battery_percent(voltage: f32) -> u8 {
+    let level = (voltage - BATTERY_EMPTY_V) / (BATTERY_FULL_V - BATTERY_EMPTY_V) * 100.0;
+    level.max(0.0).min(100.0) as u8
+}
+
+/// Draws a battery icon filled according to `level_percent` with its top
+/// right corner at `top_right`
+pub fn draw_battery_icon<D>(lcd: &mut D, level_percent: u8, charging: bool, top_right: Point)
+where
+    D: DrawTarget<Color = Rgb565>,
+{
+    let level = level_percent.min(100) as u32;
+    let area_top_left = top_right - Point::new(BATTERY_ICON_AREA.width as i32, 0);
+    let body_top_left = area_top_left + Point::new(1, 1);
+
+    // Clear the reserved area so a lower level does not leave old fill behind
+    Rectangle::new(area_top_left, BATTERY_ICON_AREA)
+        .into_styled(PrimitiveStyle::with_fill(BG_COLOR))
+        .draw(lcd)
+        .ok();
+
+    Rectangle::new(body_top_left, BATTERY_SIZE)
+        .into_styled(PrimitiveStyle::with_stroke(HIGHLIGHT_COLOR, 1))
+        .draw(lcd)
+        .ok();
+
+    let nub_top_left = body_top_left
+        + Point::new(
+            BATTERY_SIZE.width as i32,
+            (BATTERY_SIZE.height - NUB_SIZE.height) as i32 / 2,
+        );
+    Rectangle::new(nub_top_left, NUB_SIZE)
+        .into_styled(PrimitiveStyle::with_fill(HIGHLIGHT_COLOR))
+        .draw(lcd)
+        .ok();
+
+    let fill_color = if level > 50 {
+        OK_COLOR
+    } else if level >= 20 {
+        WARNING_COLOR
+    } else {
+        ALERT_COLOR
+    };
+    // One pixel gap between the outline and the fill
+    let inner = Size::new(BATTERY_SIZE.width - 4, BATTERY_SIZE.height - 4);
+    let fill_width = inner.width * level / 100;
+    if fill_width > 0 {
+        Rectangle::new(
+            body_top_left + Point::new(2, 2),
+            Size::new(fill_width, inner.height),
+        )
+        .into_styled(PrimitiveStyle::with_fill(fill_color))
+        .draw(lcd)
+        .ok();
+    }
+
+    if charging {
+        let bolt_top_left = body_top_left + Point::new(BATTERY_SIZE.width as i32 / 2 - 2, 1);
+        let pixels = BOLT_BITMAP.iter().enumerate().flat_map(move |(y, row)| {
+            (0..5).filter(move |&x| row & (0b10000 >> x) != 0).map(move |x| {
+                Pixel(bolt_top_left + Point::new(x, y as i32), HIGHLIGHT_COLOR)
+            })
+        });
+        lcd.draw_iter(pixels).ok();
+    }
+}
@@ -0,0 +1,5 @@
+#[cfg(feature = "battery")]
+pub mod battery;
+pub mod pages;
+pub mod splash;
+pub mod widgets;
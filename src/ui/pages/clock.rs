@@ -0,0 +1,110 @@
+use core::fmt::Write;
+use embedded_graphics::{
+    mono_font::{
+        ascii::FONT_8X13,
+        iso_8859_1::FONT_6X10,
+        MonoTextStyle, MonoTextStyleBuilder,
+    },
+    pixelcolor::Rgb565,
+    prelude::*,
+    primitives::{PrimitiveStyle, Rectangle},
+    text::Text,
+};
+use heapless::String;
+
+use crate::calibration::civil_from_days;
+use crate::display_config::{BG_COLOR, HIGHLIGHT_COLOR, STATUS_COLOR, TEXT_COLOR};
+use crate::time::TimeOfDay;
+use crate::types::SensorReading;
+use crate::ui::widgets::draw_7segment_number;
+use crate::util::fmt::{push_tenths, round_i32, to_tenths};
+
+// Size of one clock digit and the space left for each colon
+const DIGIT_SIZE: Size = Size::new(16, 30);
+const COLON_WIDTH: i32 = 8;
+const COLON_DOT: Size = Size::new(3, 3);
+
+// Top left corner of the HH:MM:SS row
+const TIME_ORIGIN: Point = Point::new(6, 5);
+
+/// Full screen clock: HH:MM:SS in 7-segment digits on the top half, the
+/// date and the latest reading below
+pub struct ClockPage {
+    date_style: MonoTextStyle<'static, Rgb565>,
+    reading_style: MonoTextStyle<'static, Rgb565>,
+}
+
+impl ClockPage {
+    pub fn new() -> Self {
+        ClockPage {
+            date_style: MonoTextStyleBuilder::new()
+                .font(&FONT_8X13)
+                .text_color(HIGHLIGHT_COLOR)
+                .background_color(BG_COLOR)
+                .build(),
+            reading_style: MonoTextStyleBuilder::new()
+                .font(&FONT_6X10)
+                .text_color(TEXT_COLOR)
+                .background_color(BG_COLOR)
+                .build(),
+        }
+    }
+
+    /// Draws the page for the time `unix_s` (seconds since 1970-01-01).
+    /// The colons are shown on even uptime seconds so they blink once per
+    /// second. `reading` is the latest sensor reading.
+    pub fn draw<D>(&self, lcd: &mut D, unix_s: u32, uptime_s: u32, reading: Option<SensorReading>)
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        let time = TimeOfDay::from_unix_s(unix_s);
+        let fields = [time.hour, time.minute, time.second];
+
+        // Two digits and a colon per field
+        let digit_pitch = DIGIT_SIZE.width as i32 * 5 / 4;
+        let field_pitch = 2 * digit_pitch + COLON_WIDTH;
+        let colons_visible = uptime_s % 2 == 0;
+
+        for (i, &value) in fields.iter().enumerate() {
+            let field_origin = TIME_ORIGIN + Point::new(i as i32 * field_pitch, 0);
+            draw_7segment_number(lcd, value as u32, 2, field_origin, DIGIT_SIZE, STATUS_COLOR);
+
+            if i < fields.len() - 1 {
+                let colon_x = field_origin.x + 2 * digit_pitch + (COLON_WIDTH - 3) / 2 - 2;
+                let color = if colons_visible {
+                    STATUS_COLOR
+                } else {
+                    BG_COLOR
+                };
+                for dot_y in [DIGIT_SIZE.height as i32 / 3, DIGIT_SIZE.height as i32 * 2 / 3].iter() {
+                    Rectangle::new(Point::new(colon_x, TIME_ORIGIN.y + dot_y - 1), COLON_DOT)
+                        .into_styled(PrimitiveStyle::with_fill(color))
+                        .draw(lcd)
+                        .ok();
+                }
+            }
+        }
+
+        let (year, month, day) = civil_from_days(unix_s / 86_400);
+        let mut date: String<10> = String::new();
+        write!(date, "{:02}/{:02}/{:04}", day, month, year).ok();
+        Text::new(date.as_str(), Point::new(40, 50), self.date_style)
+            .draw(lcd)
+            .ok();
+
+        // Padded to overwrite a longer previous reading
+        let mut reading_text: String<16> = String::new();
+        match reading {
+            Some(reading) => {
+                push_tenths(&mut reading_text, to_tenths(reading.temperature)).ok();
+                write!(reading_text, "°C {}%  ", round_i32(reading.humidity)).ok();
+            }
+            None => {
+                reading_text.push_str("--.-°C --%  ").ok();
+            }
+        }
+        Text::new(reading_text.as_str(), Point::new(47, 66), self.reading_style)
+            .draw(lcd)
+            .ok();
+    }
+}
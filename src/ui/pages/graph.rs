@@ -0,0 +1,92 @@
+use embedded_graphics::{
+    pixelcolor::Rgb565,
+    prelude::*,
+    primitives::{Line, PrimitiveStyle},
+};
+
+use crate::display::{clear_region, screen_size};
+use crate::display_config::{GRAPH_GRID_COLOR, GRAPH_LINE_COLOR, GRAPH_PEAK_COLOR};
+use crate::history::{RingBuffer, HISTORY_LEN};
+use crate::types::SensorReading;
+
+// Plot area: full width, 60 px high below a 10 px top margin
+const GRAPH_TOP: i32 = 10;
+const GRAPH_HEIGHT: i32 = 60;
+
+// Margin above and below the measured range, °C
+const RANGE_MARGIN: f32 = 2.0;
+
+// Dashes of the peak line, drawn and skipped pixels
+const PEAK_DASH: i32 = 4;
+const PEAK_GAP: i32 = 3;
+
+/// Draws the temperature history as a line chart scaled to the range of
+/// the data and the peak plus a 2°C margin, with a dashed line at the peak
+/// temperature and a dim line at 0°C when it is in range
+pub fn draw_temperature_graph<D>(
+    lcd: &mut D,
+    history: &RingBuffer<SensorReading, HISTORY_LEN>,
+    peak: Option<f32>,
+) where
+    D: DrawTarget<Color = Rgb565>,
+{
+    let width = screen_size().width as i32;
+
+    clear_region(
+        lcd,
+        Point::new(0, GRAPH_TOP),
+        Size::new(width as u32, GRAPH_HEIGHT as u32),
+    );
+
+    if history.len() < 2 {
+        return;
+    }
+
+    let (min_t, max_t) = history.iter().fold((f32::MAX, f32::MIN), |(lo, hi), r| {
+        (lo.min(r.temperature), hi.max(r.temperature))
+    });
+    // The peak can be older than the history, keep it on the chart
+    let max_t = peak.map_or(max_t, |p| max_t.max(p));
+    let low = min_t - RANGE_MARGIN;
+    let range = max_t + RANGE_MARGIN - low;
+
+    // Higher temperatures are drawn higher up
+    let to_y = |t: f32| {
+        GRAPH_TOP + GRAPH_HEIGHT - 1 - ((t - low) / range * (GRAPH_HEIGHT - 1) as f32) as i32
+    };
+    // Samples spread over the full width, rounded to the nearest pixel
+    let last = history.len() as i32 - 1;
+    let to_x = |i: i32| (i * (width - 1) + last / 2) / last;
+
+    if low < 0.0 && low + range > 0.0 {
+        let y = to_y(0.0);
+        Line::new(Point::new(0, y), Point::new(width - 1, y))
+            .into_styled(PrimitiveStyle::with_stroke(GRAPH_GRID_COLOR, 1))
+            .draw(lcd)
+            .ok();
+    }
+
+    if let Some(peak) = peak {
+        let y = to_y(peak);
+        let dash = PrimitiveStyle::with_stroke(GRAPH_PEAK_COLOR, 1);
+        for x in (0..width).step_by((PEAK_DASH + PEAK_GAP) as usize) {
+            let end = (x + PEAK_DASH - 1).min(width - 1);
+            Line::new(Point::new(x, y), Point::new(end, y))
+                .into_styled(dash)
+                .draw(lcd)
+                .ok();
+        }
+    }
+
+    let style = PrimitiveStyle::with_stroke(GRAPH_LINE_COLOR, 1);
+    let mut points = history
+        .iter()
+        .enumerate()
+        .map(|(i, r)| Point::new(to_x(i as i32), to_y(r.temperature)));
+    if let Some(mut previous) = points.next() {
+        for point in points {
+            Line::new(previous, point).into_styled(style).draw(lcd).ok();
+            previous = point;
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use core::fmt::Write;
+use embedded_graphics::{
+    mono_font::{ascii::FONT_6X10, MonoTextStyleBuilder},
+    pixelcolor::Rgb565,
+    prelude::*,
+};
+use heapless::String;
+
+use crate::display::{layout_point, LcdWriter};
+use crate::dht::quality::SensorHealth;
+use crate::display_config::{BG_COLOR, TEXT_COLOR};
+use crate::metrics::Metrics;
+
+// Characters in one cell, label and count together
+const CELL_WIDTH: usize = 12;
+
+/// Draws the read counters in two columns: successful and failed reads on
+/// the first row, checksum and timeout errors on the second. The sensor
+/// health and the quality (%) of the latest read are on the third row, the
+/// UART framing and parity errors on the fourth once there are any, and
+/// the calibration offsets in use on the last one.
+pub fn draw_metrics<D>(
+    lcd: &mut D,
+    metrics: &Metrics,
+    health: SensorHealth,
+    quality: u8,
+    uart_frame_errors: u32,
+    offsets: &str,
+) where
+    D: DrawTarget<Color = Rgb565>,
+{
+    let style = MonoTextStyleBuilder::new()
+        .font(&FONT_6X10)
+        .text_color(TEXT_COLOR)
+        .background_color(BG_COLOR)
+        .build();
+
+    let cells = [
+        ("OK ", metrics.reads_ok, layout_point(5, 19)),
+        ("FAIL ", metrics.reads_failed, layout_point(85, 19)),
+        ("CRC ", metrics.checksum_errors, layout_point(5, 31)),
+        ("TIMEOUT ", metrics.timeout_errors, layout_point(85, 31)),
+    ];
+
+    for &(label, count, position) in cells.iter() {
+        // Padded to overwrite a longer previous count
+        let mut writer = LcdWriter::new(lcd, style, position);
+        write!(writer, "{}{:<width$}", label, count, width = CELL_WIDTH - label.len()).ok();
+    }
+
+    let mut writer = LcdWriter::new(lcd, style, layout_point(5, 43));
+    write!(writer, "HEALTH {:<width$}", health.label(), width = CELL_WIDTH - 7).ok();
+    let mut percent: String<4> = String::new();
+    write!(percent, "{}%", quality).ok();
+    let mut writer = LcdWriter::new(lcd, style, layout_point(85, 43));
+    write!(writer, "Q:{:<width$}", percent, width = CELL_WIDTH - 2).ok();
+
+    // The count only grows, the row stays empty while there are none
+    if uart_frame_errors > 0 {
+        let mut writer = LcdWriter::new(lcd, style, layout_point(5, 55));
+        write!(writer, "UART FE: {}", uart_frame_errors).ok();
+    }
+
+    // Padded like the cells, an offset can get shorter
+    let mut writer = LcdWriter::new(lcd, style, layout_point(5, 67));
+    write!(writer, "CAL {:<width$}", offsets, width = 2 * CELL_WIDTH - 4).ok();
+}
@@ -0,0 +1,47 @@
+use embedded_graphics::{mono_font::MonoTextStyle, pixelcolor::Rgb565, prelude::*, text::Text};
+use heapless::String;
+
+use crate::display::{layout_point, temperature_unit};
+use crate::display_config::LINE_SPACING;
+use crate::types::SensorReading;
+use crate::util::fmt::{format_i32, push_tenths, to_tenths};
+
+/// Draws the extremes since power-on as `T min max` and `H min max` lines.
+/// Temperatures have one decimal in the selected unit, humidity is in
+/// whole percent.
+pub fn draw_min_max<D>(
+    lcd: &mut D,
+    min: &SensorReading,
+    max: &SensorReading,
+    style: MonoTextStyle<'static, Rgb565>,
+) where
+    D: DrawTarget<Color = Rgb565>,
+{
+    // Padded to overwrite a longer previous line
+    let mut t_line: String<20> = String::new();
+    t_line.push_str("T ").unwrap();
+    let unit = temperature_unit();
+    push_tenths(&mut t_line, to_tenths(unit.convert(min.temperature))).unwrap();
+    t_line.push(' ').unwrap();
+    push_tenths(&mut t_line, to_tenths(unit.convert(max.temperature))).unwrap();
+    while t_line.len() < 15 && t_line.push(' ').is_ok() {}
+    Text::new(t_line.as_str(), layout_point(5, 30), style)
+        .draw(lcd)
+        .ok();
+
+    let mut num_buf = [0u8; 12];
+    let mut h_line: String<20> = String::new();
+    h_line.push_str("H ").unwrap();
+    h_line
+        .push_str(format_i32((to_tenths(min.humidity) + 5) / 10, &mut num_buf))
+        .unwrap();
+    h_line.push_str("% ").unwrap();
+    h_line
+        .push_str(format_i32((to_tenths(max.humidity) + 5) / 10, &mut num_buf))
+        .unwrap();
+    h_line.push('%').unwrap();
+    while h_line.len() < 15 && h_line.push(' ').is_ok() {}
+    Text::new(h_line.as_str(), layout_point(5, 30 + LINE_SPACING), style)
+        .draw(lcd)
+        .ok();
+}
@@ -0,0 +1,91 @@
+use core::sync::atomic::{AtomicU8, Ordering};
+use embedded_graphics::{pixelcolor::Rgb565, prelude::*};
+
+use crate::display::{clear_region, screen_size, DisplayOrientation, DISPLAY_ORIENTATION};
+use crate::display_config::{BODY_REGION, FOOTER_REGION, HEADER_REGION};
+
+pub mod clock;
+pub mod graph;
+pub mod metrics;
+pub mod minmax;
+pub mod raw_data;
+pub mod uptime;
+pub mod vpd;
+
+/// Pages of the main display
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Page {
+    Current,
+    MinMax,
+    Graph,
+    Metrics,
+    Uptime,
+    Vpd,
+    // Bytes of the latest sensor frame, for bring-up
+    RawData,
+    // Time and date once the clock is set with `D`
+    Clock,
+}
+
+// Order the pages are cycled through
+const PAGES: [Page; 8] = [
+    Page::Current,
+    Page::Clock,
+    Page::MinMax,
+    Page::Graph,
+    Page::Vpd,
+    Page::Metrics,
+    Page::Uptime,
+    Page::RawData,
+];
+
+/// Seconds each page stays on screen when cycling automatically
+pub const PAGE_CYCLE_S: u32 = 10;
+
+// Index into PAGES of the page on screen
+static PAGE: AtomicU8 = AtomicU8::new(0);
+
+impl Page {
+    /// Page currently selected
+    pub fn current() -> Page {
+        PAGES[PAGE.load(Ordering::Relaxed) as usize % PAGES.len()]
+    }
+
+    /// Selects the next page, wrapping to the first
+    pub fn advance() {
+        let next = (PAGE.load(Ordering::Relaxed) as usize + 1) % PAGES.len();
+        PAGE.store(next as u8, Ordering::Relaxed);
+    }
+
+    /// Selects the previous page, wrapping to the last
+    #[cfg(feature = "ir_remote")]
+    pub fn retreat() {
+        let previous = (PAGE.load(Ordering::Relaxed) as usize + PAGES.len() - 1) % PAGES.len();
+        PAGE.store(previous as u8, Ordering::Relaxed);
+    }
+
+    /// Bands of the screen the page draws on. The main page's layouts and
+    /// the clock use the whole screen, the other pages only the body.
+    pub fn regions(&self) -> &'static [(Point, Size)] {
+        match self {
+            Page::Current | Page::Clock => &[HEADER_REGION, BODY_REGION, FOOTER_REGION],
+            _ => &[BODY_REGION],
+        }
+    }
+
+    /// Clears the bands the page draws on, the rest of the screen is left
+    /// as it is. The bands are laid out for landscape, in portrait the
+    /// whole screen is cleared.
+    pub fn clear<D>(&self, lcd: &mut D)
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        if DISPLAY_ORIENTATION == DisplayOrientation::Portrait {
+            clear_region(lcd, Point::zero(), screen_size());
+            return;
+        }
+        for &(top_left, size) in self.regions() {
+            clear_region(lcd, top_left, size);
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use core::fmt::Write;
+use embedded_graphics::{
+    mono_font::{ascii::FONT_6X10, MonoTextStyleBuilder},
+    pixelcolor::Rgb565,
+    prelude::*,
+};
+
+use crate::display::{layout_point, LcdWriter};
+use crate::display_config::{BG_COLOR, TEXT_COLOR};
+
+// Bits in a complete frame, the checksum is only checked for those
+const FRAME_BITS: u8 = 40;
+
+/// Draws the latest sensor frame for bring-up on new hardware: the five
+/// data bytes as `D: AA BB CC DD EE`, whether the checksum matched and
+/// how many bits were collected. The bits a frame that ended early is
+/// missing read as zeros.
+pub fn draw_raw_data<D>(lcd: &mut D, bytes: &[u8; 5], bits: u8, checksum_ok: bool)
+where
+    D: DrawTarget<Color = Rgb565>,
+{
+    let style = MonoTextStyleBuilder::new()
+        .font(&FONT_6X10)
+        .text_color(TEXT_COLOR)
+        .background_color(BG_COLOR)
+        .build();
+
+    let checksum = if bits < FRAME_BITS {
+        "--  "
+    } else if checksum_ok {
+        "PASS"
+    } else {
+        "FAIL"
+    };
+
+    let mut writer = LcdWriter::new(lcd, style, layout_point(5, 33));
+    write!(
+        writer,
+        "D: {:02X} {:02X} {:02X} {:02X} {:02X}\n",
+        bytes[0], bytes[1], bytes[2], bytes[3], bytes[4]
+    )
+    .ok();
+    write!(writer, "Checksum: {}\n", checksum).ok();
+    // Padded to overwrite a longer previous count
+    write!(writer, "Bits: {:<2}", bits).ok();
+}
@@ -0,0 +1,37 @@
+use core::fmt::Write;
+use embedded_graphics::{
+    mono_font::{ascii::FONT_6X10, MonoTextStyle, MonoTextStyleBuilder},
+    pixelcolor::Rgb565,
+    prelude::*,
+};
+
+use crate::display::{layout_point, LcdWriter};
+use crate::display_config::{BG_COLOR, TEXT_COLOR};
+
+/// Draws the time since boot in days, hours and minutes, with the update
+/// interval in small text in the bottom right corner
+pub fn draw_uptime<D>(
+    lcd: &mut D,
+    uptime_s: u32,
+    update_interval_s: u32,
+    style: MonoTextStyle<'static, Rgb565>,
+) where
+    D: DrawTarget<Color = Rgb565>,
+{
+    let days = uptime_s / 86_400;
+    let hours = uptime_s % 86_400 / 3600;
+    let minutes = uptime_s % 3600 / 60;
+
+    // Hours and minutes keep their width, so a shorter value never leaves
+    // characters of the previous one behind (e.g. 1d 0h 59m -> 1d 1h 0m)
+    let mut writer = LcdWriter::new(lcd, style, layout_point(5, 45));
+    write!(writer, "Up: {}d {:>2}h {:>2}m", days, hours, minutes).ok();
+
+    let small_style = MonoTextStyleBuilder::new()
+        .font(&FONT_6X10)
+        .text_color(TEXT_COLOR)
+        .background_color(BG_COLOR)
+        .build();
+    let mut writer = LcdWriter::new(lcd, small_style, layout_point(112, 62));
+    write!(writer, "Upd: {:>2}s", update_interval_s).ok();
+}
@@ -0,0 +1,54 @@
+use core::fmt::Write;
+use embedded_graphics::{
+    mono_font::{iso_8859_1::FONT_6X10, MonoTextStyleBuilder},
+    pixelcolor::Rgb565,
+    prelude::*,
+};
+
+use crate::derived_metrics::{absolute_humidity_g_m3, dew_point, vpd_kpa, VpdZone};
+use crate::display::{layout_point, temperature_unit, LcdWriter};
+use crate::display_config::{BG_COLOR, TEXT_COLOR};
+use crate::types::SensorReading;
+use crate::util::fmt::round_i32;
+
+// Characters of the longest zone label
+const ZONE_LABEL_WIDTH: usize = 4;
+
+/// Draws the moisture of the air on three lines: dew point, vapor pressure
+/// deficit as `VPD: 1.23 kPa [OK]` and absolute humidity as `AH: 12.3 g/m³`
+pub fn draw_vpd<D>(lcd: &mut D, reading: &SensorReading)
+where
+    D: DrawTarget<Color = Rgb565>,
+{
+    let style = MonoTextStyleBuilder::new()
+        .font(&FONT_6X10)
+        .text_color(TEXT_COLOR)
+        .background_color(BG_COLOR)
+        .build();
+
+    let vpd = vpd_kpa(reading.temperature, reading.humidity).max(0.0);
+    let zone = VpdZone::from_kpa(vpd);
+    // Hundredths as integers, no float formatting
+    let hundredths = (vpd * 100.0 + 0.5) as u32;
+
+    let unit = temperature_unit();
+    let dp = unit.convert(dew_point(reading.temperature, reading.humidity));
+    let ah = absolute_humidity_g_m3(reading.temperature, reading.humidity);
+    let ah_tenths = (ah * 10.0 + 0.5) as u32;
+
+    // Padded to overwrite a longer previous value
+    let label = zone.label();
+    let mut writer = LcdWriter::new(lcd, style, layout_point(5, 33));
+    write!(writer, "Dp: {}{}  \n", round_i32(dp), unit.suffix()).ok();
+    write!(
+        writer,
+        "VPD: {}.{:02} kPa [{}]{:pad$}\n",
+        hundredths / 100,
+        hundredths % 100,
+        label,
+        "",
+        pad = ZONE_LABEL_WIDTH - label.len()
+    )
+    .ok();
+    write!(writer, "AH: {}.{} g/m³ ", ah_tenths / 10, ah_tenths % 10).ok();
+}
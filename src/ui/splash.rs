@@ -0,0 +1,57 @@
+use embedded_graphics::{
+    mono_font::{ascii::FONT_6X10, MonoTextStyle},
+    pixelcolor::Rgb565,
+    prelude::*,
+    primitives::{PrimitiveStyle, PrimitiveStyleBuilder, Rectangle, StrokeAlignment},
+    text::{Alignment, Baseline, Text, TextStyleBuilder},
+};
+
+use crate::display::screen_size;
+use crate::display_config::{BG_COLOR, HIGHLIGHT_COLOR, LINE_SPACING, TEXT_COLOR, UI_FONT};
+
+// Firmware version from Cargo.toml
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+// Width of the border around the splash screen
+const BORDER_WIDTH: u32 = 4;
+
+/// Boot screen: the title centered inside a white border with the firmware
+/// version below it. Not shown after a watchdog reset, the reset cause is
+/// written to the UART instead.
+pub fn draw_splash<D>(lcd: &mut D)
+where
+    D: DrawTarget<Color = Rgb565>,
+{
+    let size = screen_size();
+    Rectangle::new(Point::new(0, 0), size)
+        .into_styled(PrimitiveStyle::with_fill(BG_COLOR))
+        .draw(lcd)
+        .ok();
+
+    let border_style = PrimitiveStyleBuilder::new()
+        .stroke_color(HIGHLIGHT_COLOR)
+        .stroke_width(BORDER_WIDTH)
+        .stroke_alignment(StrokeAlignment::Inside)
+        .build();
+    Rectangle::new(Point::new(0, 0), size)
+        .into_styled(border_style)
+        .draw(lcd)
+        .ok();
+
+    let centered = TextStyleBuilder::new()
+        .alignment(Alignment::Center)
+        .baseline(Baseline::Middle)
+        .build();
+    let center = Point::new(size.width as i32 / 2, size.height as i32 / 2);
+
+    let title_style = MonoTextStyle::new(&UI_FONT, HIGHLIGHT_COLOR);
+    Text::with_text_style("WEATHER STN", center, title_style, centered)
+        .draw(lcd)
+        .ok();
+
+    let version_style = MonoTextStyle::new(&FONT_6X10, TEXT_COLOR);
+    let version_position = center + Point::new(0, LINE_SPACING * 3 / 4);
+    Text::with_text_style(VERSION, version_position, version_style, centered)
+        .draw(lcd)
+        .ok();
+}
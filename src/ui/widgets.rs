@@ -0,0 +1,68 @@
+use embedded_graphics::{
+    pixelcolor::Rgb565,
+    prelude::*,
+    primitives::{PrimitiveStyle, Rectangle},
+};
+
+use crate::display_config::BG_COLOR;
+
+// Segments a-g lit for each digit, bit 0 is segment a
+static SEGMENT_MAP: [u8; 10] = [
+    0b0111111, 0b0000110, 0b1011011, 0b1001111, 0b1100110, 0b1101101, 0b1111101, 0b0000111,
+    0b1111111, 0b1101111,
+];
+
+/// Draws `value` as `digits` zero-padded 7-segment digits of `digit_size`
+/// starting at `top_left`, with a gap of a quarter digit width between
+/// digits. The area of each digit is cleared first.
+pub fn draw_7segment_number<D>(
+    lcd: &mut D,
+    value: u32,
+    digits: u8,
+    top_left: Point,
+    digit_size: Size,
+    color: Rgb565,
+) where
+    D: DrawTarget<Color = Rgb565>,
+{
+    let w = digit_size.width as i32;
+    let h = digit_size.height as i32;
+    let t = (w / 5).max(1);
+    let half = h / 2;
+    let pitch = w + w / 4;
+
+    // (x, y, width, height) of segments a-g relative to the digit
+    let segments = [
+        (t, 0, w - 2 * t, t),
+        (w - t, t, t, half - t),
+        (w - t, half, t, half - t),
+        (t, h - t, w - 2 * t, t),
+        (0, half, t, half - t),
+        (0, t, t, half - t),
+        (t, half - t / 2, w - 2 * t, t),
+    ];
+
+    let mut remaining = value;
+    for i in (0..digits as i32).rev() {
+        let digit_top_left = top_left + Point::new(i * pitch, 0);
+        let lit = SEGMENT_MAP[(remaining % 10) as usize];
+        remaining /= 10;
+
+        Rectangle::new(digit_top_left, digit_size)
+            .into_styled(PrimitiveStyle::with_fill(BG_COLOR))
+            .draw(lcd)
+            .ok();
+
+        for (n, &(x, y, sw, sh)) in segments.iter().enumerate() {
+            if lit & (1 << n) != 0 {
+                Rectangle::new(
+                    digit_top_left + Point::new(x, y),
+                    Size::new(sw as u32, sh as u32),
+                )
+                .into_styled(PrimitiveStyle::with_fill(color))
+                .draw(lcd)
+                .ok();
+            }
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use core::fmt::{self, Write};
+
+/// Formats an integer into `buf` without going through `core::fmt` and
+/// returns the written digits as a string slice of `buf`
+pub fn format_i32(value: i32, buf: &mut [u8; 12]) -> &str {
+    let mut n = value.unsigned_abs();
+    let mut pos = buf.len();
+
+    // Digits from right to left
+    loop {
+        pos -= 1;
+        buf[pos] = b'0' + (n % 10) as u8;
+        n /= 10;
+        if n == 0 {
+            break;
+        }
+    }
+
+    if value < 0 {
+        pos -= 1;
+        buf[pos] = b'-';
+    }
+
+    // Only ASCII digits and '-' were written
+    unsafe { core::str::from_utf8_unchecked(&buf[pos..]) }
+}
+
+/// Value rounded to the nearest integer, halves away from zero
+pub fn round_i32(value: f32) -> i32 {
+    let offset = if value < 0.0 { -0.5 } else { 0.5 };
+    (value + offset) as i32
+}
+
+/// Value in tenths, rounded to the nearest
+pub fn to_tenths(value: f32) -> i32 {
+    let offset = if value < 0.0 { -0.5 } else { 0.5 };
+    (value * 10.0 + offset) as i32
+}
+
+/// Pushes a value given in tenths as `-12.3` without going through
+/// floating-point formatting
+pub fn push_tenths(text: &mut impl Write, tenths: i32) -> fmt::Result {
+    if tenths < 0 {
+        text.write_char('-')?;
+    }
+    let abs = tenths.unsigned_abs();
+    let mut num_buf = [0u8; 12];
+    text.write_str(format_i32((abs / 10) as i32, &mut num_buf))?;
+    text.write_char('.')?;
+    text.write_char((b'0' + (abs % 10) as u8) as char)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use heapless::String;
+
+    #[test]
+    fn format_i32_matches_write() {
+        for &value in [0, 7, -7, 45, -400, 1000, i32::MAX, i32::MIN].iter() {
+            let mut num_buf = [0u8; 12];
+            let mut expected: String<12> = String::new();
+            write!(expected, "{}", value).unwrap();
+            assert_eq!(format_i32(value, &mut num_buf), expected.as_str());
+        }
+    }
+
+    #[test]
+    fn tenths_keep_the_sign() {
+        let mut text: String<8> = String::new();
+        push_tenths(&mut text, to_tenths(-0.5)).unwrap();
+        assert_eq!(text.as_str(), "-0.5");
+    }
+}
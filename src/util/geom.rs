@@ -0,0 +1,111 @@
+use heapless::Vec;
+
+// Most points a simplified line may have
+const MAX_POINTS: usize = 20;
+
+// Deepest recursion allowed, deeper segments are left as straight lines
+const MAX_DEPTH: u8 = 8;
+
+/// Simplifies a polyline with the Ramer-Douglas-Peucker algorithm,
+/// keeping only points further than `epsilon` from the simplified line.
+/// The first and last points are always kept and the output never has
+/// more than 20 points.
+pub fn rdp_simplify(points: &[(i16, i16)], epsilon: f32, output: &mut Vec<(i16, i16), 20>) {
+    output.clear();
+
+    let (first, last) = match (points.first(), points.last()) {
+        (Some(first), Some(last)) => (*first, *last),
+        _ => return,
+    };
+
+    output.push(first).ok();
+    if points.len() > 1 {
+        simplify_segment(points, epsilon * epsilon, output, 0);
+        output.push(last).ok();
+    }
+}
+
+// Pushes the kept interior points of the segment, in order. The segment
+// includes both end points.
+fn simplify_segment(
+    points: &[(i16, i16)],
+    epsilon_sq: f32,
+    output: &mut Vec<(i16, i16), 20>,
+    depth: u8,
+) {
+    if points.len() < 3 || depth >= MAX_DEPTH {
+        return;
+    }
+
+    let (x0, y0) = points[0];
+    let (x1, y1) = points[points.len() - 1];
+    let (dx, dy) = (x1 as f32 - x0 as f32, y1 as f32 - y0 as f32);
+    let length_sq = dx * dx + dy * dy;
+
+    // Farthest point from the line between the end points, compared as squared distances
+    let mut farthest = 0;
+    let mut farthest_sq = 0.0;
+    for (i, &(x, y)) in points.iter().enumerate().take(points.len() - 1).skip(1) {
+        let (px, py) = (x as f32 - x0 as f32, y as f32 - y0 as f32);
+        let distance_sq = if length_sq == 0.0 {
+            px * px + py * py
+        } else {
+            let cross = dx * py - dy * px;
+            cross * cross / length_sq
+        };
+        if distance_sq > farthest_sq {
+            farthest = i;
+            farthest_sq = distance_sq;
+        }
+    }
+
+    if farthest_sq > epsilon_sq {
+        simplify_segment(&points[..=farthest], epsilon_sq, output, depth + 1);
+        // Leave room for the last point
+        if output.len() < MAX_POINTS - 1 {
+            output.push(points[farthest]).ok();
+        }
+        simplify_segment(&points[farthest..], epsilon_sq, output, depth + 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn triangle_wave_keeps_its_corners() {
+        let mut points = [(0i16, 0i16); 60];
+        for (x, point) in points.iter_mut().enumerate() {
+            *point = (x as i16, 30 - (x as i16 - 30).abs());
+        }
+        let mut output = Vec::new();
+        rdp_simplify(&points, 1.0, &mut output);
+
+        assert_eq!(&output[..], &[(0, 0), (30, 30), (59, 1)]);
+    }
+
+    #[test]
+    fn noise_below_epsilon_is_dropped() {
+        let points = [(0, 10), (1, 11), (2, 10), (3, 9), (4, 10)];
+        let mut output = Vec::new();
+        rdp_simplify(&points, 1.5, &mut output);
+
+        assert_eq!(&output[..], &[(0, 10), (4, 10)]);
+    }
+
+    #[test]
+    fn output_is_capped() {
+        // A zigzag where every point is a corner
+        let mut points = [(0i16, 0i16); 60];
+        for (x, point) in points.iter_mut().enumerate() {
+            *point = (x as i16, if x % 2 == 0 { 0 } else { 10 });
+        }
+        let mut output = Vec::new();
+        rdp_simplify(&points, 1.0, &mut output);
+
+        assert!(output.len() <= 20);
+        assert_eq!(output.first(), Some(&(0, 0)));
+        assert_eq!(output.last(), Some(&(59, 10)));
+    }
+}
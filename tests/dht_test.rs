@@ -0,0 +1,61 @@
+#![cfg(feature = "std")]
+
+use weather_station::dht::{Dht, Dht11, SensorError};
+use weather_station::testing::{response_edges, MockDelay, MockPin, ScriptedCapture};
+use weather_station::types::SensorReading;
+
+// 45% and 23.4°C with its checksum
+const FRAME: [u8; 5] = [45, 0, 23, 4, 72];
+
+#[test]
+fn read_decodes_frame() {
+    let pin = MockPin::new();
+    let driven = pin.driven();
+    let mut dht: Dht<MockPin, MockPin, _, Dht11> =
+        Dht::new(pin, ScriptedCapture::new(response_edges(FRAME, 40)));
+    let mut delay = MockDelay::new();
+
+    assert_eq!(dht.read(&mut delay), Ok(SensorReading::new(23.4, 45.0)));
+
+    // Idle high, the start signal and the release of the line
+    assert_eq!(*driven.borrow(), [true, false, true]);
+    assert_eq!(delay.total_us, 250_000 + 20_000 + 40);
+    let frame = dht.last_frame().unwrap();
+    assert_eq!(frame.data, FRAME);
+    assert_eq!(frame.bit, 40);
+}
+
+#[test]
+fn unplugged_sensor_times_out_and_recovers() {
+    let mut dht: Dht<MockPin, MockPin, _, Dht11> =
+        Dht::new(MockPin::new(), ScriptedCapture::new(Vec::new()));
+    let mut delay = MockDelay::new();
+
+    assert_eq!(
+        dht.read(&mut delay),
+        Err(SensorError::Timeout { at_bit: 0 })
+    );
+    // The pin is back on the output side, the next read starts over
+    assert_eq!(
+        dht.read(&mut delay),
+        Err(SensorError::Timeout { at_bit: 0 })
+    );
+}
+
+#[test]
+fn flipped_bit_fails_the_checksum() {
+    let mut corrupted = FRAME;
+    corrupted[2] ^= 0x20;
+    let mut dht: Dht<MockPin, MockPin, _, Dht11> = Dht::new(
+        MockPin::new(),
+        ScriptedCapture::new(response_edges(corrupted, 40)),
+    );
+
+    assert_eq!(
+        dht.read(&mut MockDelay::new()),
+        Err(SensorError::ChecksumMismatch {
+            expected: 104,
+            got: 72
+        })
+    );
+}
@@ -0,0 +1,132 @@
+use weather_station::filter::{MovingAverage, SavitzkyGolay5};
+use weather_station::types::SensorReading;
+
+#[test]
+fn average_of_partial_window() {
+    let mut filter: MovingAverage<5> = MovingAverage::new();
+    filter.push(SensorReading::new(20.0, 40.0));
+    filter.push(SensorReading::new(22.0, 44.0));
+
+    assert_eq!(filter.average(), SensorReading::new(21.0, 42.0));
+}
+
+#[test]
+fn single_outlier_is_damped() {
+    let mut filter: MovingAverage<5> = MovingAverage::new();
+    for _ in 0..4 {
+        filter.push(SensorReading::new(21.0, 45.0));
+    }
+    // A bit flip turning 21°C into 71°C moves the average by a fifth of the jump
+    filter.push(SensorReading::new(71.0, 45.0));
+
+    let average = filter.average();
+    assert_eq!(average.temperature, 31.0);
+    assert_eq!(average.humidity, 45.0);
+}
+
+#[test]
+fn outlier_leaves_the_window() {
+    let mut filter: MovingAverage<5> = MovingAverage::new();
+    filter.push(SensorReading::new(71.0, 45.0));
+    for _ in 0..5 {
+        filter.push(SensorReading::new(21.0, 45.0));
+    }
+
+    assert_eq!(filter.average(), SensorReading::new(21.0, 45.0));
+}
+
+#[test]
+fn triangle_is_delayed_and_flattened() {
+    let triangle = [
+        20.0, 21.0, 22.0, 23.0, 24.0, 25.0, 24.0, 23.0, 22.0, 21.0, 20.0,
+    ];
+    let mut filter: MovingAverage<5> = MovingAverage::new();
+    let averages: Vec<f32> = triangle
+        .iter()
+        .map(|&temperature| {
+            filter.push(SensorReading::new(temperature, 50.0));
+            filter.average().temperature
+        })
+        .collect();
+
+    // First full window on the rising edge, the peak cut by 1.2°C, and the
+    // falling edge lagging two readings behind the input
+    assert_eq!(averages[4], 22.0);
+    assert_eq!(averages[7], 23.8);
+    assert_eq!(averages[10], 22.0);
+}
+
+#[test]
+fn average_is_zero_before_first_push() {
+    let filter: MovingAverage<5> = MovingAverage::new();
+
+    assert_eq!(filter.average(), SensorReading::zero());
+}
+
+// Reference exponential average with alpha 0.5
+fn ema(samples: &[f32]) -> Vec<f32> {
+    let mut value = samples[0];
+    samples
+        .iter()
+        .map(|&sample| {
+            value += 0.5 * (sample - value);
+            value
+        })
+        .collect()
+}
+
+fn savitzky_golay(samples: &[f32]) -> Vec<f32> {
+    let mut filter = SavitzkyGolay5::new();
+    samples.iter().map(|&sample| filter.apply(sample)).collect()
+}
+
+fn assert_close(actual: f32, expected: f32) {
+    assert!(
+        (actual - expected).abs() < 1e-4,
+        "{} is not {}",
+        actual,
+        expected
+    );
+}
+
+#[test]
+fn raw_samples_until_window_fills() {
+    let mut filter = SavitzkyGolay5::new();
+    for &sample in [20.0, 21.0, 23.0, 22.0].iter() {
+        assert_eq!(filter.apply(sample), sample);
+    }
+}
+
+#[test]
+fn square_pulse_is_smoothed() {
+    let mut input = vec![0.0f32; 5];
+    input.extend([10.0f32; 8].iter());
+    input.extend([0.0f32; 6].iter());
+    let output = savitzky_golay(&input);
+
+    // The edge dips slightly below zero, then climbs through the partial
+    // sums of the kernel
+    assert_close(output[5], -30.0 / 35.0);
+    assert_close(output[6], 90.0 / 35.0);
+    assert_close(output[7], 260.0 / 35.0);
+    assert_close(output[8], 380.0 / 35.0);
+    // The plateau and the baseline after the pulse pass through unchanged
+    assert_close(output[9], 10.0);
+    assert_close(output[12], 10.0);
+    assert_close(output[18], 0.0);
+}
+
+#[test]
+fn sine_amplitude_is_kept_better_than_with_ema() {
+    // Period of 20 samples, peaks compared once the EMA has settled
+    let input: Vec<f32> = (0..100)
+        .map(|i| (i as f32 * 2.0 * core::f32::consts::PI / 20.0).sin())
+        .collect();
+    let peak = |output: Vec<f32>| output[40..].iter().cloned().fold(f32::MIN, f32::max);
+
+    let sg_peak = peak(savitzky_golay(&input));
+    let ema_peak = peak(ema(&input));
+
+    assert!(sg_peak > 0.99, "Savitzky-Golay peak {}", sg_peak);
+    assert!(ema_peak < 0.95, "EMA peak {}", ema_peak);
+}
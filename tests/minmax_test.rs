@@ -0,0 +1,50 @@
+use weather_station::derived_metrics::{comfort_level, ComfortLevel};
+use weather_station::stats::MinMaxTracker;
+use weather_station::types::SensorReading;
+
+#[test]
+fn extremes_come_from_different_readings() {
+    let mut tracker = MinMaxTracker::new();
+    let readings = [
+        SensorReading::new(21.0, 45.0),
+        SensorReading::new(18.5, 60.0),
+        SensorReading::new(24.0, 38.0),
+        SensorReading::new(22.0, 50.0),
+    ];
+    for reading in readings.iter() {
+        tracker.update(reading);
+    }
+
+    let (min, max) = tracker.extremes().unwrap();
+    assert_eq!(min, SensorReading::new(18.5, 38.0));
+    assert_eq!(max, SensorReading::new(24.0, 60.0));
+}
+
+#[test]
+fn reset_starts_over() {
+    let mut tracker = MinMaxTracker::new();
+    assert_eq!(tracker.extremes(), None);
+
+    tracker.update(&SensorReading::new(30.0, 90.0));
+    tracker.reset(Some(SensorReading::new(20.0, 40.0)));
+    tracker.update(&SensorReading::new(21.0, 41.0));
+
+    let (min, max) = tracker.extremes().unwrap();
+    assert_eq!(min, SensorReading::new(20.0, 40.0));
+    assert_eq!(max, SensorReading::new(21.0, 41.0));
+}
+
+#[test]
+fn comfort_thresholds() {
+    assert_eq!(comfort_level(22.0, 45.0), ComfortLevel::Comfortable);
+    assert_eq!(comfort_level(26.0, 60.0), ComfortLevel::Comfortable);
+    assert_eq!(comfort_level(26.1, 45.0), ComfortLevel::Hot);
+    assert_eq!(comfort_level(22.0, 60.1), ComfortLevel::Humid);
+    assert_eq!(comfort_level(22.0, 29.9), ComfortLevel::Dry);
+}
+
+#[test]
+fn heat_takes_precedence_over_humidity() {
+    assert_eq!(comfort_level(30.0, 80.0), ComfortLevel::Hot);
+    assert_eq!(comfort_level(30.0, 20.0), ComfortLevel::Hot);
+}